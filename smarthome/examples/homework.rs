@@ -6,7 +6,7 @@ use smartlib::{
 };
 
 /// Функция, которая печает отчет по выбранному объекту
-fn print_report<T: Report>(reported_obj: &T) {
+fn print_report<T: Report + ?Sized>(reported_obj: &T) {
     println!("{}", reported_obj.report())
 }
 
@@ -23,7 +23,7 @@ fn main() {
         SmartThermometer::new(String::from("KitchenTermo"), TempMeasures::C, 25.0);
     let kitchen_socket1 = SmartElectricalSoket::new(String::from("SocketForMicrowave"), 220.0);
     let mut kitchen_socket2 = SmartElectricalSoket::new(String::from("SocketFreezer"), 220.0);
-    kitchen_socket2.turn_on();
+    kitchen_socket2.turn_on().unwrap();
 
     // Создаем кухню с помощью макроса
     let room = add_room!(
@@ -36,14 +36,22 @@ fn main() {
     let mut room2 = add_room!(String::from("Кухня"));
 
     // Динамическое добавление устройств в комнату.
-    room2.add_device_with_key("S1".to_string(), kitchen_socket1.into());
-    room2.add_device_with_key("S2".to_string(), kitchen_socket2.into());
-    room2.add_device_with_key("T1".to_string(), kitchen_termometer.into());
+    room2
+        .add_device_with_key("S1".to_string(), kitchen_socket1.into())
+        .unwrap();
+    room2
+        .add_device_with_key("S2".to_string(), kitchen_socket2.into())
+        .unwrap();
+    room2
+        .add_device_with_key("T1".to_string(), kitchen_termometer.into())
+        .unwrap();
 
-    let mut smart_home = SmartHome::new("MyHome".to_string(), vec![room]);
+    let mut smart_home = SmartHome::try_new("MyHome".to_string(), vec![room]).unwrap();
 
     // Динамическое добавление комнаты в дом
-    smart_home.add_room_with_key("Кухня".to_string(), room2);
+    smart_home
+        .add_room_with_key("Кухня".to_string(), room2)
+        .unwrap();
 
     // Возможность вызова отчетов для Дома, Комнаты и умного устройства
     print_report(&smart_home);
@@ -74,6 +82,7 @@ fn main() {
         Err(SmartHomeErrors::DeviceNotFound(device)) => {
             eprintln!("❌: устройство '{}' не найдено в указанной комнате", device);
         }
+        Err(e) => eprintln!("❌: Неизвестная ошибка: {}", e),
     }
     // Удаление комнаты
     match smart_home.delete_room("Столовая") {
@@ -0,0 +1,737 @@
+//! HTTP API дома
+//!
+//! Минимальный сервер HTTP/1.1 поверх `std::net`: каждое подключение
+//! обслуживается в отдельном потоке и закрывается после ответа.
+//! Ответы и тела запросов передаются в JSON, отчеты - в выбранном формате.
+//! После каждого изменяющего запроса дом сохраняется в файл.
+//! К устройству можно обращаться и по постоянному идентификатору:
+//! `/devices/{id}/...` равносильно `/rooms/{room}/devices/{key}/...`.
+//! `GET /devices?kind=socket&is_on=true` ищет устройства во всех комнатах: параметры
+//! `kind`, `room` и `name` отбирают по типу, комнате и части имени, остальные -
+//! по значениям полей отчета.
+//! Перенос и переименование: `POST .../devices/{key}/move` с телом `{"room": ...}`,
+//! `POST .../devices/{key}/rename` и `POST /rooms/{room}/rename` с телом `{"key": ...}`.
+//! Сцены: `GET /scenes` возвращает имена сцен, `POST /scenes/{name}/apply` применяет
+//! сцену и возвращает измененные, неизмененные и не примененные устройства.
+
+use serde::Deserialize;
+use smartlib::{
+    DeviceId, Room, SharedHome, SmartDevice, SmartHome,
+    errors::SmartHomeErrors,
+    query::DeviceQuery,
+    report::{DeviceReport, FieldValue, ReportFormat, RoomReport},
+    scenes::{SceneDevice, SceneReport},
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::Device,
+};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Наибольший размер тела запроса
+const MAX_BODY_LEN: usize = 64 * 1024;
+/// Наибольшая длина строки запроса и каждого заголовка
+const MAX_LINE_LEN: usize = 8 * 1024;
+/// Наибольшее число заголовков
+const MAX_HEADERS: usize = 64;
+/// Сколько байт отклоненного запроса дочитывается перед закрытием соединения
+const MAX_DRAIN_LEN: u64 = 1024 * 1024;
+/// Сколько ждать данных от клиента
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+enum ApiError {
+    Home(SmartHomeErrors),
+    BadRequest(String),
+    NotFound,
+    PayloadTooLarge,
+    HeadersTooLarge,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Home(err) => write!(f, "{}", err),
+            Self::BadRequest(reason) => write!(f, "Bad request: {}", reason),
+            Self::NotFound => write!(f, "Not found"),
+            Self::PayloadTooLarge => {
+                write!(f, "Request body is larger than {} bytes", MAX_BODY_LEN)
+            }
+            Self::HeadersTooLarge => write!(
+                f,
+                "Request has more than {} headers or a header longer than {} bytes",
+                MAX_HEADERS, MAX_LINE_LEN
+            ),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+impl From<SmartHomeErrors> for ApiError {
+    fn from(value: SmartHomeErrors) -> Self {
+        Self::Home(value)
+    }
+}
+
+impl ApiError {
+    fn status(&self) -> u16 {
+        match self {
+            Self::Home(
+                SmartHomeErrors::RoomNotFound(_)
+                | SmartHomeErrors::DeviceNotFound(_)
+                | SmartHomeErrors::GroupNotFound(_)
+                | SmartHomeErrors::SceneNotFound(_),
+            ) => 404,
+            Self::Home(SmartHomeErrors::DuplicateRoom(_) | SmartHomeErrors::DuplicateDevice(_)) => {
+                409
+            }
+            Self::Home(
+                SmartHomeErrors::WrongDeviceType { .. }
+                | SmartHomeErrors::InvalidValue { .. }
+                | SmartHomeErrors::Parse(_),
+            ) => 400,
+            Self::Home(SmartHomeErrors::DeviceOffline { .. }) => 503,
+            Self::Home(SmartHomeErrors::Connection { .. }) => 502,
+            Self::Home(_) => 500,
+            Self::BadRequest(_) => 400,
+            Self::NotFound => 404,
+            Self::PayloadTooLarge => 413,
+            Self::HeadersTooLarge => 431,
+        }
+    }
+
+    /// Стабильный код ошибки для клиентов API
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Home(err) => err.code(),
+            Self::BadRequest(_) => "bad_request",
+            Self::NotFound => "not_found",
+            Self::PayloadTooLarge => "payload_too_large",
+            Self::HeadersTooLarge => "headers_too_large",
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: Vec<String>,
+    query: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json<'a, T: Deserialize<'a>>(&'a self) -> Result<T, ApiError> {
+        serde_json::from_slice(&self.body).map_err(|err| ApiError::BadRequest(err.to_string()))
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn no_content() -> Self {
+        Self {
+            status: 204,
+            content_type: "application/json",
+            body: String::new(),
+        }
+    }
+
+    fn report(format: ReportFormat, body: String) -> Self {
+        let content_type = match format {
+            ReportFormat::Text => "text/plain; charset=utf-8",
+            ReportFormat::Json => "application/json",
+            ReportFormat::Csv => "text/csv; charset=utf-8",
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+        };
+        Self {
+            status: 200,
+            content_type,
+            body,
+        }
+    }
+
+    fn error(err: &ApiError) -> Self {
+        Self::json(
+            err.status(),
+            serde_json::json!({ "error": err.to_string(), "code": err.code() }).to_string(),
+        )
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[derive(Deserialize)]
+struct NewRoom {
+    name: String,
+    /// По умолчанию совпадает с именем
+    key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum NewDeviceKind {
+    Socket {
+        name: String,
+        power: f32,
+    },
+    Thermometer {
+        name: String,
+        measure: Option<TempMeasures>,
+        #[serde(default)]
+        tempreture: f32,
+    },
+}
+
+#[derive(Deserialize)]
+struct NewDevice {
+    key: String,
+    #[serde(flatten)]
+    device: NewDeviceKind,
+}
+
+#[derive(Deserialize)]
+struct MoveTo {
+    room: String,
+}
+
+#[derive(Deserialize)]
+struct Rename {
+    key: String,
+}
+
+#[derive(Deserialize, Default)]
+struct SetMeasure {
+    measure: Option<TempMeasures>,
+}
+
+/// Сервер HTTP API
+pub struct Server {
+    listener: TcpListener,
+    home: SharedHome,
+    file: PathBuf,
+    save_lock: Arc<Mutex<()>>,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A, home: SmartHome, file: PathBuf) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            home: SharedHome::new(home),
+            file,
+            save_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Принимает подключения, блокируя текущий поток
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let handler = Handler {
+                home: self.home.clone(),
+                file: self.file.clone(),
+                save_lock: Arc::clone(&self.save_lock),
+            };
+            thread::spawn(move || {
+                // Ошибка одного клиента не должна останавливать сервер
+                let _ = handler.serve(stream);
+            });
+        }
+    }
+}
+
+struct Handler {
+    home: SharedHome,
+    file: PathBuf,
+    save_lock: Arc<Mutex<()>>,
+}
+
+impl Handler {
+    fn serve(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let request = read_request(&mut stream);
+        let rejected = request.is_err();
+        let response = match request {
+            Ok(request) if request.method != "GET" => self.mutate(&request),
+            Ok(request) => self
+                .route(&request)
+                .unwrap_or_else(|err| Response::error(&err)),
+            Err(err) => match err.downcast::<ApiError>() {
+                Ok(err) => Response::error(&err),
+                Err(err) => Response::error(&ApiError::BadRequest(err.to_string())),
+            },
+        };
+        write_response(&mut stream, &response)?;
+        if rejected {
+            // Непрочитанный остаток запроса дочитывается после ответа: если закрыть
+            // сокет с данными во входном буфере, клиент получит сброс вместо ответа
+            stream.shutdown(Shutdown::Write)?;
+            io::copy(&mut (&stream).take(MAX_DRAIN_LEN), &mut io::sink())?;
+        }
+        Ok(())
+    }
+
+    /// Выполняет изменяющий запрос и сохраняет дом. Изменения выполняются по одному;
+    /// если сохранить не удалось, дом возвращается к состоянию до запроса,
+    /// чтобы память не расходилась с файлом, а повтор не применил изменение дважды.
+    fn mutate(&self, request: &Request) -> Response {
+        let _guard = self.save_lock.lock().unwrap_or_else(|err| err.into_inner());
+        let before = self.home.snapshot();
+        match self.route(request) {
+            Ok(response) => match self.home.snapshot().save(&self.file) {
+                Ok(()) => response,
+                Err(err) => {
+                    self.home.restore(before);
+                    Response::error(&err.into())
+                }
+            },
+            Err(err) => Response::error(&err),
+        }
+    }
+
+    /// Заменяет путь `/devices/{id}/...` путем по ключам комнаты и устройства
+    fn resolve_device_id(&self, path: &[String]) -> Result<Vec<String>, ApiError> {
+        let [devices, id, rest @ ..] = path else {
+            return Ok(path.to_vec());
+        };
+        if devices != "devices" {
+            return Ok(path.to_vec());
+        }
+        let id: DeviceId = id.parse().map_err(|_| ApiError::NotFound)?;
+        let (room, device) = self
+            .home
+            .locate_device(id)
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))?;
+        Ok(
+            [String::from("rooms"), room, String::from("devices"), device]
+                .into_iter()
+                .chain(rest.iter().cloned())
+                .collect(),
+        )
+    }
+
+    fn route(&self, request: &Request) -> Result<Response, ApiError> {
+        let path = self.resolve_device_id(&request.path)?;
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        match (request.method.as_str(), path.as_slice()) {
+            ("GET", ["report"]) => {
+                let format = report_format(request)?;
+                Ok(Response::report(format, self.home.report_as(format)))
+            }
+            ("GET", ["rooms"]) => Ok(Response::json(200, self.list_rooms())),
+            ("GET", ["devices"]) => Ok(Response::json(200, self.find_devices(request))),
+            ("GET", ["scenes"]) => Ok(Response::json(
+                200,
+                serde_json::Value::from(self.home.scene_names()).to_string(),
+            )),
+            ("POST", ["scenes", scene, "apply"]) => {
+                let report = self.home.apply_scene(scene)?;
+                Ok(Response::json(200, scene_report_json(&report)))
+            }
+            ("POST", ["rooms"]) => {
+                let NewRoom { name, key } = request.json()?;
+                let key = key.unwrap_or_else(|| name.clone());
+                self.home.add_room_with_key(key.clone(), Room::new(name))?;
+                self.room_json(&key).map(|body| Response::json(201, body))
+            }
+            ("GET", ["rooms", room]) => self.room_json(room).map(|body| Response::json(200, body)),
+            ("DELETE", ["rooms", room]) => {
+                self.home.delete_room(room)?;
+                Ok(Response::no_content())
+            }
+            ("POST", ["rooms", room, "rename"]) => {
+                let Rename { key } = request.json()?;
+                self.home.rename_room(room, key.clone())?;
+                self.room_json(&key).map(|body| Response::json(200, body))
+            }
+            ("GET", ["rooms", room, "report"]) => {
+                let format = report_format(request)?;
+                let body = self
+                    .home
+                    .with_room(room, |value| RoomReport::new(room, value).render(format))?;
+                Ok(Response::report(format, body))
+            }
+            ("GET", ["rooms", room, "devices"]) => {
+                let devices = self.home.with_room(room, |value| {
+                    value
+                        .devices()
+                        .map(|(key, device)| {
+                            DeviceReport::new(key, device)
+                                .with_id(value.device_id(key))
+                                .render_json()
+                        })
+                        .collect::<Vec<String>>()
+                })?;
+                Ok(Response::json(200, format!("[{}]", devices.join(","))))
+            }
+            ("POST", ["rooms", room, "devices"]) => {
+                let NewDevice { key, device } = request.json()?;
+                let device: SmartDevice = match device {
+                    NewDeviceKind::Socket { name, power } => {
+                        SmartElectricalSoket::try_new(name, power)?.into()
+                    }
+                    NewDeviceKind::Thermometer {
+                        name,
+                        measure,
+                        tempreture,
+                    } => {
+                        SmartThermometer::new(name, measure.unwrap_or(TempMeasures::C), tempreture)
+                            .into()
+                    }
+                };
+                self.home.with_room_mut(room, |value| {
+                    value.add_device_with_key(key.clone(), device)
+                })??;
+                self.device_json(room, &key)
+                    .map(|body| Response::json(201, body))
+            }
+            ("GET", ["rooms", room, "devices", device]) => self
+                .device_json(room, device)
+                .map(|body| Response::json(200, body)),
+            ("DELETE", ["rooms", room, "devices", device]) => {
+                self.home
+                    .with_room_mut(room, |value| value.delete_device(device))??;
+                Ok(Response::no_content())
+            }
+            ("POST", ["rooms", room, "devices", device, "move"]) => {
+                let MoveTo { room: to_room } = request.json()?;
+                self.home.move_device(room, &to_room, device)?;
+                self.device_json(&to_room, device)
+                    .map(|body| Response::json(200, body))
+            }
+            ("POST", ["rooms", room, "devices", device, "rename"]) => {
+                let Rename { key } = request.json()?;
+                self.home.rename_device(room, device, key.clone())?;
+                self.device_json(room, &key)
+                    .map(|body| Response::json(200, body))
+            }
+            (
+                "POST",
+                [
+                    "rooms",
+                    room,
+                    "devices",
+                    device,
+                    action @ ("on" | "off" | "switch"),
+                ],
+            ) => {
+                self.update::<SmartElectricalSoket>(
+                    room,
+                    device,
+                    "socket",
+                    |socket| match *action {
+                        "on" => socket.turn_on(),
+                        "off" => {
+                            socket.turn_off();
+                            Ok(())
+                        }
+                        _ => socket.switch(),
+                    },
+                )?;
+                self.device_json(room, device)
+                    .map(|body| Response::json(200, body))
+            }
+            ("POST", ["rooms", room, "devices", device, "measure"]) => {
+                let SetMeasure { measure } = if request.body.is_empty() {
+                    SetMeasure::default()
+                } else {
+                    request.json()?
+                };
+                self.update::<SmartThermometer>(room, device, "thermometer", |thermo| {
+                    match measure {
+                        Some(measure) => thermo.convert_to(measure),
+                        None => thermo.change_measure(),
+                    }
+                    Ok(())
+                })?;
+                self.device_json(room, device)
+                    .map(|body| Response::json(200, body))
+            }
+            _ => Err(ApiError::NotFound),
+        }
+    }
+
+    fn list_rooms(&self) -> String {
+        let rooms: Vec<serde_json::Value> = self
+            .home
+            .room_keys()
+            .into_iter()
+            .filter_map(|key| {
+                let name = self
+                    .home
+                    .with_room(&key, |room| room.get_name().to_string())
+                    .ok()?;
+                Some(serde_json::json!({ "key": key, "name": name }))
+            })
+            .collect();
+        serde_json::Value::from(rooms).to_string()
+    }
+
+    fn find_devices(&self, request: &Request) -> String {
+        let mut query = DeviceQuery::new();
+        for (name, value) in &request.query {
+            query = match name.as_str() {
+                "kind" => query.kind(value),
+                "room" => query.in_room(value),
+                "name" => query.name_contains(value),
+                field => query.field(field, FieldValue::parse(value)),
+            };
+        }
+        let home = self.home.snapshot();
+        let devices: Vec<String> = home
+            .query(&query)
+            .map(|entry| {
+                format!(
+                    "{{\"room\":{},\"device\":{}}}",
+                    serde_json::Value::from(entry.room),
+                    DeviceReport::new(entry.key, entry.device)
+                        .with_id(Some(entry.id))
+                        .render_json()
+                )
+            })
+            .collect();
+        format!("[{}]", devices.join(","))
+    }
+
+    fn room_json(&self, room: &str) -> Result<String, ApiError> {
+        Ok(self.home.with_room(room, |value| {
+            RoomReport::new(room, value).render(ReportFormat::Json)
+        })?)
+    }
+
+    fn device_json(&self, room: &str, device: &str) -> Result<String, ApiError> {
+        Ok(self.home.with_room(room, |value| {
+            value
+                .get_device(device)
+                .map(|found| {
+                    DeviceReport::new(device, found)
+                        .with_id(value.device_id(device))
+                        .render_json()
+                })
+                .ok_or_else(|| SmartHomeErrors::DeviceNotFound(device.to_string()))
+        })??)
+    }
+
+    fn update<T: Device>(
+        &self,
+        room: &str,
+        device: &str,
+        expected: &str,
+        update: impl FnOnce(&mut T) -> Result<(), SmartHomeErrors>,
+    ) -> Result<(), ApiError> {
+        self.home
+            .update_device(room, device, |value| value.downcast_mut::<T>().map(update))?
+            .ok_or_else(|| SmartHomeErrors::wrong_device_type(device, expected))?
+            .map_err(ApiError::from)
+    }
+}
+
+fn scene_report_json(report: &SceneReport) -> String {
+    let devices = |devices: &[SceneDevice]| -> Vec<serde_json::Value> {
+        devices
+            .iter()
+            .map(|device| serde_json::json!({ "id": device.id.get(), "room": device.room, "key": device.key }))
+            .collect()
+    };
+    let failed: Vec<serde_json::Value> = report
+        .failed
+        .iter()
+        .map(|failure| {
+            serde_json::json!({
+                "target": failure.target.to_string(),
+                "error": failure.error.to_string(),
+                "code": failure.error.code(),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "changed": devices(&report.changed),
+        "unchanged": devices(&report.unchanged),
+        "failed": failed,
+    })
+    .to_string()
+}
+
+fn report_format(request: &Request) -> Result<ReportFormat, ApiError> {
+    match request.query("format").unwrap_or("json") {
+        "text" => Ok(ReportFormat::Text),
+        "json" => Ok(ReportFormat::Json),
+        "csv" => Ok(ReportFormat::Csv),
+        "markdown" => Ok(ReportFormat::Markdown),
+        other => Err(ApiError::BadRequest(format!("unknown format {}", other))),
+    }
+}
+
+fn bad_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+/// Читает строку не длиннее `MAX_LINE_LEN`; `None`, если строка длиннее
+fn read_limited_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_LEN as u64 + 1).read_line(&mut line)?;
+    Ok((line.len() <= MAX_LINE_LEN).then_some(line))
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, Box<dyn Error>> {
+    let mut reader = BufReader::new(stream);
+    let Some(line) = read_limited_line(&mut reader)? else {
+        return Err(bad_data("request line is too long").into());
+    };
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_data("malformed request line").into());
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Result<Vec<String>, _>>()?;
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((decode_query_part(key)?, decode_query_part(value)?))
+        })
+        .collect::<Result<Vec<(String, String)>, io::Error>>()?;
+
+    let mut content_length = 0;
+    for count in 0.. {
+        let header = read_limited_line(&mut reader)?.ok_or(ApiError::HeadersTooLarge)?;
+        if header.is_empty() {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse()?;
+        }
+        if count == MAX_HEADERS {
+            return Err(ApiError::HeadersTooLarge.into());
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        return Err(ApiError::PayloadTooLarge.into());
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        query,
+        body,
+    })
+}
+
+/// Декодирует параметр строки запроса, где `+` означает пробел
+fn decode_query_part(value: &str) -> Result<String, io::Error> {
+    percent_decode(&value.replace('+', " "))
+}
+
+/// Декодирует `%XX`; `+` остается как есть, как в сегментах пути
+fn percent_decode(value: &str) -> Result<String, io::Error> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = bytes
+                    .get(index + 1..index + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| bad_data("malformed percent encoding"))?;
+                out.push(hex);
+                index += 3;
+            }
+            byte => {
+                out.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| bad_data("path is not valid UTF-8"))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("%D0%9A%D1%83%D1%85%D0%BD%D1%8F").unwrap(),
+            "Кухня"
+        );
+        assert_eq!(percent_decode("a+b%20c").unwrap(), "a+b c");
+        assert_eq!(decode_query_part("a+b%20c%2B").unwrap(), "a b c+");
+        assert!(percent_decode("%ZZ").is_err());
+        assert!(percent_decode("%FF").is_err());
+    }
+}
@@ -1 +1,657 @@
-fn main() {}
+mod http;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, parser::ValueSource};
+use smartlib::{
+    DeviceId, Room, SmartDevice, SmartHome, SortOrder,
+    errors::SmartHomeErrors,
+    query::DeviceQuery,
+    report::{FieldValue, ReportFormat},
+    scenes::{DeviceState, Scene, SceneTarget},
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::Device,
+};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// Управление умным домом из командной строки
+/// Состояние дома хранится в файле JSON или TOML и сохраняется после каждой изменяющей команды.
+#[derive(Debug, Parser)]
+#[command(name = "smarthome", version)]
+struct Cli {
+    /// Файл с описанием дома, формат определяется по расширению (.json или .toml)
+    #[arg(short, long, global = true, default_value = "smarthome.json")]
+    file: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Создать новый пустой дом, перезаписав файл
+    Init { name: String },
+    /// Напечатать отчет по дому, комнате или устройству
+    Report {
+        #[arg(long)]
+        room: Option<String>,
+        #[arg(long, requires = "room", conflicts_with = "format")]
+        device: Option<String>,
+        /// Отчет по устройству с постоянным идентификатором (есть в отчете JSON)
+        #[arg(long, conflicts_with_all = ["room", "format"])]
+        id: Option<DeviceId>,
+        /// Формат отчета по дому или комнате
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+    /// Операции с комнатами
+    #[command(subcommand)]
+    Room(RoomCommand),
+    /// Операции с устройствами
+    #[command(subcommand)]
+    Device(DeviceCommand),
+    /// Группы устройств
+    #[command(subcommand)]
+    Group(GroupCommand),
+    /// Сцены: желаемые состояния устройств и групп
+    #[command(subcommand)]
+    Scene(SceneCommand),
+    /// Управление розеткой
+    Socket {
+        action: SocketAction,
+        room: String,
+        key: String,
+    },
+    /// Перевести термометр в другие единицы измерения.
+    /// Без `--to` переключает между Цельсием и Фаренгейтом.
+    Measure {
+        room: String,
+        key: String,
+        #[arg(long, value_enum)]
+        to: Option<Measure>,
+    },
+    /// Найти устройства во всех комнатах; условия объединяются через «и»
+    Find {
+        /// Тип устройства: socket или thermometer
+        #[arg(long)]
+        kind: Option<String>,
+        #[arg(long)]
+        room: Option<String>,
+        /// Часть имени устройства без учета регистра
+        #[arg(long)]
+        name: Option<String>,
+        /// Значение поля отчета, например `--field is_on=true`
+        #[arg(long = "field", value_parser = parse_field)]
+        fields: Vec<(String, String)>,
+    },
+    /// Задать порядок комнат и устройств в отчетах и файле дома
+    Sort { order: Order },
+    /// Интерактивный режим: команды читаются построчно из стандартного ввода
+    Shell,
+    /// Запустить HTTP API дома
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: SocketAddr,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RoomCommand {
+    /// Добавить комнату
+    Add { name: String },
+    /// Удалить комнату
+    Remove { name: String },
+    /// Изменить ключ комнаты
+    Rename { name: String, new_name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum DeviceCommand {
+    /// Добавить термометр в комнату
+    AddThermometer {
+        room: String,
+        key: String,
+        name: String,
+        #[arg(long, value_enum, default_value = "c")]
+        measure: Measure,
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        tempreture: f32,
+        /// Заменить устройство, если ключ уже занят
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Добавить розетку в комнату
+    AddSocket {
+        room: String,
+        key: String,
+        name: String,
+        #[arg(long)]
+        power: f32,
+        /// Заменить устройство, если ключ уже занят
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Удалить устройство из комнаты
+    Remove { room: String, key: String },
+    /// Перенести устройство в другую комнату
+    Move {
+        room: String,
+        key: String,
+        to_room: String,
+    },
+    /// Изменить ключ устройства
+    Rename {
+        room: String,
+        key: String,
+        new_key: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum GroupCommand {
+    /// Задать группу, заменив группу с тем же именем
+    Set {
+        name: String,
+        /// Устройство в виде `ROOM/KEY`
+        #[arg(long = "device", value_parser = parse_location)]
+        devices: Vec<(String, String)>,
+        /// Устройство с постоянным идентификатором
+        #[arg(long = "id")]
+        ids: Vec<DeviceId>,
+    },
+    /// Удалить группу
+    Remove { name: String },
+    /// Напечатать группы и их устройства
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum SceneCommand {
+    /// Задать сцену, заменив сцену с тем же именем
+    Set {
+        name: String,
+        /// Действие `TARGET=STATE`: TARGET - `ROOM/KEY` или `@GROUP`,
+        /// STATE - `on`, `off` или единицы термометра `c`, `f`, `k`, `r`
+        #[arg(required = true, value_parser = parse_action)]
+        actions: Vec<(Target, DeviceState)>,
+    },
+    /// Применить сцену и напечатать отчет
+    Apply { name: String },
+    /// Удалить сцену
+    Remove { name: String },
+    /// Напечатать сцены и их действия
+    List,
+}
+
+/// Цель действия сцены в командной строке
+#[derive(Debug, Clone)]
+enum Target {
+    Device(String, String),
+    Group(String),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SocketAction {
+    On,
+    Off,
+    Switch,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl From<Format> for ReportFormat {
+    fn from(value: Format) -> Self {
+        match value {
+            Format::Text => ReportFormat::Text,
+            Format::Json => ReportFormat::Json,
+            Format::Csv => ReportFormat::Csv,
+            Format::Markdown => ReportFormat::Markdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Order {
+    Insertion,
+    Key,
+    Kind,
+}
+
+impl From<Order> for SortOrder {
+    fn from(value: Order) -> Self {
+        match value {
+            Order::Insertion => SortOrder::Insertion,
+            Order::Key => SortOrder::ByKey,
+            Order::Kind => SortOrder::ByKind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Measure {
+    C,
+    F,
+    K,
+    R,
+}
+
+impl From<Measure> for TempMeasures {
+    fn from(value: Measure) -> Self {
+        match value {
+            Measure::C => TempMeasures::C,
+            Measure::F => TempMeasures::F,
+            Measure::K => TempMeasures::K,
+            Measure::R => TempMeasures::R,
+        }
+    }
+}
+
+fn parse_field(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", value))
+}
+
+fn parse_location(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('/')
+        .map(|(room, key)| (room.to_string(), key.to_string()))
+        .ok_or_else(|| format!("expected ROOM/KEY, got '{}'", value))
+}
+
+fn parse_action(value: &str) -> Result<(Target, DeviceState), String> {
+    let (target, state) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected TARGET=STATE, got '{}'", value))?;
+    let target = match target.strip_prefix('@') {
+        Some(group) => Target::Group(group.to_string()),
+        None => {
+            let (room, key) = parse_location(target)?;
+            Target::Device(room, key)
+        }
+    };
+    let state = state
+        .parse()
+        .map_err(|err: SmartHomeErrors| err.to_string())?;
+    Ok((target, state))
+}
+
+/// Постоянный идентификатор устройства `key` в комнате `room`
+fn device_id(home: &SmartHome, room: &str, key: &str) -> Result<DeviceId, SmartHomeErrors> {
+    home.get_room(room)
+        .ok_or_else(|| SmartHomeErrors::RoomNotFound(room.to_string()))?
+        .device_id(key)
+        .ok_or_else(|| SmartHomeErrors::DeviceNotFound(key.to_string()))
+}
+
+/// Описание устройства группы или сцены: `room/key` или идентификатор,
+/// если устройства уже нет в доме
+fn describe_device(home: &SmartHome, id: DeviceId) -> String {
+    match home.locate_device(id) {
+        Some((room, key)) => format!("{}/{}", room, key),
+        None => format!("#{}", id),
+    }
+}
+
+fn room_mut<'a>(home: &'a mut SmartHome, room: &str) -> Result<&'a mut Room, SmartHomeErrors> {
+    home.get_mutable_room(room)
+        .ok_or_else(|| SmartHomeErrors::RoomNotFound(room.to_string()))
+}
+
+fn device_mut<'a, T: Device>(
+    home: &'a mut SmartHome,
+    room: &str,
+    key: &str,
+    expected: &str,
+) -> Result<&'a mut T, SmartHomeErrors> {
+    room_mut(home, room)?
+        .get_mutable_device(key)
+        .ok_or_else(|| SmartHomeErrors::DeviceNotFound(key.to_string()))?
+        .downcast_mut::<T>()
+        .ok_or_else(|| SmartHomeErrors::wrong_device_type(key, expected))
+}
+
+/// Добавляет устройство; с `replace` заменяет устройство с тем же ключом
+fn add_device(
+    room: &mut Room,
+    key: String,
+    device: SmartDevice,
+    replace: bool,
+) -> Result<(), SmartHomeErrors> {
+    if replace {
+        room.upsert_device(key, device).map(|_| ())
+    } else {
+        room.add_device_with_key(key, device)
+    }
+}
+
+/// Выполняет команду над домом.
+/// Возвращает `true`, если дом был изменен и его нужно сохранить.
+fn execute(command: Command, home: &mut SmartHome) -> Result<bool, Box<dyn Error>> {
+    match command {
+        Command::Init { .. } | Command::Shell | Command::Serve { .. } => {
+            return Err("command is not available here".into());
+        }
+        Command::Report {
+            room,
+            device,
+            id,
+            format,
+        } => {
+            let format = format.map_or(ReportFormat::Text, ReportFormat::from);
+            if let Some(id) = id {
+                println!("{}", home.get_device_by_id(id)?.report());
+                return Ok(false);
+            }
+            let report = match (room, device) {
+                (Some(room), Some(device)) => home.get_device_from_room(&room, &device)?.report(),
+                (Some(room), None) => home
+                    .get_room(&room)
+                    .ok_or(SmartHomeErrors::RoomNotFound(room))?
+                    .report_as(format),
+                _ => home.report_as(format),
+            };
+            println!("{}", report);
+            return Ok(false);
+        }
+        Command::Find {
+            kind,
+            room,
+            name,
+            fields,
+        } => {
+            let mut query = DeviceQuery::new();
+            if let Some(kind) = kind {
+                query = query.kind(&kind);
+            }
+            if let Some(room) = room {
+                query = query.in_room(&room);
+            }
+            if let Some(name) = name {
+                query = query.name_contains(&name);
+            }
+            for (field, value) in fields {
+                query = query.field(&field, FieldValue::parse(&value));
+            }
+            let mut found = home.query(&query).peekable();
+            if found.peek().is_none() {
+                println!("Устройства не найдены");
+            }
+            for entry in found {
+                println!(
+                    "{}/{} [{}]: {}",
+                    entry.room, entry.key, entry.id, entry.device
+                );
+            }
+            return Ok(false);
+        }
+        Command::Room(RoomCommand::Add { name }) => {
+            home.add_room(Room::new(name))?;
+        }
+        Command::Room(RoomCommand::Remove { name }) => home.delete_room(&name)?,
+        Command::Room(RoomCommand::Rename { name, new_name }) => {
+            home.rename_room(&name, new_name)?
+        }
+        Command::Device(DeviceCommand::AddThermometer {
+            room,
+            key,
+            name,
+            measure,
+            tempreture,
+            replace,
+        }) => {
+            let thermo = SmartThermometer::new(name, measure.into(), tempreture);
+            add_device(room_mut(home, &room)?, key, thermo.into(), replace)?;
+        }
+        Command::Device(DeviceCommand::AddSocket {
+            room,
+            key,
+            name,
+            power,
+            replace,
+        }) => {
+            let socket = SmartElectricalSoket::try_new(name, power)?;
+            add_device(room_mut(home, &room)?, key, socket.into(), replace)?;
+        }
+        Command::Device(DeviceCommand::Remove { room, key }) => {
+            room_mut(home, &room)?.delete_device(&key)?
+        }
+        Command::Device(DeviceCommand::Move { room, key, to_room }) => {
+            home.move_device(&room, &to_room, &key)?
+        }
+        Command::Device(DeviceCommand::Rename { room, key, new_key }) => {
+            home.rename_device(&room, &key, new_key)?
+        }
+        Command::Group(GroupCommand::Set { name, devices, ids }) => {
+            let mut members = Vec::with_capacity(devices.len() + ids.len());
+            for (room, key) in devices {
+                members.push(device_id(home, &room, &key)?);
+            }
+            members.extend(ids);
+            home.define_group(name, members)?;
+        }
+        Command::Group(GroupCommand::Remove { name }) => home.remove_group(&name)?,
+        Command::Group(GroupCommand::List) => {
+            for (name, devices) in home.groups() {
+                let devices: Vec<String> = devices
+                    .iter()
+                    .map(|id| describe_device(home, *id))
+                    .collect();
+                println!("{}: {}", name, devices.join(", "));
+            }
+            return Ok(false);
+        }
+        Command::Scene(SceneCommand::Set { name, actions }) => {
+            let mut scene = Scene::new();
+            for (target, state) in actions {
+                scene = match target {
+                    Target::Device(room, key) => scene.device(device_id(home, &room, &key)?, state),
+                    Target::Group(group) => scene.group(&group, state),
+                };
+            }
+            home.define_scene(name, scene)?;
+        }
+        Command::Scene(SceneCommand::Apply { name }) => {
+            print!("{}", home.apply_scene(&name)?);
+        }
+        Command::Scene(SceneCommand::Remove { name }) => home.remove_scene(&name)?,
+        Command::Scene(SceneCommand::List) => {
+            for (name, scene) in home.scenes() {
+                let actions: Vec<String> = scene
+                    .actions()
+                    .iter()
+                    .map(|action| {
+                        let target = match &action.target {
+                            SceneTarget::Device(id) => describe_device(home, *id),
+                            SceneTarget::Group(group) => format!("@{}", group),
+                        };
+                        format!("{}={}", target, action.state)
+                    })
+                    .collect();
+                println!("{}: {}", name, actions.join(" "));
+            }
+            return Ok(false);
+        }
+        Command::Socket { action, room, key } => {
+            let socket = device_mut::<SmartElectricalSoket>(home, &room, &key, "socket")?;
+            match action {
+                SocketAction::On => socket.turn_on()?,
+                SocketAction::Off => socket.turn_off(),
+                SocketAction::Switch => socket.switch()?,
+            }
+            println!("{}", socket);
+        }
+        Command::Sort { order } => home.set_sort_order(order.into()),
+        Command::Measure { room, key, to } => {
+            let thermo = device_mut::<SmartThermometer>(home, &room, &key, "thermometer")?;
+            match to {
+                Some(measure) => thermo.convert_to(measure.into()),
+                None => thermo.change_measure(),
+            }
+            println!("{}", thermo);
+        }
+    }
+    Ok(true)
+}
+
+/// Ошибка загрузки файла дома
+#[derive(Debug)]
+struct LoadError {
+    path: PathBuf,
+    source: SmartHomeErrors,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "can't load '{}': {}", self.path.display(), self.source)
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Печатает ошибку; ошибки дома, в том числе вложенные, сопровождаются стабильным кодом
+fn print_error(err: &(dyn Error + 'static)) {
+    let home_err = std::iter::successors(Some(err), |err| (*err).source())
+        .find_map(|err| err.downcast_ref::<SmartHomeErrors>());
+    match home_err {
+        Some(home_err) => eprintln!("❌ [{}]: {}", home_err.code(), err),
+        None => eprintln!("❌: {}", err),
+    }
+}
+
+/// Делит строку интерактивного режима на аргументы. Аргумент с пробелами
+/// заключается в двойные или одинарные кавычки, `\` экранирует следующий символ.
+fn split_args(line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (None | Some('"'), '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or("nothing to escape at the end of the line")?;
+                current.get_or_insert_default().push(escaped);
+            }
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                current.get_or_insert_default();
+            }
+            (None, ch) if ch.is_whitespace() => args.extend(current.take()),
+            (_, ch) => current.get_or_insert_default().push(ch),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Интерактивный режим: каждая строка разбирается как аргументы командной строки.
+/// Файл дома задается при запуске, `--file` в строке не допускается.
+fn shell(cli_file: PathBuf, home: &mut SmartHome) -> Result<(), Box<dyn Error>> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        print!("> ");
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let args = match split_args(line.trim_end_matches(['\r', '\n'])) {
+            Ok(args) => args,
+            Err(err) => {
+                print_error(err.as_ref());
+                continue;
+            }
+        };
+        match args
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .as_slice()
+        {
+            [] => continue,
+            ["exit" | "quit"] => return Ok(()),
+            _ => {}
+        }
+        let matches = match Cli::command()
+            .try_get_matches_from(std::iter::once(String::from("smarthome")).chain(args))
+        {
+            Ok(matches) => matches,
+            Err(err) => {
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+        if matches.value_source("file") == Some(ValueSource::CommandLine) {
+            eprintln!("❌: --file can't be changed in the shell");
+            continue;
+        }
+        let cli = Cli::from_arg_matches(&matches)?;
+        match execute(cli.command, home) {
+            Ok(true) => home.save(&cli_file)?,
+            Ok(false) => {}
+            Err(err) => print_error(err.as_ref()),
+        }
+    }
+}
+
+/// Запускает HTTP API и обслуживает запросы до завершения процесса
+fn serve(addr: SocketAddr, cli_file: PathBuf, home: SmartHome) -> Result<(), Box<dyn Error>> {
+    let server = http::Server::bind(addr, home, cli_file)?;
+    let mut stdout = io::stdout();
+    writeln!(stdout, "Listening on http://{}", server.local_addr()?)?;
+    stdout.flush()?;
+    server.run();
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    if let Command::Init { name } = cli.command {
+        return Ok(SmartHome::try_new(name, vec![])?.save(&cli.file)?);
+    }
+    let mut home = SmartHome::load(&cli.file).map_err(|source| LoadError {
+        path: cli.file.clone(),
+        source,
+    })?;
+    match cli.command {
+        Command::Shell => return shell(cli.file, &mut home),
+        Command::Serve { addr } => return serve(addr, cli.file, home),
+        _ => {}
+    }
+    if execute(cli.command, &mut home)? {
+        home.save(&cli.file)?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            print_error(err.as_ref());
+            ExitCode::FAILURE
+        }
+    }
+}
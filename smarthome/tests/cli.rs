@@ -0,0 +1,347 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn home_file(test_name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("smarthome-cli-{}", test_name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn smarthome(file: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_smarthome"))
+        .arg("--file")
+        .arg(file)
+        .args(args)
+        .output()
+        .expect("failed to run smarthome")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn builds_home_and_prints_report() {
+    let file = home_file("report.json");
+    assert!(smarthome(&file, &["init", "MyHome"]).status.success());
+    assert!(smarthome(&file, &["room", "add", "Кухня"]).status.success());
+    let added = smarthome(
+        &file,
+        &[
+            "device",
+            "add-socket",
+            "Кухня",
+            "S1",
+            "Freezer",
+            "--power",
+            "220",
+        ],
+    );
+    assert!(added.status.success());
+    let added = smarthome(
+        &file,
+        &[
+            "device",
+            "add-thermometer",
+            "Кухня",
+            "T1",
+            "KitchenTermo",
+            "--tempreture",
+            "-5",
+        ],
+    );
+    assert!(added.status.success());
+
+    let switched = smarthome(&file, &["socket", "on", "Кухня", "S1"]);
+    assert!(stdout(&switched).contains("включена, мощность 220.0 Вт"));
+    let measured = smarthome(&file, &["measure", "Кухня", "T1"]);
+    assert!(stdout(&measured).contains("23° F"));
+    let measured = smarthome(&file, &["measure", "Кухня", "T1", "--to", "k"]);
+    assert!(stdout(&measured).contains("268.15 K"));
+    let measured = smarthome(&file, &["measure", "Кухня", "T1", "--to", "f"]);
+    assert!(stdout(&measured).contains("23° F"));
+
+    let report = stdout(&smarthome(&file, &["report"]));
+    assert!(report.contains("Отчет для дома: MyHome"));
+    assert!(report.contains("Комната 'Кухня'"));
+    assert!(report.contains("Розетка 'Freezer': включена"));
+    assert!(report.contains("Термометр 'KitchenTermo', Температура: 23° F"));
+
+    let json = stdout(&smarthome(&file, &["report", "--format", "json"]));
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let id = json["rooms"][0]["devices"][1]["id"].to_string();
+    let by_id = stdout(&smarthome(&file, &["report", "--id", &id]));
+    assert!(by_id.contains("Термометр 'KitchenTermo'"));
+
+    assert!(
+        smarthome(&file, &["device", "remove", "Кухня", "S1"])
+            .status
+            .success()
+    );
+    let report = stdout(&smarthome(&file, &["report", "--room", "Кухня"]));
+    assert!(!report.contains("Freezer"));
+}
+
+#[test]
+fn reports_errors_with_failure_status() {
+    let file = home_file("errors.toml");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+
+    let missing = smarthome(&file, &["room", "remove", "Nope"]);
+    assert!(!missing.status.success());
+    assert!(
+        String::from_utf8_lossy(&missing.stderr).contains("[room_not_found]: Room Nope not found")
+    );
+
+    assert!(smarthome(&file, &["room", "add", "Зал"]).status.success());
+    smarthome(&file, &["device", "add-thermometer", "Зал", "T", "Termo"]);
+    let wrong_type = smarthome(&file, &["socket", "on", "Зал", "T"]);
+    assert!(!wrong_type.status.success());
+    assert!(String::from_utf8_lossy(&wrong_type.stderr).contains("[wrong_device_type]"));
+    let negative = smarthome(
+        &file,
+        &["device", "add-socket", "Зал", "S", "Lamp", "--power=-5"],
+    );
+    assert!(String::from_utf8_lossy(&negative.stderr).contains("[invalid_value]"));
+
+    let duplicate = smarthome(&file, &["room", "add", "Зал"]);
+    assert!(!duplicate.status.success());
+    assert!(String::from_utf8_lossy(&duplicate.stderr).contains("[duplicate_room]"));
+    let duplicate = smarthome(
+        &file,
+        &["device", "add-socket", "Зал", "T", "Lamp", "--power", "60"],
+    );
+    assert!(String::from_utf8_lossy(&duplicate.stderr).contains("[duplicate_device]"));
+    let replaced = smarthome(
+        &file,
+        &[
+            "device",
+            "add-socket",
+            "Зал",
+            "T",
+            "Lamp",
+            "--power",
+            "60",
+            "--replace",
+        ],
+    );
+    assert!(replaced.status.success());
+    assert!(stdout(&smarthome(&file, &["report"])).contains("Розетка 'Lamp'"));
+}
+
+#[test]
+fn moves_and_renames_devices() {
+    let file = home_file("move.json");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+    smarthome(&file, &["room", "add", "Кухня"]);
+    smarthome(&file, &["room", "add", "Зал"]);
+    smarthome(
+        &file,
+        &[
+            "device",
+            "add-socket",
+            "Кухня",
+            "S",
+            "Lamp",
+            "--power",
+            "60",
+        ],
+    );
+    smarthome(&file, &["socket", "on", "Кухня", "S"]);
+
+    let moved = smarthome(&file, &["device", "move", "Кухня", "S", "Зал"]);
+    assert!(moved.status.success());
+    let renamed = smarthome(&file, &["device", "rename", "Зал", "S", "L1"]);
+    assert!(renamed.status.success());
+    assert!(
+        smarthome(&file, &["room", "rename", "Зал", "Гостиная"])
+            .status
+            .success()
+    );
+    let report = stdout(&smarthome(
+        &file,
+        &["report", "--room", "Гостиная", "--device", "L1"],
+    ));
+    assert!(report.contains("Розетка 'Lamp': включена"));
+
+    let missing = smarthome(&file, &["device", "move", "Кухня", "S", "Гостиная"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("[device_not_found]"));
+    let taken = smarthome(&file, &["room", "rename", "Гостиная", "Кухня"]);
+    assert!(String::from_utf8_lossy(&taken.stderr).contains("[duplicate_room]"));
+}
+
+#[test]
+fn finds_devices_across_rooms() {
+    let file = home_file("find.json");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+    for room in ["Кухня", "Зал"] {
+        smarthome(&file, &["room", "add", room]);
+        smarthome(
+            &file,
+            &["device", "add-socket", room, "S", "Lamp", "--power", "60"],
+        );
+        smarthome(&file, &["device", "add-thermometer", room, "T", "Termo"]);
+    }
+    smarthome(&file, &["socket", "on", "Зал", "S"]);
+
+    let found = stdout(&smarthome(
+        &file,
+        &["find", "--kind", "socket", "--field", "is_on=true"],
+    ));
+    assert_eq!(found.lines().count(), 1);
+    assert!(found.starts_with("Зал/S ["));
+    let found = stdout(&smarthome(&file, &["find", "--name", "term"]));
+    assert_eq!(found.lines().count(), 2);
+    let found = stdout(&smarthome(&file, &["find", "--room", "Нет"]));
+    assert!(found.contains("Устройства не найдены"));
+    assert!(
+        !smarthome(&file, &["find", "--field", "is_on"])
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn defines_and_applies_scenes() {
+    let file = home_file("scenes.json");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+    assert!(smarthome(&file, &["room", "add", "Зал"]).status.success());
+    let lamp = ["device", "add-socket", "Зал", "S", "Lamp", "--power", "60"];
+    assert!(smarthome(&file, &lamp).status.success());
+    let termo = ["device", "add-thermometer", "Зал", "T", "Termo"];
+    assert!(smarthome(&file, &termo).status.success());
+
+    let group = ["group", "set", "lights", "--device", "Зал/S"];
+    assert!(smarthome(&file, &group).status.success());
+    assert_eq!(
+        stdout(&smarthome(&file, &["group", "list"])),
+        "lights: Зал/S\n"
+    );
+    let scene = ["scene", "set", "night", "@lights=off", "Зал/T=f"];
+    assert!(smarthome(&file, &scene).status.success());
+    let scene = ["scene", "set", "evening", "@lights=on"];
+    assert!(smarthome(&file, &scene).status.success());
+    assert_eq!(
+        stdout(&smarthome(&file, &["scene", "list"])),
+        "night: @lights=off Зал/T=f\nevening: @lights=on\n"
+    );
+
+    let applied = stdout(&smarthome(&file, &["scene", "apply", "evening"]));
+    assert!(applied.starts_with("Изменено: 1, без изменений: 0, ошибок: 0"));
+    assert!(applied.contains("Зал/S: изменено"));
+    // Группы ссылаются на устройства по идентификатору и переживают перенос
+    assert!(smarthome(&file, &["room", "add", "Кухня"]).status.success());
+    assert!(
+        smarthome(&file, &["device", "move", "Зал", "S", "Кухня"])
+            .status
+            .success()
+    );
+    let applied = stdout(&smarthome(&file, &["scene", "apply", "night"]));
+    assert!(applied.starts_with("Изменено: 2, без изменений: 0, ошибок: 0"));
+    assert!(applied.contains("Кухня/S: изменено"));
+
+    let missing = smarthome(&file, &["scene", "set", "bad", "@nope=on"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("[group_not_found]"));
+    assert!(
+        !smarthome(&file, &["scene", "set", "bad", "Зал/T=hot"])
+            .status
+            .success()
+    );
+    assert!(
+        smarthome(&file, &["scene", "remove", "night"])
+            .status
+            .success()
+    );
+    let missing = smarthome(&file, &["scene", "apply", "night"]);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("[scene_not_found]"));
+}
+
+fn run_shell(file: &PathBuf, input: &str) -> Output {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_smarthome"))
+        .arg("--file")
+        .arg(file)
+        .arg("shell")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn shell_executes_commands_from_stdin() {
+    let file = home_file("shell.json");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+    let output = run_shell(
+        &file,
+        "room add Зал\ndevice add-socket Зал S Lamp --power 60\nreport\n",
+    );
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Розетка 'Lamp'"));
+    // Изменения из интерактивного режима сохраняются в файл
+    assert!(stdout(&smarthome(&file, &["report"])).contains("Комната 'Зал'"));
+}
+
+#[test]
+fn shell_supports_quoted_keys_and_rejects_file() {
+    let file = home_file("shell-quotes.json");
+    let other = home_file("shell-other.json");
+    assert!(smarthome(&file, &["init", "H"]).status.success());
+    let output = run_shell(
+        &file,
+        concat!(
+            "room add \"Living room\"\n",
+            "device add-socket 'Living room' Floor\\ lamp Lamp --power 60\n",
+            "room add 'Broken\n",
+            "room add Hall --file shell-other.json\n",
+            "report --room \"Living room\"\n",
+        ),
+    );
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Розетка 'Lamp'"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unterminated quote"));
+    assert!(stderr.contains("--file can't be changed in the shell"));
+
+    let report = stdout(&smarthome(&file, &["report", "--format", "json"]));
+    assert!(report.contains("Living room"));
+    assert!(report.contains("Floor lamp"));
+    assert!(!report.contains("Hall"));
+    assert!(!other.exists());
+}
+
+#[test]
+fn rejects_unknown_file_format() {
+    let file = home_file("format.txt");
+    let output = smarthome(&file, &["init", "H"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unsupported file format"));
+}
+
+#[test]
+fn load_errors_keep_stable_codes() {
+    let file = home_file("load.json");
+    std::fs::write(&file, r#"{"version": 99, "name": "H"}"#).unwrap();
+    let output = smarthome(&file, &["report"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[unsupported_version]"));
+    assert!(stderr.contains("can't load"));
+
+    std::fs::write(&file, "{").unwrap();
+    let stderr = String::from_utf8_lossy(&smarthome(&file, &["report"]).stderr).to_string();
+    assert!(stderr.contains("[parse]"));
+    std::fs::write(&file, r#"{"version": 1}"#).unwrap();
+    let stderr = String::from_utf8_lossy(&smarthome(&file, &["report"]).stderr).to_string();
+    assert!(stderr.contains("[malformed_file]"));
+}
@@ -0,0 +1,389 @@
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+/// Сервер, запущенный на свободном порту; процесс завершается вместе с тестом
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn home_file(test_name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("smarthome-http-{}", test_name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn smarthome(file: &PathBuf, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_smarthome"))
+        .arg("--file")
+        .arg(file)
+        .args(args)
+        .status()
+        .expect("failed to run smarthome");
+    assert!(status.success());
+}
+
+fn start(file: &PathBuf) -> Server {
+    smarthome(file, &["init", "MyHome"]);
+    serve(file)
+}
+
+/// Запускает сервер для уже созданного файла дома
+fn serve(file: &PathBuf) -> Server {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_smarthome"))
+        .arg("--file")
+        .arg(file)
+        .args(["serve", "--addr", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run smarthome");
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on http://")
+        .expect("server did not start")
+        .to_string();
+    Server { child, addr }
+}
+
+/// Отправляет запрос и возвращает код ответа и тело
+fn request(server: &Server, method: &str, path: &str, body: Option<&str>) -> (u16, String) {
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    let body = body.unwrap_or_default();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        server.addr,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+fn json(body: &str) -> Value {
+    serde_json::from_str(body).unwrap()
+}
+
+#[test]
+fn manages_home_over_http() {
+    let file = home_file("crud.json");
+    let server = start(&file);
+
+    let (status, body) = request(&server, "POST", "/rooms", Some(r#"{"name":"Кухня"}"#));
+    assert_eq!(status, 201);
+    assert_eq!(json(&body)["key"], "Кухня");
+    let kitchen = "/rooms/%D0%9A%D1%83%D1%85%D0%BD%D1%8F";
+
+    let socket = r#"{"key":"S1","kind":"socket","name":"Kettle","power":2000}"#;
+    let (status, _) = request(
+        &server,
+        "POST",
+        &format!("{}/devices", kitchen),
+        Some(socket),
+    );
+    assert_eq!(status, 201);
+    let thermo = r#"{"key":"T1","kind":"thermometer","name":"Termo","tempreture":20}"#;
+    let (status, _) = request(
+        &server,
+        "POST",
+        &format!("{}/devices", kitchen),
+        Some(thermo),
+    );
+    assert_eq!(status, 201);
+
+    let (status, body) = request(&server, "POST", &format!("{}/devices/S1/on", kitchen), None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["fields"]["is_on"], true);
+    let (_, body) = request(
+        &server,
+        "POST",
+        &format!("{}/devices/S1/switch", kitchen),
+        None,
+    );
+    assert_eq!(json(&body)["fields"]["is_on"], false);
+
+    let (status, body) = request(
+        &server,
+        "POST",
+        &format!("{}/devices/T1/measure", kitchen),
+        Some(r#"{"measure":"F"}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["status"], "68° F");
+
+    // Устройство доступно и по постоянному идентификатору
+    let id = json(&body)["id"].as_u64().unwrap();
+    let (status, body) = request(&server, "GET", &format!("/devices/{}", id), None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["key"], "T1");
+    let (status, body) = request(
+        &server,
+        "POST",
+        &format!("/devices/{}/measure", id),
+        Some(r#"{"measure":"C"}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["status"], "20° C");
+    let (status, _) = request(&server, "GET", "/devices/999999", None);
+    assert_eq!(status, 404);
+    let (_, body) = request(
+        &server,
+        "POST",
+        &format!("/devices/{}/measure", id),
+        Some(r#"{"measure":"F"}"#),
+    );
+    assert_eq!(json(&body)["status"], "68° F");
+
+    let (status, body) = request(&server, "GET", &format!("{}/devices", kitchen), None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body).as_array().unwrap().len(), 2);
+    let (_, body) = request(&server, "GET", "/rooms", None);
+    assert_eq!(json(&body), json(r#"[{"key":"Кухня","name":"Кухня"}]"#));
+
+    let (status, body) = request(&server, "GET", "/devices?kind=socket&is_on=false", None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)[0]["room"], "Кухня");
+    assert_eq!(json(&body)[0]["device"]["key"], "S1");
+    let (_, body) = request(&server, "GET", "/devices?name=TERMO", None);
+    assert_eq!(json(&body).as_array().unwrap().len(), 1);
+
+    let (status, body) = request(&server, "GET", "/report?format=text", None);
+    assert_eq!(status, 200);
+    assert!(body.contains("Отчет для дома: MyHome"));
+
+    // Перенос и переименование сохраняют идентификатор устройства
+    request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    let (status, body) = request(
+        &server,
+        "POST",
+        &format!("/devices/{}/move", id),
+        Some(r#"{"room":"Hall"}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["id"], id);
+    let (status, body) = request(
+        &server,
+        "POST",
+        "/rooms/Hall/devices/T1/rename",
+        Some(r#"{"key":"T2"}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["key"], "T2");
+    let (status, body) = request(
+        &server,
+        "POST",
+        "/rooms/Hall/rename",
+        Some(r#"{"key":"Холл"}"#),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["key"], "Холл");
+    let (status, _) = request(
+        &server,
+        "POST",
+        &format!("{}/devices/S1/move", kitchen),
+        Some(r#"{"room":"Nope"}"#),
+    );
+    assert_eq!(status, 404);
+    let (status, _) = request(
+        &server,
+        "POST",
+        &format!("{}/rename", kitchen),
+        Some(r#"{"key":"Холл"}"#),
+    );
+    assert_eq!(status, 409);
+
+    let (status, _) = request(&server, "DELETE", &format!("{}/devices/S1", kitchen), None);
+    assert_eq!(status, 204);
+
+    // Изменения сохраняются в файл дома
+    let saved = std::fs::read_to_string(&file).unwrap();
+    assert!(saved.contains("Termo"));
+    assert!(!saved.contains("Kettle"));
+}
+
+#[test]
+fn reports_errors_as_json() {
+    let file = home_file("errors.json");
+    let server = start(&file);
+    request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    let socket = r#"{"key":"S1","kind":"socket","name":"Lamp","power":60}"#;
+    request(&server, "POST", "/rooms/Hall/devices", Some(socket));
+
+    let (status, body) = request(&server, "GET", "/rooms/Nope", None);
+    assert_eq!(status, 404);
+    assert!(json(&body)["error"].as_str().unwrap().contains("Nope"));
+    assert_eq!(json(&body)["code"], "room_not_found");
+    let (status, _) = request(&server, "GET", "/rooms/Hall/devices/Nope", None);
+    assert_eq!(status, 404);
+    let (status, _) = request(&server, "DELETE", "/rooms/Nope", None);
+    assert_eq!(status, 404);
+    let (status, _) = request(&server, "GET", "/unknown", None);
+    assert_eq!(status, 404);
+
+    let (status, body) = request(&server, "POST", "/rooms/Hall/devices/S1/measure", None);
+    assert_eq!(status, 400);
+    assert_eq!(json(&body)["code"], "wrong_device_type");
+    let negative = r#"{"key":"S2","kind":"socket","name":"Lamp","power":-1}"#;
+    let (status, body) = request(&server, "POST", "/rooms/Hall/devices", Some(negative));
+    assert_eq!(status, 400);
+    assert_eq!(json(&body)["code"], "invalid_value");
+    let (status, body) = request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    assert_eq!(status, 409);
+    assert_eq!(json(&body)["code"], "duplicate_room");
+    let (status, _) = request(&server, "POST", "/rooms/Hall/devices", Some(socket));
+    assert_eq!(status, 409);
+    let (status, _) = request(&server, "POST", "/rooms", Some("{"));
+    assert_eq!(status, 400);
+    let (status, _) = request(&server, "GET", "/report?format=pdf", None);
+    assert_eq!(status, 400);
+
+    // Слишком большое тело отклоняется до чтения
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    write!(
+        stream,
+        "POST /rooms HTTP/1.1\r\nHost: {}\r\nContent-Length: 1000000\r\n\r\n",
+        server.addr
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    assert!(response.contains("payload_too_large"));
+
+    // Длина строки запроса и заголовков тоже ограничена
+    let raw = |request: String| {
+        let mut stream = TcpStream::connect(&server.addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = raw(format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(16 * 1024)));
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    let response = raw(format!(
+        "GET /rooms HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+        "a".repeat(16 * 1024)
+    ));
+    assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+    assert!(response.contains("headers_too_large"));
+    let headers: String = (0..100)
+        .map(|index| format!("X-{}: 1\r\n", index))
+        .collect();
+    let response = raw(format!("GET /rooms HTTP/1.1\r\n{}\r\n", headers));
+    assert!(response.starts_with("HTTP/1.1 431"));
+}
+
+#[test]
+fn plus_in_path_is_not_a_space() {
+    let file = home_file("plus.json");
+    let server = start(&file);
+    request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    let socket = r#"{"key":"A+B","kind":"socket","name":"Lamp","power":60}"#;
+    let (status, _) = request(&server, "POST", "/rooms/Hall/devices", Some(socket));
+    assert_eq!(status, 201);
+
+    let (status, body) = request(&server, "GET", "/rooms/Hall/devices/A+B", None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body)["name"], "Lamp");
+    let (status, _) = request(&server, "GET", "/rooms/Hall/devices/A%2BB", None);
+    assert_eq!(status, 200);
+    let (status, _) = request(&server, "GET", "/rooms/Hall/devices/A%20B", None);
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn applies_scenes() {
+    let file = home_file("scenes.json");
+    smarthome(&file, &["init", "MyHome"]);
+    smarthome(&file, &["room", "add", "Hall"]);
+    smarthome(
+        &file,
+        &[
+            "device",
+            "add-socket",
+            "Hall",
+            "S1",
+            "Lamp",
+            "--power",
+            "60",
+        ],
+    );
+    smarthome(&file, &["device", "add-thermometer", "Hall", "T1", "Termo"]);
+    smarthome(
+        &file,
+        &[
+            "group", "set", "all", "--device", "Hall/S1", "--device", "Hall/T1",
+        ],
+    );
+    smarthome(&file, &["scene", "set", "evening", "Hall/S1=on"]);
+    smarthome(&file, &["scene", "set", "broken", "@all=off"]);
+    let server = serve(&file);
+
+    let (status, body) = request(&server, "GET", "/scenes", None);
+    assert_eq!(status, 200);
+    assert_eq!(json(&body), serde_json::json!(["evening", "broken"]));
+
+    let (status, body) = request(&server, "POST", "/scenes/evening/apply", None);
+    assert_eq!(status, 200);
+    let report = json(&body);
+    assert_eq!(report["changed"][0]["room"], "Hall");
+    assert_eq!(report["changed"][0]["key"], "S1");
+    assert!(report["changed"][0]["id"].is_u64());
+    assert_eq!(report["failed"], serde_json::json!([]));
+    let (_, body) = request(&server, "GET", "/rooms/Hall/devices/S1", None);
+    assert_eq!(json(&body)["fields"]["is_on"], true);
+
+    let (_, body) = request(&server, "POST", "/scenes/evening/apply", None);
+    assert_eq!(json(&body)["unchanged"][0]["key"], "S1");
+
+    // Ошибка одного устройства не мешает остальным
+    let (status, body) = request(&server, "POST", "/scenes/broken/apply", None);
+    assert_eq!(status, 200);
+    let report = json(&body);
+    assert_eq!(report["changed"][0]["key"], "S1");
+    assert_eq!(report["failed"][0]["code"], "wrong_device_type");
+
+    let (status, body) = request(&server, "POST", "/scenes/nope/apply", None);
+    assert_eq!(status, 404);
+    assert_eq!(json(&body)["code"], "scene_not_found");
+}
+
+#[test]
+fn failed_save_rolls_back_changes() {
+    let file = home_file("rollback.json");
+    let server = start(&file);
+    // Временный файл сохранения не создать, пока на его месте каталог
+    let blocker = file.with_file_name("smarthome-http-rollback.json.tmp");
+    std::fs::create_dir_all(&blocker).unwrap();
+
+    let (status, _) = request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    assert_eq!(status, 500);
+    let (status, _) = request(&server, "GET", "/rooms/Hall", None);
+    assert_eq!(status, 404);
+
+    std::fs::remove_dir(&blocker).unwrap();
+    let (status, _) = request(&server, "POST", "/rooms", Some(r#"{"name":"Hall"}"#));
+    assert_eq!(status, 201);
+    let (status, _) = request(&server, "GET", "/rooms/Hall", None);
+    assert_eq!(status, 200);
+}
@@ -0,0 +1,243 @@
+//! Асинхронные сервер и клиент умной розетки
+//!
+//! Доступно при включенной опции `tokio`. Используется тот же протокол,
+//! что и в модуле `tcp`, поэтому асинхронный клиент работает с любым сервером.
+//! Каждый запрос ограничен по времени, а незавершенный запрос можно отменить.
+//! Асинхронное ожидание показаний термометра - `SmartThermometer::next_tempreture`.
+//! Операции `SmartHome` выполняются в памяти и остаются синхронными.
+
+use crate::{
+    errors::{ConnectionErrors, SmartHomeErrors},
+    protocol::{Command, HEADER_LEN, Response, decode_header, encode_frame},
+    smart_devices::SmartElectricalSoket,
+    tcp::{parse_response, respond},
+};
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+/// Время ожидания ответа по умолчанию
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    payload: &[u8],
+) -> Result<(), ConnectionErrors> {
+    writer.write_all(&encode_frame(payload)?).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<(u8, Vec<u8>), ConnectionErrors> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    let (version, len) = decode_header(header)?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok((version, payload))
+}
+
+/// Асинхронный сервер, предоставляющий доступ к розетке по сети
+/// Каждое подключение обслуживается в отдельной задаче.
+/// Розетка хранится под `std::sync::Mutex`, как в блокирующем `SocketServer`:
+/// блокировка берется только на время выполнения команды и не удерживается через `.await`.
+pub struct AsyncSocketServer {
+    listener: TcpListener,
+    socket: Arc<Mutex<SmartElectricalSoket>>,
+}
+
+impl AsyncSocketServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, socket: SmartElectricalSoket) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            socket: Arc::new(Mutex::new(socket)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Общий доступ к обслуживаемой розетке
+    pub fn socket(&self) -> Arc<Mutex<SmartElectricalSoket>> {
+        Arc::clone(&self.socket)
+    }
+
+    /// Принимает подключения, пока не произойдет ошибка приема
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let socket = Arc::clone(&self.socket);
+            tokio::spawn(async move {
+                // Ошибка одного клиента не должна останавливать сервер
+                let _ = handle_connection(stream, socket).await;
+            });
+        }
+    }
+
+    /// Принимает подключения, пока не завершится `shutdown`
+    pub async fn run_until<F: Future<Output = ()>>(self, shutdown: F) -> io::Result<()> {
+        tokio::select! {
+            result = self.run() => result,
+            _ = shutdown => Ok(()),
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    socket: Arc<Mutex<SmartElectricalSoket>>,
+) -> Result<(), ConnectionErrors> {
+    loop {
+        let (version, payload) = match read_frame(&mut stream).await {
+            Ok(frame) => frame,
+            Err(ConnectionErrors::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        // Блокировка розетки освобождается внутри `respond`, до записи ответа
+        let response = respond(version, &payload, &socket);
+        write_frame(&mut stream, &response.encode()).await?;
+    }
+}
+
+/// Асинхронный клиент удалённой розетки
+/// Если запрос прерван по таймауту, отменой или удалением future,
+/// соединение закрывается и открывается заново при следующем запросе.
+/// Недоступность розетки и таймаут возвращаются как `SmartHomeErrors::DeviceOffline`.
+pub struct AsyncSocketClient {
+    addr: SocketAddr,
+    stream: Option<TcpStream>,
+    timeout: Duration,
+}
+
+impl AsyncSocketClient {
+    /// Подключается к серверу, ожидая не дольше `DEFAULT_TIMEOUT`
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, SmartHomeErrors> {
+        let addr = tokio::net::lookup_host(addr)
+            .await?
+            .next()
+            .ok_or_else(|| SmartHomeErrors::invalid_value("address", "no address resolved"))?;
+        let stream = tokio::time::timeout(DEFAULT_TIMEOUT, TcpStream::connect(addr))
+            .await
+            .map_err(|_| ConnectionErrors::Timeout)
+            .and_then(|connected| connected.map_err(ConnectionErrors::from))
+            .map_err(|err| SmartHomeErrors::connection(&addr.to_string(), err))?;
+        Ok(Self {
+            addr,
+            stream: Some(stream),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Задает время ожидания ответа на каждый запрос
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    async fn exchange(&mut self, command: Command) -> Result<Response, ConnectionErrors> {
+        // Соединение возвращается на место только после полного ответа,
+        // поэтому прерванный запрос не оставит в потоке чужих данных
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => TcpStream::connect(self.addr).await?,
+        };
+        write_frame(&mut stream, &command.encode()).await?;
+        let (version, payload) = read_frame(&mut stream).await?;
+        self.stream = Some(stream);
+        parse_response(version, &payload)
+    }
+
+    fn error(&self, err: ConnectionErrors) -> SmartHomeErrors {
+        SmartHomeErrors::connection(&self.addr.to_string(), err)
+    }
+
+    /// Отправляет команду и ждет ответ не дольше заданного времени
+    pub async fn request(&mut self, command: Command) -> Result<Response, SmartHomeErrors> {
+        let response = tokio::time::timeout(self.timeout, self.exchange(command))
+            .await
+            .map_err(|_| ConnectionErrors::Timeout)
+            .and_then(|response| response);
+        response.map_err(|err| self.error(err))
+    }
+
+    /// Как `request`, но запрос отменяется, если раньше завершится `cancel`
+    pub async fn request_until<F: Future<Output = ()>>(
+        &mut self,
+        command: Command,
+        cancel: F,
+    ) -> Result<Response, SmartHomeErrors> {
+        tokio::select! {
+            biased;
+            _ = cancel => Err(self.error(ConnectionErrors::Cancelled)),
+            response = self.request(command) => response,
+        }
+    }
+
+    fn unexpected(&self, response: Response) -> SmartHomeErrors {
+        self.error(ConnectionErrors::UnexpectedResponse(format!(
+            "{:?}",
+            response
+        )))
+    }
+
+    async fn expect_ok(&mut self, command: Command) -> Result<(), SmartHomeErrors> {
+        match self.request(command).await? {
+            Response::Ok => Ok(()),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub async fn turn_on(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::TurnOn).await
+    }
+
+    pub async fn turn_off(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::TurnOff).await
+    }
+
+    pub async fn switch(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::Switch).await
+    }
+
+    pub async fn is_on(&mut self) -> Result<bool, SmartHomeErrors> {
+        match self.request(Command::IsOn).await? {
+            Response::State(is_on) => Ok(is_on),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub async fn get_power(&mut self) -> Result<f32, SmartHomeErrors> {
+        match self.request(Command::GetPower).await? {
+            Response::Power(power) => Ok(power),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub async fn get_name(&mut self) -> Result<String, SmartHomeErrors> {
+        match self.request(Command::GetName).await? {
+            Response::Name(name) => Ok(name),
+            other => Err(self.unexpected(other)),
+        }
+    }
+}
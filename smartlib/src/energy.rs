@@ -0,0 +1,272 @@
+//! Учет потребленной энергии
+//!
+//! Розетка запоминает интервалы, в течение которых она была включена,
+//! вместе с мощностью на каждом интервале и по ним считает потребление
+//! в Вт·ч за любой период.
+//! Если журнал не сохраняемый (`EnergyMeter::set_persistent`), в файл дома
+//! закрытые интервалы попадают одной суммой: после загрузки она учитывается
+//! в потреблении за все время, но не за отдельные периоды.
+
+use crate::structures::{Room, SmartHome};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Интервал, в течение которого устройство было включено с постоянной мощностью
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct OnInterval {
+    from: SystemTime,
+    to: SystemTime,
+    power: f32,
+}
+
+#[cfg(feature = "serde")]
+impl OnInterval {
+    fn duration(&self) -> Duration {
+        self.to.duration_since(self.from).unwrap_or_default()
+    }
+}
+
+/// Текущий незакрытый интервал
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ActivePeriod {
+    since: SystemTime,
+    power: f32,
+}
+
+/// Журнал включений устройства
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnergyMeter {
+    intervals: Vec<OnInterval>,
+    active: Option<ActivePeriod>,
+    /// Энергия в Вт·ч интервалов, которые сохранены только суммой
+    archived: f32,
+    /// Интервалы сохраняются вместе с домом
+    persistent: bool,
+}
+
+impl EnergyMeter {
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    /// Включает сохранение интервалов в файл дома
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    /// Отмечает включение с мощностью `power` Вт; повторное включение не начинает новый интервал
+    pub fn switch_on(&mut self, at: SystemTime, power: f32) {
+        if self.active.is_none() {
+            self.active = Some(ActivePeriod { since: at, power });
+        }
+    }
+
+    /// Отмечает выключение и закрывает текущий интервал
+    pub fn switch_off(&mut self, at: SystemTime) {
+        let Some(ActivePeriod { since, power }) = self.active.take() else {
+            return;
+        };
+        if at <= since {
+            return;
+        }
+        match self.intervals.last_mut() {
+            // Смежные интервалы с той же мощностью склеиваются,
+            // чтобы журнал не рос от лишних переключений
+            Some(last) if last.to == since && last.power == power => last.to = at,
+            _ => self.intervals.push(OnInterval {
+                from: since,
+                to: at,
+                power,
+            }),
+        }
+    }
+
+    /// Отмечает изменение мощности включенного устройства
+    pub fn set_power(&mut self, at: SystemTime, power: f32) {
+        if self.active.is_some_and(|active| active.power != power) {
+            self.switch_off(at);
+            self.switch_on(at, power);
+        }
+    }
+
+    /// Энергия в Вт·ч, потребленная в пределах `[from, to)`.
+    /// Незакрытый интервал считается до момента `now`.
+    pub fn energy_between(&self, from: SystemTime, to: SystemTime, now: SystemTime) -> f32 {
+        let open = self.active.map(|active| OnInterval {
+            from: active.since,
+            to: now,
+            power: active.power,
+        });
+        self.intervals
+            .iter()
+            .chain(open.as_ref())
+            .map(|interval| {
+                let start = interval.from.max(from);
+                let end = interval.to.min(to);
+                watt_hours(
+                    interval.power,
+                    end.duration_since(start).unwrap_or_default(),
+                )
+            })
+            .fold(0.0, |sum, energy| sum + energy)
+    }
+
+    /// Энергия в Вт·ч, потребленная за все время, включая сохраненную суммой
+    pub fn energy_total(&self, now: SystemTime) -> f32 {
+        self.archived + self.energy_between(UNIX_EPOCH, now, now)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::{ActivePeriod, EnergyMeter, OnInterval};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    fn is_zero(value: &f32) -> bool {
+        *value == 0.0
+    }
+
+    #[derive(Serialize)]
+    struct MeterRef<'a> {
+        #[serde(skip_serializing_if = "<[OnInterval]>::is_empty")]
+        intervals: &'a [OnInterval],
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<ActivePeriod>,
+        #[serde(skip_serializing_if = "is_zero")]
+        archived: f32,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        persistent: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct MeterRecord {
+        #[serde(default)]
+        intervals: Vec<OnInterval>,
+        #[serde(default)]
+        active: Option<ActivePeriod>,
+        #[serde(default)]
+        archived: f32,
+        #[serde(default)]
+        persistent: bool,
+    }
+
+    impl Serialize for EnergyMeter {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let (intervals, archived) = if self.persistent {
+                (self.intervals.as_slice(), self.archived)
+            } else {
+                let closed: f32 = self
+                    .intervals
+                    .iter()
+                    .map(|interval| super::watt_hours(interval.power, interval.duration()))
+                    .sum();
+                (&[][..], self.archived + closed)
+            };
+            MeterRef {
+                intervals,
+                active: self.active,
+                archived,
+                persistent: self.persistent,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for EnergyMeter {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let record = MeterRecord::deserialize(deserializer)?;
+            Ok(Self {
+                intervals: record.intervals,
+                active: record.active,
+                archived: record.archived,
+                persistent: record.persistent,
+            })
+        }
+    }
+}
+
+/// Энергия в Вт·ч при мощности `power` Вт за время `duration`
+pub fn watt_hours(power: f32, duration: Duration) -> f32 {
+    power * duration.as_secs_f32() / 3600.0
+}
+
+impl Room {
+    /// Энергия в Вт·ч, потребленная устройствами комнаты за период `[from, to)`
+    pub fn energy_between(&self, from: SystemTime, to: SystemTime) -> f32 {
+        self.devices()
+            .filter_map(|(_, device)| device.energy_between(from, to))
+            .fold(0.0, |sum, energy| sum + energy)
+    }
+
+    /// Энергия в Вт·ч, потребленная устройствами комнаты за все время.
+    /// `None`, если в комнате нет устройств с учетом энергии.
+    pub fn energy_total(&self) -> Option<f32> {
+        self.devices()
+            .filter_map(|(_, device)| device.energy_total())
+            .reduce(|sum, energy| sum + energy)
+    }
+}
+
+impl SmartHome {
+    /// Энергия в Вт·ч, потребленная всеми устройствами дома за период `[from, to)`
+    pub fn energy_between(&self, from: SystemTime, to: SystemTime) -> f32 {
+        self.rooms()
+            .map(|(_, room)| room.energy_between(from, to))
+            .fold(0.0, |sum, energy| sum + energy)
+    }
+
+    /// Энергия в Вт·ч, потребленная всеми устройствами дома за все время.
+    /// `None`, если в доме нет устройств с учетом энергии.
+    pub fn energy_total(&self) -> Option<f32> {
+        self.rooms()
+            .filter_map(|(_, room)| room.energy_total())
+            .reduce(|sum, energy| sum + energy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_on_time_is_clipped_to_period() {
+        let mut meter = EnergyMeter::default();
+        meter.switch_on(at(10), 3600.0);
+        meter.switch_off(at(20));
+        meter.switch_on(at(30), 3600.0);
+        meter.switch_on(at(35), 3600.0);
+
+        assert_eq!(meter.energy_between(at(0), at(100), at(40)), 20.0);
+        assert_eq!(meter.energy_between(at(15), at(32), at(40)), 7.0);
+        assert_eq!(meter.energy_between(at(50), at(60), at(40)), 0.0);
+    }
+
+    #[test]
+    fn test_power_change_splits_interval() {
+        let mut meter = EnergyMeter::default();
+        meter.switch_on(at(0), 3600.0);
+        meter.set_power(at(10), 7200.0);
+        meter.switch_off(at(20));
+        assert_eq!(meter.intervals.len(), 2);
+        assert_eq!(meter.energy_between(at(0), at(20), at(20)), 30.0);
+    }
+
+    #[test]
+    fn test_adjacent_intervals_are_merged() {
+        let mut meter = EnergyMeter::default();
+        meter.switch_on(at(0), 1000.0);
+        meter.switch_off(at(10));
+        meter.switch_on(at(10), 1000.0);
+        meter.switch_off(at(20));
+        assert_eq!(meter.intervals.len(), 1);
+        assert_eq!(
+            meter.energy_between(at(0), at(20), at(20)),
+            1000.0 * 20.0 / 3600.0
+        );
+    }
+}
@@ -1,10 +1,117 @@
+use crate::protocol::ErrorCode;
 use std::error::Error;
-use std::fmt;
+use std::{fmt, io};
 
+/// Ошибки операций над домом
+/// У каждого варианта есть стабильный код `SmartHomeErrors::code`, по которому
+/// ошибку различают CLI и сетевые интерфейсы; текст сообщения может меняться.
 #[derive(Debug)]
 pub enum SmartHomeErrors {
     RoomNotFound(String),
     DeviceNotFound(String),
+    /// Комната с таким ключом уже есть в доме
+    DuplicateRoom(String),
+    /// Устройство с таким ключом уже есть в комнате
+    DuplicateDevice(String),
+    /// Группа устройств с таким именем не определена
+    GroupNotFound(String),
+    /// Сцена с таким именем не определена
+    SceneNotFound(String),
+    /// Операция не поддерживается устройством этого типа
+    WrongDeviceType {
+        device: String,
+        expected: String,
+    },
+    /// Устройство не отвечает или его показания устарели
+    DeviceOffline {
+        device: String,
+        source: Option<ConnectionErrors>,
+    },
+    /// Удаленное устройство ответило с нарушением протокола или запрос был отменен
+    Connection {
+        device: String,
+        source: ConnectionErrors,
+    },
+    /// Недопустимое значение параметра
+    InvalidValue {
+        name: String,
+        reason: String,
+    },
+    /// Ошибка чтения или записи файла дома
+    Io(io::Error),
+    /// Содержимое не удалось разобрать; исходная ошибка доступна через `source`
+    Parse(Box<dyn Error + Send + Sync>),
+    /// Формат файла не определяется по расширению
+    UnsupportedFormat(String),
+    /// Файл создан несовместимой версией схемы
+    UnsupportedVersion(u32),
+    /// Содержимое файла не соответствует схеме
+    MalformedFile(String),
+    /// Некорректное расписание планировщика
+    InvalidSchedule(String),
+    /// Некорректный топик или фильтр подписки брокера
+    InvalidTopic(String),
+}
+
+impl SmartHomeErrors {
+    /// Стабильный код ошибки
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RoomNotFound(_) => "room_not_found",
+            Self::DeviceNotFound(_) => "device_not_found",
+            Self::DuplicateRoom(_) => "duplicate_room",
+            Self::DuplicateDevice(_) => "duplicate_device",
+            Self::GroupNotFound(_) => "group_not_found",
+            Self::SceneNotFound(_) => "scene_not_found",
+            Self::WrongDeviceType { .. } => "wrong_device_type",
+            Self::DeviceOffline { .. } => "device_offline",
+            Self::Connection { .. } => "connection",
+            Self::InvalidValue { .. } => "invalid_value",
+            Self::Io(_) => "io",
+            Self::Parse(_) => "parse",
+            Self::UnsupportedFormat(_) => "unsupported_format",
+            Self::UnsupportedVersion(_) => "unsupported_version",
+            Self::MalformedFile(_) => "malformed_file",
+            Self::InvalidSchedule(_) => "invalid_schedule",
+            Self::InvalidTopic(_) => "invalid_topic",
+        }
+    }
+
+    pub fn invalid_value(name: &str, reason: impl Into<String>) -> Self {
+        Self::InvalidValue {
+            name: name.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Устройство `device` не в сети, причина неизвестна
+    pub fn offline(device: &str) -> Self {
+        Self::DeviceOffline {
+            device: device.to_string(),
+            source: None,
+        }
+    }
+
+    /// Ошибка обращения к удаленному устройству `device`: недоступность
+    /// и отсутствие ответа становятся `DeviceOffline`, остальное - `Connection`
+    pub fn connection(device: &str, source: ConnectionErrors) -> Self {
+        let device = device.to_string();
+        if source.is_unreachable() {
+            Self::DeviceOffline {
+                device,
+                source: Some(source),
+            }
+        } else {
+            Self::Connection { device, source }
+        }
+    }
+
+    pub fn wrong_device_type(device: &str, expected: &str) -> Self {
+        Self::WrongDeviceType {
+            device: device.to_string(),
+            expected: expected.to_string(),
+        }
+    }
 }
 
 impl fmt::Display for SmartHomeErrors {
@@ -12,8 +119,112 @@ impl fmt::Display for SmartHomeErrors {
         match self {
             Self::DeviceNotFound(device_name) => write!(f, "Device {} not found", device_name),
             Self::RoomNotFound(room_name) => write!(f, "Room {} not found", room_name),
+            Self::DuplicateRoom(room_name) => write!(f, "Room {} already exists", room_name),
+            Self::DuplicateDevice(device_name) => {
+                write!(f, "Device {} already exists", device_name)
+            }
+            Self::GroupNotFound(group) => write!(f, "Group {} not found", group),
+            Self::SceneNotFound(scene) => write!(f, "Scene {} not found", scene),
+            Self::WrongDeviceType { device, expected } => {
+                write!(f, "Device {} is not a {}", device, expected)
+            }
+            Self::DeviceOffline {
+                device,
+                source: None,
+            } => write!(f, "Device {} is offline", device),
+            Self::DeviceOffline {
+                device,
+                source: Some(err),
+            } => write!(f, "Device {} is offline: {}", device, err),
+            Self::Connection { device, source } => {
+                write!(f, "Device {} failed to respond: {}", device, source)
+            }
+            Self::InvalidValue { name, reason } => write!(f, "Invalid {}: {}", name, reason),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Parse(err) => write!(f, "Parse error: {}", err),
+            Self::UnsupportedFormat(path) => write!(f, "Unsupported file format of {}", path),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported home file version {}", version)
+            }
+            Self::MalformedFile(reason) => write!(f, "Malformed home file: {}", reason),
+            Self::InvalidSchedule(reason) => write!(f, "Invalid schedule {}", reason),
+            Self::InvalidTopic(topic) => write!(f, "Invalid topic {}", topic),
+        }
+    }
+}
+
+impl Error for SmartHomeErrors {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err.as_ref()),
+            Self::DeviceOffline {
+                source: Some(err), ..
+            } => Some(err),
+            Self::Connection { source, .. } => Some(source),
+            _ => None,
         }
     }
 }
 
-impl Error for SmartHomeErrors {}
+impl From<io::Error> for SmartHomeErrors {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Ошибки сетевого взаимодействия с удалёнными устройствами
+#[derive(Debug)]
+pub enum ConnectionErrors {
+    Io(io::Error),
+    UnsupportedVersion(u8),
+    MalformedFrame(String),
+    Remote(ErrorCode),
+    UnexpectedResponse(String),
+    /// Устройство не ответило за отведенное время
+    Timeout,
+    /// Операция отменена до получения ответа
+    Cancelled,
+}
+
+impl ConnectionErrors {
+    /// Устройство недоступно: не удалось подключиться, соединение разорвано,
+    /// ответ не пришел вовремя или сервер сообщил, что устройство не в сети
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            Self::Io(_) | Self::Timeout | Self::Remote(ErrorCode::DeviceOffline)
+        )
+    }
+}
+
+impl fmt::Display for ConnectionErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported protocol version {}", version)
+            }
+            Self::MalformedFrame(reason) => write!(f, "Malformed frame: {}", reason),
+            Self::Remote(code) => write!(f, "Remote error {}: {}", code.code(), code),
+            Self::UnexpectedResponse(response) => write!(f, "Unexpected response {}", response),
+            Self::Timeout => write!(f, "Operation timed out"),
+            Self::Cancelled => write!(f, "Operation cancelled"),
+        }
+    }
+}
+
+impl Error for ConnectionErrors {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConnectionErrors {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
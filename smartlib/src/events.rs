@@ -0,0 +1,172 @@
+//! События изменения устройств и состава дома
+
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+/// Событие, которое дом рассылает подписчикам
+#[derive(Debug, Clone, PartialEq)]
+pub enum HomeEvent {
+    RoomAdded {
+        room: String,
+    },
+    RoomRemoved {
+        room: String,
+    },
+    DeviceAdded {
+        room: String,
+        device: String,
+    },
+    DeviceRemoved {
+        room: String,
+        device: String,
+    },
+    /// Состояние устройства изменилось: отличается результат `Device::status`
+    /// или `Device::fields`; во втором случае описания могут совпадать
+    StateChanged {
+        room: String,
+        device: String,
+        old_status: String,
+        new_status: String,
+    },
+}
+
+/// Идентификатор подписки, позволяющий от нее отказаться
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Callback = dyn Fn(&HomeEvent) + Send + Sync;
+
+enum Listener {
+    Callback(Arc<Callback>),
+    Channel(Sender<HomeEvent>),
+}
+
+#[derive(Default)]
+struct Listeners {
+    next_id: u64,
+    items: Vec<(SubscriptionId, Listener)>,
+}
+
+/// Шина событий дома
+/// Клоны шины разделяют общий список подписчиков.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    listeners: Arc<Mutex<Listeners>>,
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("listeners", &self.lock().items.len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Listeners> {
+        self.listeners.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn add(&self, listener: Listener) -> SubscriptionId {
+        let mut listeners = self.lock();
+        let id = SubscriptionId(listeners.next_id);
+        listeners.next_id += 1;
+        listeners.items.push((id, listener));
+        id
+    }
+
+    /// Регистрирует обработчик, вызываемый для каждого события
+    pub fn subscribe<F>(&self, callback: F) -> SubscriptionId
+    where
+        F: Fn(&HomeEvent) + Send + Sync + 'static,
+    {
+        self.add(Listener::Callback(Arc::new(callback)))
+    }
+
+    /// Возвращает канал, в который будут приходить события.
+    /// Подписка снимается автоматически, когда получатель удален.
+    pub fn channel(&self) -> Receiver<HomeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.add(Listener::Channel(sender));
+        receiver
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut listeners = self.lock();
+        let before = listeners.items.len();
+        listeners.items.retain(|(item_id, _)| *item_id != id);
+        listeners.items.len() != before
+    }
+
+    /// Рассылает событие всем подписчикам.
+    /// Обработчики вызываются без удержания блокировки, поэтому могут подписываться сами.
+    pub fn emit(&self, event: HomeEvent) {
+        let mut callbacks = Vec::new();
+        let mut closed = Vec::new();
+        {
+            let listeners = self.lock();
+            for (id, listener) in &listeners.items {
+                match listener {
+                    Listener::Callback(callback) => callbacks.push(Arc::clone(callback)),
+                    Listener::Channel(sender) => {
+                        if sender.send(event.clone()).is_err() {
+                            closed.push(*id);
+                        }
+                    }
+                }
+            }
+        }
+        for id in closed {
+            self.unsubscribe(id);
+        }
+        for callback in callbacks {
+            callback(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room_added() -> HomeEvent {
+        HomeEvent::RoomAdded {
+            room: String::from("Кухня"),
+        }
+    }
+
+    #[test]
+    fn test_callback_and_unsubscribe() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let id = {
+            let seen = Arc::clone(&seen);
+            bus.subscribe(move |event| seen.lock().unwrap().push(event.clone()))
+        };
+        bus.emit(room_added());
+        assert!(bus.unsubscribe(id));
+        bus.emit(room_added());
+        assert_eq!(*seen.lock().unwrap(), vec![room_added()]);
+    }
+
+    #[test]
+    fn test_channel_is_dropped_with_receiver() {
+        let bus = EventBus::new();
+        let receiver = bus.channel();
+        bus.emit(room_added());
+        assert_eq!(receiver.try_recv(), Ok(room_added()));
+
+        drop(receiver);
+        bus.emit(room_added());
+        assert_eq!(bus.lock().items.len(), 0);
+    }
+}
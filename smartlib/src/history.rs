@@ -0,0 +1,336 @@
+//! История показаний термометров
+//!
+//! Термометр хранит ограниченный буфер показаний в градусах Цельсия.
+//! Статистика считается за произвольный период и пересчитывается в нужные единицы.
+//! В файл дома показания попадают, только если история сохраняемая
+//! (`TemperatureHistory::set_persistent`), иначе сохраняется лишь ее размер.
+
+use crate::{
+    report::csv_cell,
+    smart_devices::{SmartThermometer, TempMeasures},
+    structures::Room,
+};
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Сколько показаний хранится по умолчанию
+pub const DEFAULT_HISTORY_CAPACITY: usize = 1024;
+
+/// Показание термометра в градусах Цельсия
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemperatureSample {
+    pub at: SystemTime,
+    pub celsius: f32,
+}
+
+impl TemperatureSample {
+    pub fn value_in(&self, measure: &TempMeasures) -> f32 {
+        TempMeasures::C.convert(self.celsius, measure)
+    }
+}
+
+/// Статистика показаний за период
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureStats {
+    pub count: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Изменение температуры в час по методу наименьших квадратов
+    pub trend_per_hour: f32,
+    pub measure: TempMeasures,
+}
+
+impl TemperatureStats {
+    /// Считает статистику по показаниям; `None`, если показаний нет
+    pub fn from_samples<'a>(
+        samples: impl IntoIterator<Item = &'a TemperatureSample>,
+        measure: &TempMeasures,
+    ) -> Option<Self> {
+        let samples: Vec<&TemperatureSample> = samples.into_iter().collect();
+        let first = samples.iter().map(|sample| sample.at).min()?;
+        let hours = |sample: &TemperatureSample| {
+            sample
+                .at
+                .duration_since(first)
+                .unwrap_or_default()
+                .as_secs_f64()
+                / 3600.0
+        };
+
+        let count = samples.len();
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        let (mut sum_x, mut sum_y) = (0.0f64, 0.0f64);
+        for sample in &samples {
+            min = min.min(sample.celsius);
+            max = max.max(sample.celsius);
+            sum_x += hours(sample);
+            sum_y += f64::from(sample.celsius);
+        }
+        let mean_x = sum_x / count as f64;
+        let mean_y = sum_y / count as f64;
+        let (mut covariance, mut variance) = (0.0f64, 0.0f64);
+        for sample in &samples {
+            let dx = hours(sample) - mean_x;
+            covariance += dx * (f64::from(sample.celsius) - mean_y);
+            variance += dx * dx;
+        }
+        let trend = if variance > 0.0 {
+            (covariance / variance) as f32
+        } else {
+            0.0
+        };
+
+        let convert = |value: f32| TempMeasures::C.convert(value, measure);
+        Some(Self {
+            count,
+            min: convert(min),
+            max: convert(max),
+            mean: convert(mean_y as f32),
+            // Для изменения температуры важен только масштаб шкалы, без смещения
+            trend_per_hour: convert(trend) - convert(0.0),
+            measure: *measure,
+        })
+    }
+}
+
+/// Ограниченный буфер показаний: при переполнении удаляются самые старые
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureHistory {
+    capacity: usize,
+    /// Показания сохраняются вместе с домом
+    persistent: bool,
+    samples: VecDeque<TemperatureSample>,
+}
+
+impl Default for TemperatureHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl TemperatureHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            persistent: false,
+            samples: VecDeque::with_capacity(capacity.min(DEFAULT_HISTORY_CAPACITY)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    /// Включает сохранение показаний в файл дома
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn record(&mut self, at: SystemTime, celsius: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(TemperatureSample { at, celsius });
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &TemperatureSample> {
+        self.samples.iter()
+    }
+
+    /// Показания за период `[from, to)`
+    pub fn window(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> impl Iterator<Item = &TemperatureSample> {
+        self.samples
+            .iter()
+            .filter(move |sample| sample.at >= from && sample.at < to)
+    }
+
+    pub fn stats(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+        measure: &TempMeasures,
+    ) -> Option<TemperatureStats> {
+        TemperatureStats::from_samples(self.window(from, to), measure)
+    }
+
+    /// Выгрузка в CSV: время в секундах Unix и температура в указанных единицах
+    pub fn to_csv(&self, measure: &TempMeasures) -> String {
+        let mut out = String::from("timestamp,tempreture\n");
+        for sample in &self.samples {
+            let _ = writeln!(
+                out,
+                "{},{}",
+                unix_seconds(sample.at),
+                sample.value_in(measure)
+            );
+        }
+        out
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::{TemperatureHistory, TemperatureSample};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::VecDeque;
+
+    #[derive(Serialize)]
+    struct HistoryRef<'a> {
+        capacity: usize,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        persistent: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        samples: Option<&'a VecDeque<TemperatureSample>>,
+    }
+
+    #[derive(Deserialize)]
+    struct HistoryRecord {
+        capacity: usize,
+        #[serde(default)]
+        persistent: bool,
+        #[serde(default)]
+        samples: VecDeque<TemperatureSample>,
+    }
+
+    impl Serialize for TemperatureHistory {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            HistoryRef {
+                capacity: self.capacity,
+                persistent: self.persistent,
+                samples: self.persistent.then_some(&self.samples),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TemperatureHistory {
+        /// Показания сверх `capacity` отбрасываются, начиная с самых старых
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let HistoryRecord {
+                capacity,
+                persistent,
+                mut samples,
+            } = HistoryRecord::deserialize(deserializer)?;
+            let extra = samples.len().saturating_sub(capacity);
+            samples.drain(..extra);
+            Ok(Self {
+                capacity,
+                persistent,
+                samples,
+            })
+        }
+    }
+}
+
+fn unix_seconds(at: SystemTime) -> f64 {
+    at.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs_f64()
+}
+
+impl Room {
+    fn thermometers(&self) -> impl Iterator<Item = (&str, &SmartThermometer)> {
+        self.devices()
+            .filter_map(|(key, device)| Some((key, device.downcast_ref::<SmartThermometer>()?)))
+    }
+
+    /// Статистика по показаниям всех термометров комнаты за период `[from, to)`
+    pub fn temperature_stats(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+        measure: &TempMeasures,
+    ) -> Option<TemperatureStats> {
+        let samples: Vec<&TemperatureSample> = self
+            .thermometers()
+            .flat_map(|(_, thermo)| thermo.history().window(from, to))
+            .collect();
+        TemperatureStats::from_samples(samples, measure)
+    }
+
+    /// Выгрузка истории всех термометров комнаты в CSV
+    pub fn temperature_history_csv(&self, measure: &TempMeasures) -> String {
+        let mut out = String::from("device_key,timestamp,tempreture\n");
+        for (key, thermo) in self.thermometers() {
+            for sample in thermo.history().samples() {
+                let _ = writeln!(
+                    out,
+                    "{},{},{}",
+                    csv_cell(key),
+                    unix_seconds(sample.at),
+                    sample.value_in(measure)
+                );
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = TemperatureHistory::with_capacity(2);
+        history.record(at(0), 1.0);
+        history.record(at(1), 2.0);
+        history.record(at(2), 3.0);
+        let values: Vec<f32> = history.samples().map(|sample| sample.celsius).collect();
+        assert_eq!(values, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_stats_and_trend() {
+        let mut history = TemperatureHistory::default();
+        for hour in 0..4 {
+            history.record(at(hour * 3600), 20.0 + hour as f32);
+        }
+        let stats = history
+            .stats(at(0), at(4 * 3600), &TempMeasures::C)
+            .unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!((stats.min, stats.max, stats.mean), (20.0, 23.0, 21.5));
+        assert!((stats.trend_per_hour - 1.0).abs() < 1e-5);
+
+        let fahrenheit = history
+            .stats(at(0), at(4 * 3600), &TempMeasures::F)
+            .unwrap();
+        assert_eq!(fahrenheit.min, 68.0);
+        assert!((fahrenheit.trend_per_hour - 1.8).abs() < 1e-4);
+
+        assert_eq!(
+            history.stats(at(5 * 3600), at(6 * 3600), &TempMeasures::C),
+            None
+        );
+    }
+}
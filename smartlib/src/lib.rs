@@ -1,8 +1,28 @@
+#[cfg(feature = "tokio")]
+pub mod async_tcp;
+pub mod clock;
+pub mod energy;
 pub mod errors;
+pub mod events;
+pub mod history;
 pub mod macros;
+pub mod mqtt;
+#[cfg(feature = "serde")]
+pub mod persistence;
+pub mod protocol;
+pub mod query;
+pub mod report;
+pub mod rules;
+pub mod scenes;
+pub mod scheduler;
+pub mod shared;
+pub mod simulator;
 pub mod smart_devices;
 pub mod structures;
-pub use crate::structures::{Room, SmartDevice, SmartHome};
+pub mod tcp;
+pub mod udp;
+pub use crate::shared::SharedHome;
+pub use crate::structures::{Device, DeviceId, Room, RoomId, SmartDevice, SmartHome, SortOrder};
 
 #[cfg(test)]
 mod tests;
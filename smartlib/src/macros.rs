@@ -1,3 +1,7 @@
+/// Создает комнату с устройствами
+///
+/// # Panics
+/// Если ключи устройств повторяются или не годятся в качестве ключа.
 #[macro_export]
 macro_rules! add_room {
     ($room_name: expr) => {{
@@ -5,13 +9,15 @@ macro_rules! add_room {
     }};
     ($room_name: expr, ($key: expr, $device: expr)) => {{
         let mut room = $crate::structures::Room::new($room_name);
-        room.add_device_with_key(String::from($key), $device.into());
+        room.add_device_with_key(String::from($key), $device.into())
+            .expect("add_room!: device keys must be unique");
         room
     }};
     ($room_name: expr, $(($key: expr, $device: expr)),+ $(,)?) => {{
         let mut room = $crate::structures::Room::new($room_name);
         $(
-            room.add_device_with_key(String::from($key), $device.into());
+            room.add_device_with_key(String::from($key), $device.into())
+                .expect("add_room!: device keys must be unique");
         )+
         room
     }};
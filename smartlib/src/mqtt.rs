@@ -0,0 +1,649 @@
+//! Мост к брокеру сообщений в стиле MQTT
+//!
+//! Состояние каждого устройства публикуется сохраняемым (retained) сообщением
+//! в топик `<префикс>/<комната>/<устройство>/state` в виде JSON из отчета об устройстве.
+//! Команды принимаются из топиков `<префикс>/<комната>/<устройство>/set`:
+//! - розетка: `on`, `off`, `switch`;
+//! - термометр: `C`, `F`, `K` или `R` для выбора единиц, число - новое показание в текущих единицах.
+//!
+//! Регистр команд не важен. Состояние публикуется заново при каждом изменении полей отчета.
+//!
+//! Ошибки выполнения команд публикуются в `<префикс>/<комната>/<устройство>/error`.
+//! Символы `/`, `+`, `#` и `%` в ключах комнат и устройств кодируются как `%XX`.
+//! Для локальной работы и тестов есть брокер `LocalBroker`, работающий внутри процесса;
+//! внешний брокер подключается реализацией трейта `Broker`.
+
+use crate::{
+    errors::SmartHomeErrors,
+    events::{self, HomeEvent},
+    report::DeviceReport,
+    shared::SharedHome,
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::Device,
+};
+use indexmap::IndexMap;
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Префикс топиков моста по умолчанию
+pub const DEFAULT_PREFIX: &str = "home";
+
+/// Сообщение брокера
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: String,
+    /// Брокер хранит последнее такое сообщение и отдает его новым подписчикам
+    pub retain: bool,
+}
+
+impl Message {
+    pub fn new(topic: impl Into<String>, payload: impl Into<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: payload.into(),
+            retain: false,
+        }
+    }
+
+    /// Сохраняемое сообщение; пустое содержимое удаляет сохраненное сообщение топика
+    pub fn retained(topic: impl Into<String>, payload: impl Into<String>) -> Self {
+        Self {
+            retain: true,
+            ..Self::new(topic, payload)
+        }
+    }
+}
+
+/// Проверяет фильтр подписки: `+` заменяет один уровень, `#` - все оставшиеся
+pub fn validate_filter(filter: &str) -> Result<(), SmartHomeErrors> {
+    let levels: Vec<&str> = filter.split('/').collect();
+    let valid = !filter.is_empty()
+        && levels
+            .iter()
+            .enumerate()
+            .all(|(index, level)| match *level {
+                "#" => index == levels.len() - 1,
+                "+" => true,
+                level => !level.contains(['+', '#']),
+            });
+    if valid {
+        Ok(())
+    } else {
+        Err(SmartHomeErrors::InvalidTopic(filter.to_string()))
+    }
+}
+
+/// Соответствует ли топик фильтру подписки
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(expected), Some(level)) if expected == level => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Кодирует ключ в один уровень топика
+fn encode_level(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for ch in key.chars() {
+        match ch {
+            '%' | '/' | '+' | '#' => out.push_str(&format!("%{:02X}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn decode_level(level: &str) -> Option<String> {
+    let mut out = String::with_capacity(level.len());
+    let mut chars = level.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '%' {
+            let hex: String = chars.by_ref().take(2).collect();
+            let code = u8::from_str_radix(&hex, 16)
+                .ok()
+                .filter(|code| hex.len() == 2 && code.is_ascii())?;
+            out.push(char::from(code));
+        } else {
+            out.push(ch);
+        }
+    }
+    Some(out)
+}
+
+/// Идентификатор подписки на брокере
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+pub type MessageCallback = dyn Fn(&Message) + Send + Sync;
+
+/// Брокер, через который мост обменивается сообщениями
+pub trait Broker: Send + Sync {
+    fn publish(&self, message: Message);
+    /// Подписывается на топики, подходящие под фильтр.
+    /// Сохраненные сообщения таких топиков доставляются сразу.
+    fn subscribe(
+        &self,
+        filter: &str,
+        callback: Box<MessageCallback>,
+    ) -> Result<SubscriptionId, SmartHomeErrors>;
+    fn unsubscribe(&self, id: SubscriptionId) -> bool;
+}
+
+enum Listener {
+    Callback(Arc<MessageCallback>),
+    Channel(Sender<Message>),
+}
+
+#[derive(Default)]
+struct BrokerState {
+    next_id: u64,
+    subscriptions: Vec<(SubscriptionId, String, Listener)>,
+    retained: IndexMap<String, Message>,
+}
+
+/// Брокер внутри процесса
+/// Клоны брокера разделяют подписки и сохраненные сообщения.
+#[derive(Clone, Default)]
+pub struct LocalBroker {
+    state: Arc<Mutex<BrokerState>>,
+}
+
+impl fmt::Debug for LocalBroker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("LocalBroker")
+            .field("subscriptions", &state.subscriptions.len())
+            .field("retained", &state.retained.len())
+            .finish()
+    }
+}
+
+impl LocalBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BrokerState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn add(&self, filter: &str, listener: Listener) -> Result<SubscriptionId, SmartHomeErrors> {
+        validate_filter(filter)?;
+        let mut state = self.lock();
+        let id = SubscriptionId(state.next_id);
+        state.next_id += 1;
+        let retained: Vec<Message> = state
+            .retained
+            .values()
+            .filter(|message| topic_matches(filter, &message.topic))
+            .cloned()
+            .collect();
+        let callback = match &listener {
+            Listener::Callback(callback) => Some(Arc::clone(callback)),
+            Listener::Channel(sender) => {
+                for message in &retained {
+                    let _ = sender.send(message.clone());
+                }
+                None
+            }
+        };
+        state.subscriptions.push((id, filter.to_string(), listener));
+        drop(state);
+        if let Some(callback) = callback {
+            for message in &retained {
+                callback(message);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Возвращает канал, в который будут приходить сообщения подходящих топиков.
+    /// Подписка снимается автоматически, когда получатель удален.
+    pub fn channel(&self, filter: &str) -> Result<Receiver<Message>, SmartHomeErrors> {
+        let (sender, receiver) = mpsc::channel();
+        self.add(filter, Listener::Channel(sender))?;
+        Ok(receiver)
+    }
+
+    /// Сохраненное сообщение топика
+    pub fn retained(&self, topic: &str) -> Option<Message> {
+        self.lock().retained.get(topic).cloned()
+    }
+}
+
+impl Broker for LocalBroker {
+    /// Обработчики вызываются без удержания блокировки, поэтому могут публиковать сами
+    fn publish(&self, message: Message) {
+        let mut callbacks = Vec::new();
+        let mut closed = Vec::new();
+        {
+            let mut state = self.lock();
+            if message.retain {
+                if message.payload.is_empty() {
+                    state.retained.shift_remove(&message.topic);
+                } else {
+                    state
+                        .retained
+                        .insert(message.topic.clone(), message.clone());
+                }
+            }
+            for (id, filter, listener) in &state.subscriptions {
+                if !topic_matches(filter, &message.topic) {
+                    continue;
+                }
+                match listener {
+                    Listener::Callback(callback) => callbacks.push(Arc::clone(callback)),
+                    Listener::Channel(sender) => {
+                        if sender.send(message.clone()).is_err() {
+                            closed.push(*id);
+                        }
+                    }
+                }
+            }
+        }
+        for id in closed {
+            self.unsubscribe(id);
+        }
+        for callback in callbacks {
+            callback(&message);
+        }
+    }
+
+    fn subscribe(
+        &self,
+        filter: &str,
+        callback: Box<MessageCallback>,
+    ) -> Result<SubscriptionId, SmartHomeErrors> {
+        self.add(filter, Listener::Callback(Arc::from(callback)))
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut state = self.lock();
+        let before = state.subscriptions.len();
+        state.subscriptions.retain(|(item_id, _, _)| *item_id != id);
+        state.subscriptions.len() != before
+    }
+}
+
+/// Команда из топика `.../set`
+#[derive(Debug, Clone, PartialEq)]
+enum SetCommand {
+    On,
+    Off,
+    Switch,
+    Measure(TempMeasures),
+    Tempreture(f32),
+}
+
+impl SetCommand {
+    /// Регистр не важен, как в `DeviceState::from_str`
+    fn parse(payload: &str) -> Option<Self> {
+        match payload.trim().to_ascii_lowercase().as_str() {
+            "on" => Some(Self::On),
+            "off" => Some(Self::Off),
+            "switch" => Some(Self::Switch),
+            "c" => Some(Self::Measure(TempMeasures::C)),
+            "f" => Some(Self::Measure(TempMeasures::F)),
+            "k" => Some(Self::Measure(TempMeasures::K)),
+            "r" => Some(Self::Measure(TempMeasures::R)),
+            value => value
+                .parse()
+                .ok()
+                .filter(|value: &f32| value.is_finite())
+                .map(Self::Tempreture),
+        }
+    }
+
+    fn apply(&self, key: &str, device: &mut dyn Device) -> Result<(), SmartHomeErrors> {
+        let supported = match self {
+            Self::On | Self::Off | Self::Switch => {
+                match device.downcast_mut::<SmartElectricalSoket>() {
+                    Some(socket) => {
+                        match self {
+                            Self::On => socket.turn_on()?,
+                            Self::Off => socket.turn_off(),
+                            _ => socket.switch()?,
+                        }
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Self::Measure(measure) => device
+                .downcast_mut::<SmartThermometer>()
+                .map(|thermo| thermo.convert_to(*measure))
+                .is_some(),
+            Self::Tempreture(tempreture) => device
+                .downcast_mut::<SmartThermometer>()
+                .map(|thermo| thermo.set_tempreture(*tempreture))
+                .is_some(),
+        };
+        if supported {
+            Ok(())
+        } else {
+            let expected = match self {
+                Self::On | Self::Off | Self::Switch => "socket",
+                Self::Measure(_) | Self::Tempreture(_) => "thermometer",
+            };
+            Err(SmartHomeErrors::wrong_device_type(key, expected))
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Input {
+    Event(HomeEvent),
+    Command(Message),
+    Stop,
+}
+
+/// Мост между домом и брокером
+/// Изменения дома и команды из брокера собираются в очередь и обрабатываются
+/// вызовом `poll` или в отдельном потоке после `spawn`.
+pub struct MqttBridge<B: Broker> {
+    home: SharedHome,
+    broker: B,
+    prefix: String,
+    inputs: Receiver<Input>,
+    sender: Sender<Input>,
+    event_subscription: events::SubscriptionId,
+    command_subscription: SubscriptionId,
+    /// Устройства, состояние которых опубликовано
+    published: HashSet<(String, String)>,
+}
+
+impl<B: Broker> fmt::Debug for MqttBridge<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttBridge")
+            .field("prefix", &self.prefix)
+            .field("published", &self.published.len())
+            .finish()
+    }
+}
+
+impl<B: Broker> MqttBridge<B> {
+    /// Подключает мост и публикует текущее состояние всех устройств
+    pub fn new(home: SharedHome, broker: B, prefix: &str) -> Result<Self, SmartHomeErrors> {
+        validate_filter(prefix)?;
+        if prefix.split('/').any(|level| level == "+" || level == "#") {
+            return Err(SmartHomeErrors::InvalidTopic(prefix.to_string()));
+        }
+        let (sender, inputs) = mpsc::channel();
+        let commands = sender.clone();
+        let command_subscription = broker.subscribe(
+            &format!("{}/+/+/set", prefix),
+            Box::new(move |message: &Message| {
+                let _ = commands.send(Input::Command(message.clone()));
+            }),
+        )?;
+        let events = sender.clone();
+        let event_subscription = home.events().subscribe(move |event| {
+            let _ = events.send(Input::Event(event.clone()));
+        });
+        let mut bridge = Self {
+            home,
+            broker,
+            prefix: prefix.to_string(),
+            inputs,
+            sender,
+            event_subscription,
+            command_subscription,
+            published: HashSet::new(),
+        };
+        for room in bridge.home.room_keys() {
+            bridge.publish_room(&room);
+        }
+        Ok(bridge)
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Топик устройства, например `home/Кухня/S1/state`
+    pub fn topic(&self, room: &str, device: &str, suffix: &str) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.prefix,
+            encode_level(room),
+            encode_level(device),
+            suffix
+        )
+    }
+
+    /// Обрабатывает накопившиеся события и команды, не блокируя поток.
+    /// Возвращает количество обработанных элементов.
+    pub fn poll(&mut self) -> usize {
+        let mut handled = 0;
+        while let Ok(input) = self.inputs.try_recv() {
+            self.handle(input);
+            handled += 1;
+        }
+        handled
+    }
+
+    /// Запускает обработку в отдельном потоке
+    pub fn spawn(mut self) -> BridgeHandle
+    where
+        B: 'static,
+    {
+        let stop = self.sender.clone();
+        let thread = thread::spawn(move || {
+            while let Ok(input) = self.inputs.recv() {
+                if let Input::Stop = input {
+                    break;
+                }
+                self.handle(input);
+            }
+        });
+        BridgeHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn handle(&mut self, input: Input) {
+        match input {
+            Input::Event(HomeEvent::RoomAdded { room }) => self.publish_room(&room),
+            Input::Event(HomeEvent::RoomRemoved { room }) => self.clear_room(&room),
+            Input::Event(
+                HomeEvent::DeviceAdded { room, device }
+                | HomeEvent::StateChanged { room, device, .. },
+            ) => self.publish_device(&room, &device),
+            Input::Event(HomeEvent::DeviceRemoved { room, device }) => self.clear(&room, &device),
+            Input::Command(message) => self.execute(&message),
+            Input::Stop => {}
+        }
+    }
+
+    fn publish_room(&mut self, room: &str) {
+        let states = self.home.with_room(room, |value| {
+            value
+                .devices()
+                .map(|(key, device)| {
+                    (
+                        key.to_string(),
+                        DeviceReport::new(key, device)
+                            .with_id(value.device_id(key))
+                            .render_json(),
+                    )
+                })
+                .collect::<Vec<(String, String)>>()
+        });
+        for (device, state) in states.unwrap_or_default() {
+            self.publish_state(room, &device, state);
+        }
+    }
+
+    fn publish_device(&mut self, room: &str, device: &str) {
+        let state = self.home.with_room(room, |value| {
+            value.get_device(device).map(|found| {
+                DeviceReport::new(device, found)
+                    .with_id(value.device_id(device))
+                    .render_json()
+            })
+        });
+        match state.ok().flatten() {
+            Some(state) => self.publish_state(room, device, state),
+            None => self.clear(room, device),
+        }
+    }
+
+    fn publish_state(&mut self, room: &str, device: &str, state: String) {
+        let topic = self.topic(room, device, "state");
+        self.broker.publish(Message::retained(topic, state));
+        self.published
+            .insert((room.to_string(), device.to_string()));
+    }
+
+    /// Удаляет сохраненное состояние устройства
+    fn clear(&mut self, room: &str, device: &str) {
+        if self
+            .published
+            .remove(&(room.to_string(), device.to_string()))
+        {
+            let topic = self.topic(room, device, "state");
+            self.broker.publish(Message::retained(topic, ""));
+        }
+    }
+
+    fn clear_room(&mut self, room: &str) {
+        let devices: Vec<String> = self
+            .published
+            .iter()
+            .filter(|(published_room, _)| published_room == room)
+            .map(|(_, device)| device.clone())
+            .collect();
+        for device in devices {
+            self.clear(room, &device);
+        }
+    }
+
+    fn execute(&mut self, message: &Message) {
+        let Some((room, device)) = self.parse_set_topic(&message.topic) else {
+            return;
+        };
+        let result = match SetCommand::parse(&message.payload) {
+            Some(command) => self
+                .home
+                .update_device(&room, &device, |value| command.apply(&device, value))
+                .and_then(|result| result)
+                .map_err(|err| err.to_string()),
+            None => Err(format!("Unknown command {}", message.payload)),
+        };
+        if let Err(err) = result {
+            let topic = self.topic(&room, &device, "error");
+            self.broker.publish(Message::new(topic, err));
+        }
+    }
+
+    fn parse_set_topic(&self, topic: &str) -> Option<(String, String)> {
+        let rest = topic.strip_prefix(&self.prefix)?.strip_prefix('/')?;
+        match rest.split('/').collect::<Vec<&str>>().as_slice() {
+            [room, device, "set"] => Some((decode_level(room)?, decode_level(device)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<B: Broker> Drop for MqttBridge<B> {
+    fn drop(&mut self) {
+        self.home.events().unsubscribe(self.event_subscription);
+        self.broker.unsubscribe(self.command_subscription);
+    }
+}
+
+/// Мост, работающий в отдельном потоке
+/// Поток останавливается при вызове `stop` или удалении дескриптора.
+#[derive(Debug)]
+pub struct BridgeHandle {
+    stop: Sender<Input>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BridgeHandle {
+    /// Останавливает мост после обработки уже полученных событий и команд
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.stop.send(Input::Stop);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for BridgeHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches("home/+/+/set", "home/Кухня/S1/set"));
+        assert!(!topic_matches("home/+/+/set", "home/Кухня/S1/state"));
+        assert!(!topic_matches("home/+/set", "home/Кухня/S1/set"));
+        assert!(topic_matches("home/#", "home/Кухня/S1/state"));
+        assert!(topic_matches("home/#", "home"));
+        assert!(!topic_matches("home/Кухня", "home"));
+    }
+
+    #[test]
+    fn test_validate_filter() {
+        assert!(validate_filter("home/+/S1/#").is_ok());
+        assert!(validate_filter("home/#/state").is_err());
+        assert!(validate_filter("home/S+").is_err());
+        assert!(validate_filter("").is_err());
+    }
+
+    #[test]
+    fn test_level_encoding() {
+        let key = "a/b+c#d%e";
+        assert_eq!(encode_level(key), "a%2Fb%2Bc%23d%25e");
+        assert_eq!(decode_level(&encode_level(key)).unwrap(), key);
+        assert!(decode_level("%G1").is_none());
+        assert!(decode_level("%2").is_none());
+    }
+
+    #[test]
+    fn test_set_command_parse() {
+        assert_eq!(SetCommand::parse("on"), Some(SetCommand::On));
+        assert_eq!(
+            SetCommand::parse("F"),
+            Some(SetCommand::Measure(TempMeasures::F))
+        );
+        assert_eq!(
+            SetCommand::parse("k"),
+            Some(SetCommand::Measure(TempMeasures::K))
+        );
+        assert_eq!(SetCommand::parse("ON"), Some(SetCommand::On));
+        assert_eq!(
+            SetCommand::parse("-3.5"),
+            Some(SetCommand::Tempreture(-3.5))
+        );
+        assert_eq!(SetCommand::parse("NaN"), None);
+        assert_eq!(SetCommand::parse("boil"), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Сохранение и загрузка дома в форматах JSON и TOML
+//!
+//! Доступно при включенной опции `serde`. Сохраняются только встроенные
+//! устройства библиотеки; для устройств других типов сериализация вернет ошибку.
+
+use crate::{
+    errors::SmartHomeErrors,
+    scenes::Scene,
+    smart_devices::{SmartElectricalSoket, SmartThermometer},
+    structures::{Device, DeviceId, Room, RoomId, SmartDevice, SmartHome, SortOrder, reserve_id},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+use std::{collections::HashSet, fs, io::Write, path::Path};
+
+/// Версия схемы файла дома
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Формат файла дома
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomeFormat {
+    Json,
+    Toml,
+}
+
+impl HomeFormat {
+    /// Определяет формат по расширению файла
+    pub fn from_path(path: &Path) -> Result<Self, SmartHomeErrors> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(HomeFormat::Json),
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(HomeFormat::Toml),
+            _ => Err(SmartHomeErrors::UnsupportedFormat(
+                path.display().to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum DeviceRecordRef<'a> {
+    Thermometer(&'a SmartThermometer),
+    Socket(&'a SmartElectricalSoket),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum DeviceRecord {
+    Thermometer(SmartThermometer),
+    Socket(SmartElectricalSoket),
+}
+
+impl Serialize for dyn Device {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let record = if let Some(thermo) = self.downcast_ref::<SmartThermometer>() {
+            DeviceRecordRef::Thermometer(thermo)
+        } else if let Some(socket) = self.downcast_ref::<SmartElectricalSoket>() {
+            DeviceRecordRef::Socket(socket)
+        } else {
+            return Err(ser::Error::custom(format!(
+                "device kind '{}' can't be serialized",
+                self.kind()
+            )));
+        };
+        record.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SmartDevice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match DeviceRecord::deserialize(deserializer)? {
+            DeviceRecord::Thermometer(thermo) => thermo.into(),
+            DeviceRecord::Socket(socket) => socket.into(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct DeviceEntryRef<'a> {
+    key: &'a str,
+    id: DeviceId,
+    #[serde(flatten)]
+    device: &'a dyn Device,
+}
+
+#[derive(Deserialize)]
+struct DeviceEntry {
+    key: String,
+    /// В файлах без идентификаторов устройства получают новые
+    #[serde(default)]
+    id: Option<DeviceId>,
+    #[serde(flatten)]
+    device: SmartDevice,
+}
+
+#[derive(Serialize)]
+struct RoomRef<'a> {
+    id: RoomId,
+    name: &'a str,
+    order: SortOrder,
+    devices: Vec<DeviceEntryRef<'a>>,
+}
+
+#[derive(Deserialize)]
+struct RoomRecord {
+    #[serde(default)]
+    id: Option<RoomId>,
+    name: String,
+    /// В файлах без порядка комнаты берут порядок дома
+    #[serde(default)]
+    order: Option<SortOrder>,
+    #[serde(default)]
+    devices: Vec<DeviceEntry>,
+}
+
+impl Serialize for Room {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RoomRef {
+            id: self.id(),
+            name: self.get_name(),
+            order: self.get_sort_order(),
+            devices: self
+                .devices()
+                .map(|(key, device)| DeviceEntryRef {
+                    key,
+                    id: self.device_id(key).expect("key comes from the room"),
+                    device,
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl RoomRecord {
+    /// Сохраненные идентификаторы комнаты и ее устройств
+    fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.id.map(RoomId::get).into_iter().chain(
+            self.devices
+                .iter()
+                .filter_map(|entry| entry.id.map(DeviceId::get)),
+        )
+    }
+
+    /// Собирает комнату; `default_order` применяется, если порядок не сохранен
+    fn into_room(self, default_order: SortOrder) -> Result<Room, SmartHomeErrors> {
+        for id in self.ids() {
+            reserve_id(id);
+        }
+        let mut room = Room::new(self.name);
+        room.set_sort_order(self.order.unwrap_or(default_order));
+        if let Some(id) = self.id {
+            room.set_id(id);
+        }
+        for entry in self.devices {
+            match entry.id {
+                Some(id) => room.add_device_with_id(entry.key, id, entry.device),
+                None => room.add_device_with_key(entry.key, entry.device),
+            }?;
+        }
+        Ok(room)
+    }
+}
+
+impl<'de> Deserialize<'de> for Room {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        RoomRecord::deserialize(deserializer)?
+            .into_room(SortOrder::default())
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+struct RoomEntryRef<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    room: &'a Room,
+}
+
+#[derive(Deserialize)]
+struct RoomEntry {
+    key: String,
+    #[serde(flatten)]
+    room: RoomRecord,
+}
+
+#[derive(Serialize)]
+struct GroupRef<'a> {
+    name: &'a str,
+    devices: &'a [DeviceId],
+}
+
+#[derive(Deserialize)]
+struct GroupRecord {
+    name: String,
+    #[serde(default)]
+    devices: Vec<DeviceId>,
+}
+
+#[derive(Serialize)]
+struct SceneRef<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    scene: &'a Scene,
+}
+
+#[derive(Deserialize)]
+struct SceneRecord {
+    name: String,
+    #[serde(flatten)]
+    scene: Scene,
+}
+
+#[derive(Serialize)]
+struct HomeRef<'a> {
+    version: u32,
+    name: &'a str,
+    order: SortOrder,
+    rooms: Vec<RoomEntryRef<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<GroupRef<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    scenes: Vec<SceneRef<'a>>,
+}
+
+#[derive(Deserialize)]
+struct HomeRecord {
+    version: u32,
+    name: String,
+    #[serde(default)]
+    order: SortOrder,
+    #[serde(default)]
+    rooms: Vec<RoomEntry>,
+    #[serde(default)]
+    groups: Vec<GroupRecord>,
+    #[serde(default)]
+    scenes: Vec<SceneRecord>,
+}
+
+impl Serialize for SmartHome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        HomeRef {
+            version: SCHEMA_VERSION,
+            name: self.get_name(),
+            order: self.get_sort_order(),
+            rooms: self
+                .rooms()
+                .map(|(key, room)| RoomEntryRef { key, room })
+                .collect(),
+            groups: self
+                .groups()
+                .map(|(name, devices)| GroupRef { name, devices })
+                .collect(),
+            scenes: self
+                .scenes()
+                .map(|(name, scene)| SceneRef { name, scene })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SmartHome {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = HomeRecord::deserialize(deserializer)?;
+        if record.version != SCHEMA_VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported schema version {}",
+                record.version
+            )));
+        }
+        let mut home = SmartHome::try_new(record.name, vec![]).map_err(de::Error::custom)?;
+        // Группы и сцены ссылаются на устройства по идентификатору, поэтому
+        // повторяющиеся идентификаторы нельзя молча заменить новыми. Сохраненные
+        // идентификаторы проверяются до создания комнат, чтобы новые, выданные
+        // устройствам без идентификатора, с ними не совпали.
+        let mut ids = HashSet::new();
+        for entry in &record.rooms {
+            if let Some(id) = entry.room.ids().find(|id| !ids.insert(*id)) {
+                return Err(de::Error::custom(format!(
+                    "id {} is used more than once",
+                    id
+                )));
+            }
+        }
+        for id in ids {
+            reserve_id(id);
+        }
+        for entry in record.rooms {
+            let room = entry
+                .room
+                .into_room(record.order)
+                .map_err(de::Error::custom)?;
+            home.add_room_with_key(entry.key, room)
+                .map_err(de::Error::custom)?;
+        }
+        // Порядок дома задается после добавления комнат, чтобы не затереть
+        // сохраненный порядок отдельных комнат
+        home.restore_sort_order(record.order);
+        // Группы и сцены загружаются как есть: ссылки на удаленные устройства
+        // проявятся ошибками в отчете о применении сцены
+        let book = home.scene_book_mut();
+        for group in record.groups {
+            book.groups.insert(group.name, group.devices);
+        }
+        for scene in record.scenes {
+            book.scenes.insert(scene.name, scene.scene);
+        }
+        Ok(home)
+    }
+}
+
+/// Проверяет версию схемы до разбора остального содержимого,
+/// чтобы отличать несовместимые файлы от поврежденных
+fn check_version(version: Option<i64>) -> Result<(), SmartHomeErrors> {
+    match version {
+        Some(version) if version == i64::from(SCHEMA_VERSION) => Ok(()),
+        Some(version) => Err(SmartHomeErrors::UnsupportedVersion(
+            u32::try_from(version).unwrap_or(u32::MAX),
+        )),
+        None => Err(SmartHomeErrors::MalformedFile(String::from(
+            "missing or invalid version field",
+        ))),
+    }
+}
+
+fn malformed(err: impl std::fmt::Display) -> SmartHomeErrors {
+    SmartHomeErrors::MalformedFile(err.to_string())
+}
+
+fn parse_error(err: impl std::error::Error + Send + Sync + 'static) -> SmartHomeErrors {
+    SmartHomeErrors::Parse(Box::new(err))
+}
+
+impl SmartHome {
+    /// Разбирает дом из строки в заданном формате
+    pub fn from_str_with(content: &str, format: HomeFormat) -> Result<Self, SmartHomeErrors> {
+        match format {
+            HomeFormat::Json => {
+                let value: serde_json::Value =
+                    serde_json::from_str(content).map_err(parse_error)?;
+                check_version(value.get("version").and_then(serde_json::Value::as_i64))?;
+                serde_json::from_value(value).map_err(malformed)
+            }
+            HomeFormat::Toml => {
+                let table: toml::Table = toml::from_str(content).map_err(parse_error)?;
+                check_version(table.get("version").and_then(toml::Value::as_integer))?;
+                table.try_into().map_err(malformed)
+            }
+        }
+    }
+
+    /// Представляет дом строкой в заданном формате
+    pub fn to_string_with(&self, format: HomeFormat) -> Result<String, SmartHomeErrors> {
+        match format {
+            HomeFormat::Json => serde_json::to_string_pretty(self).map_err(malformed),
+            HomeFormat::Toml => toml::to_string_pretty(self).map_err(malformed),
+        }
+    }
+
+    /// Загружает дом из файла, формат определяется по расширению (`.json` или `.toml`)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SmartHomeErrors> {
+        let path = path.as_ref();
+        let format = HomeFormat::from_path(path)?;
+        Self::from_str_with(&fs::read_to_string(path)?, format)
+    }
+
+    /// Сохраняет дом в файл, формат определяется по расширению (`.json` или `.toml`).
+    /// Содержимое сначала пишется во временный файл рядом с целевым и затем
+    /// переименовывается, поэтому сбой во время записи не портит прежний файл.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SmartHomeErrors> {
+        let path = path.as_ref();
+        let content = self.to_string_with(HomeFormat::from_path(path)?)?;
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+        let written = fs::File::create(&temp_path).and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(err) = written.and_then(|()| fs::rename(&temp_path, path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+}
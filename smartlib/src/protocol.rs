@@ -0,0 +1,263 @@
+//! Протокол управления умной розеткой по TCP
+//!
+//! Каждое сообщение передаётся кадром:
+//! `[версия: u8][длина полезной нагрузки: u32 BE][полезная нагрузка]`.
+//! Запрос содержит один байт команды, ответ - байт тега и данные.
+
+use crate::errors::ConnectionErrors;
+use std::{
+    fmt,
+    io::{Read, Write},
+};
+
+/// Текущая версия протокола
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Максимальный размер полезной нагрузки кадра
+pub const MAX_PAYLOAD_LEN: u32 = 64 * 1024;
+
+/// Команды, которые клиент отправляет розетке
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    TurnOn,
+    TurnOff,
+    Switch,
+    IsOn,
+    GetPower,
+    GetName,
+}
+
+impl Command {
+    fn code(self) -> u8 {
+        match self {
+            Command::TurnOn => 1,
+            Command::TurnOff => 2,
+            Command::Switch => 3,
+            Command::IsOn => 4,
+            Command::GetPower => 5,
+            Command::GetName => 6,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Command::TurnOn),
+            2 => Some(Command::TurnOff),
+            3 => Some(Command::Switch),
+            4 => Some(Command::IsOn),
+            5 => Some(Command::GetPower),
+            6 => Some(Command::GetName),
+            _ => None,
+        }
+    }
+
+    pub fn encode(self) -> Vec<u8> {
+        vec![self.code()]
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, ErrorCode> {
+        match payload {
+            [code] => Command::from_code(*code).ok_or(ErrorCode::UnknownCommand),
+            _ => Err(ErrorCode::MalformedFrame),
+        }
+    }
+}
+
+/// Коды ошибок, которые сервер возвращает клиенту
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnsupportedVersion,
+    UnknownCommand,
+    MalformedFrame,
+    Internal,
+    /// Розетка не в сети и не выполняет команду
+    DeviceOffline,
+}
+
+impl ErrorCode {
+    pub fn code(self) -> u8 {
+        match self {
+            ErrorCode::UnsupportedVersion => 1,
+            ErrorCode::UnknownCommand => 2,
+            ErrorCode::MalformedFrame => 3,
+            ErrorCode::Internal => 4,
+            ErrorCode::DeviceOffline => 5,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            1 => ErrorCode::UnsupportedVersion,
+            2 => ErrorCode::UnknownCommand,
+            3 => ErrorCode::MalformedFrame,
+            5 => ErrorCode::DeviceOffline,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::UnsupportedVersion => write!(f, "unsupported protocol version"),
+            ErrorCode::UnknownCommand => write!(f, "unknown command"),
+            ErrorCode::MalformedFrame => write!(f, "malformed frame"),
+            ErrorCode::Internal => write!(f, "internal server error"),
+            ErrorCode::DeviceOffline => write!(f, "device is offline"),
+        }
+    }
+}
+
+/// Ответы сервера на команды
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    Ok,
+    State(bool),
+    Power(f32),
+    Name(String),
+    Error(ErrorCode),
+}
+
+impl Response {
+    const OK: u8 = 0;
+    const STATE: u8 = 1;
+    const POWER: u8 = 2;
+    const NAME: u8 = 3;
+    const ERROR: u8 = 0xFF;
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Response::Ok => vec![Self::OK],
+            Response::State(is_on) => vec![Self::STATE, *is_on as u8],
+            Response::Power(power) => {
+                let mut out = vec![Self::POWER];
+                out.extend_from_slice(&power.to_be_bytes());
+                out
+            }
+            Response::Name(name) => {
+                let mut out = vec![Self::NAME];
+                out.extend_from_slice(name.as_bytes());
+                out
+            }
+            Response::Error(code) => vec![Self::ERROR, code.code()],
+        }
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, ConnectionErrors> {
+        let malformed = || ConnectionErrors::MalformedFrame(format!("bad response {:?}", payload));
+        let (tag, data) = payload.split_first().ok_or_else(malformed)?;
+        match (*tag, data) {
+            (Self::OK, []) => Ok(Response::Ok),
+            (Self::STATE, [state]) => Ok(Response::State(*state != 0)),
+            (Self::POWER, data) => {
+                let bytes: [u8; 4] = data.try_into().map_err(|_| malformed())?;
+                Ok(Response::Power(f32::from_be_bytes(bytes)))
+            }
+            (Self::NAME, data) => String::from_utf8(data.to_vec())
+                .map(Response::Name)
+                .map_err(|_| malformed()),
+            (Self::ERROR, [code]) => Ok(Response::Error(ErrorCode::from_code(*code))),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+/// Размер заголовка кадра: версия и длина
+pub(crate) const HEADER_LEN: usize = 5;
+
+/// Собирает кадр с текущей версией протокола
+pub(crate) fn encode_frame(payload: &[u8]) -> Result<Vec<u8>, ConnectionErrors> {
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_PAYLOAD_LEN)
+        .ok_or_else(|| ConnectionErrors::MalformedFrame(String::from("payload is too large")))?;
+    let mut frame = Vec::with_capacity(payload.len() + HEADER_LEN);
+    frame.push(PROTOCOL_VERSION);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Разбирает заголовок кадра и возвращает версию и длину полезной нагрузки
+pub(crate) fn decode_header(header: [u8; HEADER_LEN]) -> Result<(u8, usize), ConnectionErrors> {
+    let version = header[0];
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+    if len > MAX_PAYLOAD_LEN {
+        return Err(ConnectionErrors::MalformedFrame(format!(
+            "payload length {} exceeds limit",
+            len
+        )));
+    }
+    Ok((version, len as usize))
+}
+
+/// Записывает кадр с текущей версией протокола
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> Result<(), ConnectionErrors> {
+    writer.write_all(&encode_frame(payload)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Читает кадр и возвращает версию протокола и полезную нагрузку
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<(u8, Vec<u8>), ConnectionErrors> {
+    let mut header = [0u8; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    let (version, len) = decode_header(header)?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok((version, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_roundtrip() {
+        for command in [
+            Command::TurnOn,
+            Command::TurnOff,
+            Command::Switch,
+            Command::IsOn,
+            Command::GetPower,
+            Command::GetName,
+        ] {
+            assert_eq!(Command::decode(&command.encode()), Ok(command));
+        }
+        assert_eq!(Command::decode(&[42]), Err(ErrorCode::UnknownCommand));
+        assert_eq!(Command::decode(&[]), Err(ErrorCode::MalformedFrame));
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        for response in [
+            Response::Ok,
+            Response::State(true),
+            Response::Power(220.5),
+            Response::Name(String::from("Розетка")),
+            Response::Error(ErrorCode::UnknownCommand),
+        ] {
+            assert_eq!(Response::decode(&response.encode()).unwrap(), response);
+        }
+        assert!(Response::decode(&[2, 1]).is_err());
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &[1, 2, 3]).unwrap();
+        assert_eq!(buffer, vec![PROTOCOL_VERSION, 0, 0, 0, 3, 1, 2, 3]);
+        let (version, payload) = read_frame(&mut buffer.as_slice()).unwrap();
+        assert_eq!(version, PROTOCOL_VERSION);
+        assert_eq!(payload, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let header = [PROTOCOL_VERSION, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(matches!(
+            read_frame(&mut header.as_slice()),
+            Err(ConnectionErrors::MalformedFrame(_))
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+//! Поиск устройств по всему дому
+//!
+//! `SmartHome::devices` обходит устройства всех комнат вместе с ключом комнаты,
+//! а `DeviceQuery` отбирает их по типу, комнате, имени и состоянию, чтобы
+//! не нужно было заранее знать, в какой комнате находится устройство.
+
+use crate::{
+    report::{FieldValue, ReportField},
+    structures::{Device, DeviceId, SmartHome},
+};
+use std::fmt;
+
+/// Устройство дома вместе с комнатой, в которой оно находится
+#[derive(Debug, Clone, Copy)]
+pub struct HomeDevice<'a> {
+    /// Ключ комнаты
+    pub room: &'a str,
+    /// Ключ устройства в комнате
+    pub key: &'a str,
+    pub id: DeviceId,
+    pub device: &'a dyn Device,
+}
+
+type Predicate = dyn Fn(&dyn Device) -> bool + Send + Sync;
+
+/// Условия отбора устройств; устройство подходит, если выполнены все условия
+#[derive(Default)]
+pub struct DeviceQuery {
+    kind: Option<String>,
+    room: Option<String>,
+    name: Option<String>,
+    fields: Vec<ReportField>,
+    predicates: Vec<Box<Predicate>>,
+}
+
+impl DeviceQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Устройства заданного типа, например `socket`
+    pub fn kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    /// Устройства комнаты с ключом `room`
+    pub fn in_room(mut self, room: &str) -> Self {
+        self.room = Some(room.to_string());
+        self
+    }
+
+    /// Устройства, имя которых содержит `text` без учета регистра
+    pub fn name_contains(mut self, text: &str) -> Self {
+        self.name = Some(text.to_lowercase());
+        self
+    }
+
+    /// Устройства, у которых поле отчета `name` равно `value`,
+    /// например `field("is_on", FieldValue::Bool(true))`
+    pub fn field(mut self, name: &str, value: FieldValue) -> Self {
+        self.fields.push(ReportField::new(name, value));
+        self
+    }
+
+    /// Устройства типа `T`, для которых выполняется `predicate`
+    pub fn of_type<T: Device>(
+        mut self,
+        predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicates.push(Box::new(move |device: &dyn Device| {
+            device.downcast_ref::<T>().is_some_and(&predicate)
+        }));
+        self
+    }
+
+    /// Устройства, для которых выполняется `predicate`
+    pub fn matching(
+        mut self,
+        predicate: impl Fn(&dyn Device) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    pub fn matches(&self, entry: &HomeDevice<'_>) -> bool {
+        let device = entry.device;
+        if self
+            .kind
+            .as_deref()
+            .is_some_and(|kind| kind != device.kind())
+            || self.room.as_deref().is_some_and(|room| room != entry.room)
+            || self
+                .name
+                .as_deref()
+                .is_some_and(|name| !device.name().to_lowercase().contains(name))
+        {
+            return false;
+        }
+        if !self.fields.is_empty() {
+            let fields = device.fields();
+            if !self.fields.iter().all(|expected| fields.contains(expected)) {
+                return false;
+            }
+        }
+        self.predicates.iter().all(|predicate| predicate(device))
+    }
+}
+
+impl fmt::Debug for DeviceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceQuery")
+            .field("kind", &self.kind)
+            .field("room", &self.room)
+            .field("name", &self.name)
+            .field("fields", &self.fields)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
+
+impl SmartHome {
+    /// Все устройства дома вместе с ключами комнат в порядке `SortOrder`
+    pub fn devices(&self) -> impl Iterator<Item = HomeDevice<'_>> {
+        self.rooms().flat_map(|(room_key, room)| {
+            room.devices().map(move |(key, device)| HomeDevice {
+                room: room_key,
+                key,
+                id: room.device_id(key).expect("key comes from the room"),
+                device,
+            })
+        })
+    }
+
+    /// Устройства, подходящие под условия `query`
+    pub fn query<'a>(&'a self, query: &'a DeviceQuery) -> impl Iterator<Item = HomeDevice<'a>> {
+        self.devices().filter(move |entry| query.matches(entry))
+    }
+
+    /// Устройства с именем `name` во всех комнатах
+    pub fn find_by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = HomeDevice<'a>> {
+        self.devices()
+            .filter(move |entry| entry.device.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        add_room,
+        smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    };
+
+    fn create_home() -> SmartHome {
+        let mut kettle = SmartElectricalSoket::new(String::from("Kettle"), 2000.0);
+        kettle.turn_on().unwrap();
+        let kitchen = add_room!(
+            String::from("Кухня"),
+            ("S1", kettle),
+            (
+                "T1",
+                SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+            ),
+        );
+        let hall = add_room!(
+            String::from("Зал"),
+            ("S1", SmartElectricalSoket::new(String::from("Lamp"), 60.0)),
+            (
+                "T1",
+                SmartThermometer::new(String::from("Termo"), TempMeasures::F, 70.0)
+            ),
+        );
+        SmartHome::try_new(String::from("MyHome"), vec![kitchen, hall]).unwrap()
+    }
+
+    fn locations<'a>(devices: impl Iterator<Item = HomeDevice<'a>>) -> Vec<(&'a str, &'a str)> {
+        devices.map(|entry| (entry.room, entry.key)).collect()
+    }
+
+    #[test]
+    fn test_devices_iterate_all_rooms() {
+        let home = create_home();
+        assert_eq!(
+            locations(home.devices()),
+            vec![
+                ("Кухня", "S1"),
+                ("Кухня", "T1"),
+                ("Зал", "S1"),
+                ("Зал", "T1")
+            ]
+        );
+        let entry = home.devices().next().unwrap();
+        assert_eq!(home.locate_device(entry.id), Some(("Кухня", "S1")));
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let home = create_home();
+        let sockets = DeviceQuery::new().kind("socket");
+        assert_eq!(
+            locations(home.query(&sockets)),
+            vec![("Кухня", "S1"), ("Зал", "S1")]
+        );
+
+        let on = DeviceQuery::new().field("is_on", FieldValue::Bool(true));
+        assert_eq!(locations(home.query(&on)), vec![("Кухня", "S1")]);
+        let on = DeviceQuery::new().of_type::<SmartElectricalSoket>(|socket| socket.is_on());
+        assert_eq!(locations(home.query(&on)), vec![("Кухня", "S1")]);
+
+        let hall_thermo = DeviceQuery::new().in_room("Зал").name_contains("TER");
+        assert_eq!(locations(home.query(&hall_thermo)), vec![("Зал", "T1")]);
+        let fahrenheit = DeviceQuery::new().matching(|device| device.status().contains("° F"));
+        assert_eq!(locations(home.query(&fahrenheit)), vec![("Зал", "T1")]);
+        assert_eq!(home.query(&DeviceQuery::new().kind("lamp")).count(), 0);
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        let home = create_home();
+        assert_eq!(
+            locations(home.find_by_name("Termo")),
+            vec![("Кухня", "T1"), ("Зал", "T1")]
+        );
+        assert_eq!(home.find_by_name("termo").count(), 0);
+    }
+}
@@ -0,0 +1,425 @@
+//! Структурированные отчеты о состоянии дома
+//!
+//! Отчет строится как модель дом → комнаты → устройства и может быть
+//! представлен текстом, JSON, CSV или Markdown.
+
+use crate::structures::{Device, DeviceId, Room, RoomId, SmartHome};
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Значение поля устройства в отчете
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    Number(f32),
+    Text(String),
+}
+
+impl FieldValue {
+    /// Разбирает значение из строки: `true` и `false` - логические значения,
+    /// числа - числовые, остальное - текст
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "true" => FieldValue::Bool(true),
+            "false" => FieldValue::Bool(false),
+            _ => value
+                .parse()
+                .map_or_else(|_| FieldValue::Text(value.to_string()), FieldValue::Number),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        match self {
+            FieldValue::Bool(value) => value.to_string(),
+            FieldValue::Number(value) if value.is_finite() => value.to_string(),
+            FieldValue::Number(_) => String::from("null"),
+            FieldValue::Text(value) => json_string(value),
+        }
+    }
+
+    fn to_plain(&self) -> String {
+        match self {
+            FieldValue::Bool(value) => value.to_string(),
+            FieldValue::Number(value) => value.to_string(),
+            FieldValue::Text(value) => value.clone(),
+        }
+    }
+}
+
+/// Именованное поле с состоянием устройства
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportField {
+    pub name: String,
+    pub value: FieldValue,
+}
+
+impl ReportField {
+    pub fn new(name: &str, value: FieldValue) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReport {
+    pub key: String,
+    /// Постоянный идентификатор, если отчет построен по устройству комнаты
+    pub id: Option<DeviceId>,
+    pub name: String,
+    pub kind: String,
+    pub status: String,
+    /// Человекочитаемое описание устройства
+    pub description: String,
+    pub fields: Vec<ReportField>,
+}
+
+impl DeviceReport {
+    pub fn new(key: &str, device: &dyn Device) -> Self {
+        Self {
+            key: key.to_string(),
+            id: None,
+            name: device.name().to_string(),
+            kind: device.kind().to_string(),
+            status: device.status(),
+            description: device.to_string(),
+            fields: device.fields(),
+        }
+    }
+
+    pub fn with_id(mut self, id: Option<DeviceId>) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Устройство в виде объекта JSON
+    pub fn render_json(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| format!("{}:{}", json_string(&field.name), field.value.to_json()))
+            .collect();
+        format!(
+            "{{\"key\":{},\"id\":{},\"name\":{},\"kind\":{},\"status\":{},\"fields\":{{{}}}}}",
+            json_string(&self.key),
+            self.id.map_or(String::from("null"), |id| id.to_string()),
+            json_string(&self.name),
+            json_string(&self.kind),
+            json_string(&self.status),
+            fields.join(",")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomReport {
+    pub key: String,
+    pub id: RoomId,
+    pub name: String,
+    pub devices: Vec<DeviceReport>,
+    /// Потребление в кВт·ч до момента, переданного в `with_energy_until`,
+    /// если в комнате есть устройства с учетом энергии
+    pub energy_kwh: Option<f32>,
+}
+
+impl RoomReport {
+    pub fn new(key: &str, room: &Room) -> Self {
+        Self {
+            key: key.to_string(),
+            id: room.id(),
+            name: room.get_name().to_string(),
+            devices: room
+                .devices()
+                .map(|(key, device)| DeviceReport::new(key, device).with_id(room.device_id(key)))
+                .collect(),
+            energy_kwh: None,
+        }
+    }
+
+    /// Добавляет в отчет потребление комнаты до момента `until`.
+    /// Потребление зависит от времени, поэтому по умолчанию в отчет не входит.
+    pub fn with_energy_until(mut self, room: &Room, until: SystemTime) -> Self {
+        self.energy_kwh = room
+            .devices()
+            .filter_map(|(_, device)| device.energy_between(UNIX_EPOCH, until))
+            .reduce(|sum, energy| sum + energy)
+            .map(|energy| energy / 1000.0);
+        self
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.render_text(),
+            ReportFormat::Json => self.render_json(),
+            ReportFormat::Csv => render_csv(std::slice::from_ref(self)),
+            ReportFormat::Markdown => self.render_markdown(),
+        }
+    }
+
+    fn render_text(&self) -> String {
+        let mut out = format!("Комната '{}': \n", self.name);
+        for device in &self.devices {
+            let _ = writeln!(out, "| -- {}", device.description);
+        }
+        if let Some(energy) = self.energy_kwh {
+            let _ = writeln!(out, "| Потреблено: {:.3} кВт·ч", energy);
+        }
+        out
+    }
+
+    fn render_json(&self) -> String {
+        let devices: Vec<String> = self.devices.iter().map(DeviceReport::render_json).collect();
+        format!(
+            "{{\"key\":{},\"id\":{},\"name\":{},\"devices\":[{}],\"energy_kwh\":{}}}",
+            json_string(&self.key),
+            self.id,
+            json_string(&self.name),
+            devices.join(","),
+            energy_json(self.energy_kwh)
+        )
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut out = format!("## Комната '{}'\n\n", escape_markdown(&self.name));
+        out.push_str("| Ключ | Имя | Тип | Состояние |\n");
+        out.push_str("| --- | --- | --- | --- |\n");
+        for device in &self.devices {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                escape_markdown(&device.key),
+                escape_markdown(&device.name),
+                escape_markdown(&device.kind),
+                escape_markdown(&device.status)
+            );
+        }
+        if let Some(energy) = self.energy_kwh {
+            let _ = writeln!(out, "\nПотреблено: {:.3} кВт·ч", energy);
+        }
+        out
+    }
+}
+
+impl From<&Room> for RoomReport {
+    fn from(room: &Room) -> Self {
+        RoomReport::new(room.get_name(), room)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HomeReport {
+    pub name: String,
+    pub rooms: Vec<RoomReport>,
+    /// Потребление всего дома в кВт·ч, см. `HomeReport::with_energy_until`
+    pub energy_kwh: Option<f32>,
+}
+
+impl From<&SmartHome> for HomeReport {
+    fn from(home: &SmartHome) -> Self {
+        Self {
+            name: home.get_name().to_string(),
+            rooms: home
+                .rooms()
+                .map(|(key, room)| RoomReport::new(key, room))
+                .collect(),
+            energy_kwh: None,
+        }
+    }
+}
+
+impl HomeReport {
+    /// Отчет о доме вместе с потреблением комнат и всего дома до момента `until`
+    pub fn with_energy_until(home: &SmartHome, until: SystemTime) -> Self {
+        let rooms: Vec<RoomReport> = home
+            .rooms()
+            .map(|(key, room)| RoomReport::new(key, room).with_energy_until(room, until))
+            .collect();
+        let energy_kwh = rooms
+            .iter()
+            .filter_map(|room| room.energy_kwh)
+            .reduce(|sum, energy| sum + energy);
+        Self {
+            name: home.get_name().to_string(),
+            rooms,
+            energy_kwh,
+        }
+    }
+}
+
+/// Формат представления отчета
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Текстовый отчет в том же виде, что и `Report::report`
+    Text,
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl HomeReport {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => {
+                let mut out = format!("Отчет для дома: {}\n\n", self.name);
+                for room in &self.rooms {
+                    out.push_str(&room.render_text());
+                }
+                if let Some(energy) = self.energy_kwh {
+                    let _ = writeln!(out, "Всего потреблено: {:.3} кВт·ч", energy);
+                }
+                out
+            }
+            ReportFormat::Json => {
+                let rooms: Vec<String> = self.rooms.iter().map(RoomReport::render_json).collect();
+                format!(
+                    "{{\"name\":{},\"rooms\":[{}],\"energy_kwh\":{}}}",
+                    json_string(&self.name),
+                    rooms.join(","),
+                    energy_json(self.energy_kwh)
+                )
+            }
+            ReportFormat::Csv => render_csv(&self.rooms),
+            ReportFormat::Markdown => {
+                let rooms: Vec<String> =
+                    self.rooms.iter().map(RoomReport::render_markdown).collect();
+                let mut out = format!(
+                    "# Отчет для дома: {}\n\n{}",
+                    escape_markdown(&self.name),
+                    rooms.join("\n")
+                );
+                if let Some(energy) = self.energy_kwh {
+                    let _ = writeln!(out, "\n**Всего потреблено: {:.3} кВт·ч**", energy);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Одна строка на устройство; для каждого поля устройств отдельная колонка
+fn render_csv(rooms: &[RoomReport]) -> String {
+    let mut field_names: Vec<&str> = Vec::new();
+    for device in rooms.iter().flat_map(|room| &room.devices) {
+        for field in &device.fields {
+            if !field_names.contains(&field.name.as_str()) {
+                field_names.push(&field.name);
+            }
+        }
+    }
+
+    let mut out = String::from("room_key,room_name,device_key,device_name,kind,status");
+    for name in &field_names {
+        out.push(',');
+        out.push_str(&csv_cell(name));
+    }
+    out.push('\n');
+    for room in rooms {
+        for device in &room.devices {
+            let mut row = vec![
+                csv_cell(&room.key),
+                csv_cell(&room.name),
+                csv_cell(&device.key),
+                csv_cell(&device.name),
+                csv_cell(&device.kind),
+                csv_cell(&device.status),
+            ];
+            for name in &field_names {
+                let value = device
+                    .fields
+                    .iter()
+                    .find(|field| field.name == *name)
+                    .map(|field| csv_cell(&field.value.to_plain()))
+                    .unwrap_or_default();
+                row.push(value);
+            }
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+impl SmartHome {
+    /// Отчет о доме в выбранном формате
+    pub fn report_as(&self, format: ReportFormat) -> String {
+        HomeReport::from(self).render(format)
+    }
+}
+
+impl Room {
+    /// Отчет о комнате в выбранном формате
+    pub fn report_as(&self, format: ReportFormat) -> String {
+        RoomReport::from(self).render(format)
+    }
+}
+
+fn energy_json(energy_kwh: Option<f32>) -> String {
+    energy_kwh.map_or(String::from("null"), |energy| {
+        FieldValue::Number(energy).to_json()
+    })
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub(crate) fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_markdown(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string_escaping() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_csv_cell_quoting() {
+        assert_eq!(csv_cell("plain"), "plain");
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_field_value_parse() {
+        assert_eq!(FieldValue::parse("true"), FieldValue::Bool(true));
+        assert_eq!(FieldValue::parse("-2.5"), FieldValue::Number(-2.5));
+        assert_eq!(FieldValue::parse("C"), FieldValue::Text(String::from("C")));
+    }
+
+    #[test]
+    fn test_markdown_escaping() {
+        assert_eq!(escape_markdown("a|b"), "a\\|b");
+    }
+}
@@ -0,0 +1,569 @@
+//! Правила автоматизации
+//!
+//! Правило состоит из условия над состоянием устройств и списка действий,
+//! которые выполняются, когда условие выполнено. Действия идемпотентны:
+//! действие "срабатывает", только если оно изменит состояние устройства.
+//!
+//! Для `SmartHome` правила применяются по запросу: `RuleEngine::process`
+//! учитывает изменения с прошлого вызова. Для `SharedHome` движок можно
+//! запустить в отдельном потоке через `RuleEngine::spawn`, тогда правила
+//! проверяются после каждого изменения дома без участия вызывающего.
+
+use crate::{
+    errors::SmartHomeErrors,
+    events::HomeEvent,
+    shared::SharedHome,
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::{Device, SmartHome},
+};
+use std::{
+    fmt,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Максимальное число проходов `RuleEngine::process` для одного набора событий.
+/// Ограничивает цепочки правил, которые включают друг друга по кругу.
+const MAX_PASSES: usize = 8;
+
+#[cfg(feature = "serde")]
+fn default_measure() -> TempMeasures {
+    TempMeasures::C
+}
+
+/// Условие над состоянием дома
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Condition {
+    /// Температура термометра выше значения в указанных единицах
+    TemperatureAbove {
+        room: String,
+        device: String,
+        value: f32,
+        #[cfg_attr(feature = "serde", serde(default = "default_measure"))]
+        measure: TempMeasures,
+    },
+    /// Температура термометра ниже значения в указанных единицах
+    TemperatureBelow {
+        room: String,
+        device: String,
+        value: f32,
+        #[cfg_attr(feature = "serde", serde(default = "default_measure"))]
+        measure: TempMeasures,
+    },
+    /// Розетка включена или выключена
+    SocketIs {
+        room: String,
+        device: String,
+        on: bool,
+    },
+    /// Суммарная мощность розеток комнаты или, без комнаты, всего дома выше значения
+    PowerAbove {
+        #[cfg_attr(feature = "serde", serde(default))]
+        room: Option<String>,
+        watts: f32,
+    },
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+fn find_device<'a, T: Device>(home: &'a SmartHome, room: &str, device: &str) -> Option<&'a T> {
+    home.get_device_from_room(room, device)
+        .ok()?
+        .downcast_ref::<T>()
+}
+
+fn power(home: &SmartHome, room: Option<&str>) -> f32 {
+    home.rooms()
+        .filter(|(key, _)| room.is_none_or(|room| room == *key))
+        .flat_map(|(_, room)| room.devices())
+        .filter_map(|(_, device)| device.downcast_ref::<SmartElectricalSoket>())
+        .map(SmartElectricalSoket::get_power)
+        .sum()
+}
+
+impl Condition {
+    /// Проверяет условие. Отсутствующие устройства условие не выполняют.
+    pub fn is_met(&self, home: &SmartHome) -> bool {
+        match self {
+            Condition::TemperatureAbove {
+                room,
+                device,
+                value,
+                measure,
+            } => find_device::<SmartThermometer>(home, room, device)
+                .is_some_and(|thermo| thermo.get_tempreture_in(measure) > *value),
+            Condition::TemperatureBelow {
+                room,
+                device,
+                value,
+                measure,
+            } => find_device::<SmartThermometer>(home, room, device)
+                .is_some_and(|thermo| thermo.get_tempreture_in(measure) < *value),
+            Condition::SocketIs { room, device, on } => {
+                find_device::<SmartElectricalSoket>(home, room, device)
+                    .is_some_and(|socket| socket.is_on() == *on)
+            }
+            Condition::PowerAbove { room, watts } => power(home, room.as_deref()) > *watts,
+            Condition::All(conditions) => conditions.iter().all(|cond| cond.is_met(home)),
+            Condition::Any(conditions) => conditions.iter().any(|cond| cond.is_met(home)),
+            Condition::Not(condition) => !condition.is_met(home),
+        }
+    }
+}
+
+/// Действие над устройством
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Action {
+    TurnOn {
+        room: String,
+        device: String,
+    },
+    TurnOff {
+        room: String,
+        device: String,
+    },
+    SetMeasure {
+        room: String,
+        device: String,
+        measure: TempMeasures,
+    },
+}
+
+/// Ошибка выполнения действия
+#[derive(Debug)]
+pub enum ActionError {
+    /// Ошибка дома, например `SmartHomeErrors::WrongDeviceType`, если устройство
+    /// не того типа, который нужен действию
+    Home(SmartHomeErrors),
+    /// Действие противоречит действию другого правила и не выполнялось
+    Conflict { rule: String },
+}
+
+impl ActionError {
+    /// Стабильный код ошибки; для ошибок дома совпадает с `SmartHomeErrors::code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Home(err) => err.code(),
+            Self::Conflict { .. } => "conflict",
+        }
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Home(err) => write!(f, "{}", err),
+            Self::Conflict { rule } => write!(f, "Conflicts with rule {}", rule),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Home(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SmartHomeErrors> for ActionError {
+    fn from(value: SmartHomeErrors) -> Self {
+        Self::Home(value)
+    }
+}
+
+impl Action {
+    /// Комната и устройство, над которыми выполняется действие
+    pub fn target(&self) -> (&str, &str) {
+        match self {
+            Action::TurnOn { room, device }
+            | Action::TurnOff { room, device }
+            | Action::SetMeasure { room, device, .. } => (room, device),
+        }
+    }
+
+    /// Два действия противоречат друг другу, если приводят одно устройство в разные состояния
+    pub fn conflicts_with(&self, other: &Action) -> bool {
+        if self.target() != other.target() {
+            return false;
+        }
+        match (self, other) {
+            (Action::TurnOn { .. }, Action::TurnOff { .. })
+            | (Action::TurnOff { .. }, Action::TurnOn { .. }) => true,
+            (
+                Action::SetMeasure { measure, .. },
+                Action::SetMeasure {
+                    measure: other_measure,
+                    ..
+                },
+            ) => measure != other_measure,
+            _ => false,
+        }
+    }
+
+    fn wrong_type(&self, expected: &str) -> ActionError {
+        SmartHomeErrors::wrong_device_type(self.target().1, expected).into()
+    }
+
+    /// Проверяет, изменит ли действие состояние устройства
+    pub fn would_change(&self, home: &SmartHome) -> Result<bool, ActionError> {
+        let (room, device) = self.target();
+        let device = home.get_device_from_room(room, device)?;
+        match self {
+            Action::TurnOn { .. } | Action::TurnOff { .. } => {
+                let socket = device
+                    .downcast_ref::<SmartElectricalSoket>()
+                    .ok_or_else(|| self.wrong_type("socket"))?;
+                Ok(socket.is_on() != matches!(self, Action::TurnOn { .. }))
+            }
+            Action::SetMeasure { measure, .. } => {
+                let thermo = device
+                    .downcast_ref::<SmartThermometer>()
+                    .ok_or_else(|| self.wrong_type("thermometer"))?;
+                Ok(thermo.get_temp_measure() != measure)
+            }
+        }
+    }
+
+    /// Выполняет действие, сообщая подписчикам дома об изменениях
+    pub fn apply(&self, home: &mut SmartHome) -> Result<(), ActionError> {
+        let (room, device) = self.target();
+        home.update_device(room, device, |device| self.apply_to(device))?
+    }
+
+    /// Выполняет действие над разделяемым домом, как `apply`
+    pub fn apply_shared(&self, home: &SharedHome) -> Result<(), ActionError> {
+        let (room, device) = self.target();
+        home.update_device(room, device, |device| self.apply_to(device))?
+    }
+
+    fn apply_to(&self, device: &mut dyn Device) -> Result<(), ActionError> {
+        match self {
+            Action::TurnOn { .. } | Action::TurnOff { .. } => {
+                let socket = device
+                    .downcast_mut::<SmartElectricalSoket>()
+                    .ok_or_else(|| self.wrong_type("socket"))?;
+                if matches!(self, Action::TurnOn { .. }) {
+                    socket.turn_on()?;
+                } else {
+                    socket.turn_off();
+                }
+                Ok(())
+            }
+            Action::SetMeasure { measure, .. } => {
+                let thermo = device
+                    .downcast_mut::<SmartThermometer>()
+                    .ok_or_else(|| self.wrong_type("thermometer"))?;
+                if thermo.get_temp_measure() != measure {
+                    thermo.convert_to(*measure);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rule {
+    pub name: String,
+    pub when: Condition,
+    pub then: Vec<Action>,
+}
+
+impl Rule {
+    pub fn new(name: &str, when: Condition, then: Vec<Action>) -> Self {
+        Self {
+            name: name.to_string(),
+            when,
+            then,
+        }
+    }
+}
+
+/// Пара правил, действия которых противоречат друг другу
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleConflict {
+    pub first: String,
+    pub second: String,
+    pub room: String,
+    pub device: String,
+}
+
+/// Действие, которое сработает при текущем состоянии дома
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedAction {
+    pub rule: String,
+    pub action: Action,
+    /// Правило, которое одновременно требует противоположного действия.
+    /// Такое действие не будет выполнено.
+    pub conflict: Option<String>,
+}
+
+/// Результат выполнения сработавшего действия
+#[derive(Debug)]
+pub struct ActionOutcome {
+    pub rule: String,
+    pub action: Action,
+    pub result: Result<(), ActionError>,
+}
+
+/// Набор правил, применяемых к дому
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    events: Option<Receiver<HomeEvent>>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            events: None,
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Находит правила, которые могут одновременно приводить устройство в разные состояния
+    pub fn conflicts(&self) -> Vec<RuleConflict> {
+        let mut conflicts = Vec::new();
+        for (index, first) in self.rules.iter().enumerate() {
+            for second in &self.rules[index + 1..] {
+                for action in &first.then {
+                    if second.then.iter().any(|other| action.conflicts_with(other)) {
+                        let (room, device) = action.target();
+                        conflicts.push(RuleConflict {
+                            first: first.name.clone(),
+                            second: second.name.clone(),
+                            room: room.to_string(),
+                            device: device.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Пробный прогон: действия, которые сработали бы сейчас, без изменения дома.
+    /// Действия над отсутствующими устройствами или устройствами другого типа
+    /// также попадают в план, чтобы `evaluate` сообщил об ошибке.
+    pub fn dry_run(&self, home: &SmartHome) -> Vec<PlannedAction> {
+        let triggered: Vec<(&str, &Action)> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.when.is_met(home))
+            .flat_map(|rule| rule.then.iter().map(|action| (rule.name.as_str(), action)))
+            .collect();
+        triggered
+            .iter()
+            .filter(|(_, action)| action.would_change(home).unwrap_or(true))
+            .map(|(rule, action)| PlannedAction {
+                rule: rule.to_string(),
+                action: (*action).clone(),
+                conflict: triggered
+                    .iter()
+                    .find(|(other_rule, other)| other_rule != rule && action.conflicts_with(other))
+                    .map(|(other_rule, _)| other_rule.to_string()),
+            })
+            .collect()
+    }
+
+    /// Выполняет сработавшие действия.
+    /// Противоречащие друг другу действия разных правил не выполняются.
+    pub fn evaluate(&self, home: &mut SmartHome) -> Vec<ActionOutcome> {
+        Self::execute(self.dry_run(home), |action| action.apply(home))
+    }
+
+    /// То же, что `evaluate`, для разделяемого дома.
+    /// Сработавшие действия определяются по снимку дома.
+    pub fn evaluate_shared(&self, home: &SharedHome) -> Vec<ActionOutcome> {
+        Self::execute(self.dry_run(&home.snapshot()), |action| {
+            action.apply_shared(home)
+        })
+    }
+
+    fn execute(
+        plans: Vec<PlannedAction>,
+        mut apply: impl FnMut(&Action) -> Result<(), ActionError>,
+    ) -> Vec<ActionOutcome> {
+        plans
+            .into_iter()
+            .map(|plan| {
+                let result = match plan.conflict {
+                    Some(rule) => Err(ActionError::Conflict { rule }),
+                    None => apply(&plan.action),
+                };
+                ActionOutcome {
+                    rule: plan.rule,
+                    action: plan.action,
+                    result,
+                }
+            })
+            .collect()
+    }
+
+    /// Подписывает движок на события дома для `process`
+    pub fn watch(&mut self, home: &SmartHome) {
+        self.events = Some(home.events().channel());
+    }
+
+    /// Применяет правила, если с прошлого вызова в доме были изменения.
+    /// Изменения, вызванные самими правилами, обрабатываются повторно,
+    /// но не более `MAX_PASSES` раз. Об ошибке одного и того же действия
+    /// сообщается один раз за вызов.
+    pub fn process(&mut self, home: &mut SmartHome) -> Vec<ActionOutcome> {
+        let mut outcomes = Vec::new();
+        let Some(events) = &self.events else {
+            return outcomes;
+        };
+        for _ in 0..MAX_PASSES {
+            if events.try_iter().count() == 0 {
+                break;
+            }
+            for outcome in self.evaluate(home) {
+                push_outcome(&mut outcomes, outcome);
+            }
+        }
+        // События последнего прохода уже учтены в состоянии дома
+        events.try_iter().for_each(drop);
+        outcomes
+    }
+
+    /// Запускает движок в отдельном потоке: правила проверяются после каждого
+    /// изменения дома, как в `process`. Результаты сработавших действий
+    /// доступны через `RuleHandle::outcomes`.
+    pub fn spawn(self, home: SharedHome) -> RuleHandle {
+        let (sender, inputs) = mpsc::channel();
+        let events = sender.clone();
+        let subscription = home.events().subscribe(move |_| {
+            let _ = events.send(Input::Changed);
+        });
+        let (results, outcomes) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut stop = false;
+            while !stop && matches!(inputs.recv(), Ok(Input::Changed)) {
+                let mut batch = Vec::new();
+                for _ in 0..MAX_PASSES {
+                    for outcome in self.evaluate_shared(&home) {
+                        push_outcome(&mut batch, outcome);
+                    }
+                    let pending: Vec<Input> = inputs.try_iter().collect();
+                    stop = pending.contains(&Input::Stop);
+                    if stop || pending.is_empty() {
+                        break;
+                    }
+                }
+                // События последнего прохода уже учтены в состоянии дома
+                stop |= inputs.try_iter().any(|input| input == Input::Stop);
+                for outcome in batch {
+                    let _ = results.send(outcome);
+                }
+            }
+            home.events().unsubscribe(subscription);
+        });
+        RuleHandle {
+            stop: sender,
+            outcomes,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Добавляет результат, если об ошибке того же действия еще не сообщалось
+fn push_outcome(outcomes: &mut Vec<ActionOutcome>, outcome: ActionOutcome) {
+    let reported = outcome.result.is_err()
+        && outcomes.iter().any(|seen| {
+            seen.result.is_err() && seen.rule == outcome.rule && seen.action == outcome.action
+        });
+    if !reported {
+        outcomes.push(outcome);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Changed,
+    Stop,
+}
+
+/// Управление движком, запущенным через `RuleEngine::spawn`.
+/// Удаление без `stop` также останавливает поток.
+#[derive(Debug)]
+pub struct RuleHandle {
+    stop: Sender<Input>,
+    outcomes: Receiver<ActionOutcome>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RuleHandle {
+    /// Результаты сработавших действий в порядке выполнения
+    pub fn outcomes(&self) -> &Receiver<ActionOutcome> {
+        &self.outcomes
+    }
+
+    /// Останавливает движок после обработки уже полученных изменений
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.stop.send(Input::Stop);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RuleHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(feature = "serde")]
+mod config {
+    use super::{Rule, RuleEngine};
+    use crate::{errors::SmartHomeErrors, persistence::HomeFormat};
+    use std::{fs, path::Path};
+
+    #[derive(serde::Deserialize)]
+    struct RulesFile {
+        #[serde(default)]
+        rules: Vec<Rule>,
+    }
+
+    impl RuleEngine {
+        /// Разбирает правила из строки в заданном формате
+        pub fn from_str_with(content: &str, format: HomeFormat) -> Result<Self, SmartHomeErrors> {
+            let file: RulesFile = match format {
+                HomeFormat::Json => serde_json::from_str(content)
+                    .map_err(|err| SmartHomeErrors::Parse(Box::new(err)))?,
+                HomeFormat::Toml => {
+                    toml::from_str(content).map_err(|err| SmartHomeErrors::Parse(Box::new(err)))?
+                }
+            };
+            Ok(Self::new(file.rules))
+        }
+
+        /// Загружает правила из файла, формат определяется по расширению
+        pub fn load(path: impl AsRef<Path>) -> Result<Self, SmartHomeErrors> {
+            let path = path.as_ref();
+            let format = HomeFormat::from_path(path)?;
+            Self::from_str_with(&fs::read_to_string(path)?, format)
+        }
+    }
+}
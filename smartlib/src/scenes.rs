@@ -0,0 +1,374 @@
+//! Группы устройств и сцены
+//!
+//! Группа - именованный набор устройств из любых комнат, сцена - набор
+//! желаемых состояний для устройств и групп. Устройства задаются постоянными
+//! идентификаторами, поэтому группы и сцены не ломаются при переносе и
+//! переименовании. Применение сцены возвращает отчет о том, какие устройства
+//! изменились, а какие не удалось привести к нужному состоянию.
+
+use crate::{
+    errors::SmartHomeErrors,
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::{Device, DeviceId, SmartHome, check_key},
+};
+use indexmap::IndexMap;
+use std::{fmt, str::FromStr};
+
+/// Желаемое состояние устройства
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DeviceState {
+    /// Розетка включена
+    On,
+    /// Розетка выключена
+    Off,
+    /// Термометр показывает температуру в заданных единицах
+    Measure(TempMeasures),
+}
+
+impl DeviceState {
+    /// Приводит устройство к состоянию; `true`, если состояние устройства изменилось
+    pub fn apply(self, key: &str, device: &mut dyn Device) -> Result<bool, SmartHomeErrors> {
+        let before = device.status();
+        match self {
+            DeviceState::On | DeviceState::Off => {
+                let socket = device
+                    .downcast_mut::<SmartElectricalSoket>()
+                    .ok_or_else(|| SmartHomeErrors::wrong_device_type(key, "socket"))?;
+                if self == DeviceState::On {
+                    socket.turn_on()?;
+                } else {
+                    socket.turn_off();
+                }
+            }
+            DeviceState::Measure(measure) => device
+                .downcast_mut::<SmartThermometer>()
+                .ok_or_else(|| SmartHomeErrors::wrong_device_type(key, "thermometer"))?
+                .convert_to(measure),
+        }
+        Ok(device.status() != before)
+    }
+}
+
+/// Разбирает `on`, `off` или единицы измерения `c`, `f`, `k`, `r`
+impl FromStr for DeviceState {
+    type Err = SmartHomeErrors;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "on" => Ok(DeviceState::On),
+            "off" => Ok(DeviceState::Off),
+            "c" => Ok(DeviceState::Measure(TempMeasures::C)),
+            "f" => Ok(DeviceState::Measure(TempMeasures::F)),
+            "k" => Ok(DeviceState::Measure(TempMeasures::K)),
+            "r" => Ok(DeviceState::Measure(TempMeasures::R)),
+            _ => Err(SmartHomeErrors::invalid_value(
+                "state",
+                format!("unknown state '{}'", value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            DeviceState::On => "on",
+            DeviceState::Off => "off",
+            DeviceState::Measure(TempMeasures::C) => "c",
+            DeviceState::Measure(TempMeasures::F) => "f",
+            DeviceState::Measure(TempMeasures::K) => "k",
+            DeviceState::Measure(TempMeasures::R) => "r",
+        };
+        f.write_str(state)
+    }
+}
+
+/// Устройство или группа, к которым относится действие сцены
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SceneTarget {
+    Device(DeviceId),
+    Group(String),
+}
+
+impl fmt::Display for SceneTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneTarget::Device(id) => write!(f, "device {}", id),
+            SceneTarget::Group(name) => write!(f, "group {}", name),
+        }
+    }
+}
+
+/// Действие сцены: привести цель к состоянию `state`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneAction {
+    pub target: SceneTarget,
+    pub state: DeviceState,
+}
+
+/// Сцена: набор действий над устройствами и группами.
+/// Если к устройству относится несколько действий, применяется последнее,
+/// а само устройство изменяется один раз.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    actions: Vec<SceneAction>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Добавляет действие над одним устройством
+    pub fn device(self, id: DeviceId, state: DeviceState) -> Self {
+        self.action(SceneTarget::Device(id), state)
+    }
+
+    /// Добавляет действие над всеми устройствами группы
+    pub fn group(self, name: &str, state: DeviceState) -> Self {
+        self.action(SceneTarget::Group(name.to_string()), state)
+    }
+
+    pub fn action(mut self, target: SceneTarget, state: DeviceState) -> Self {
+        self.actions.push(SceneAction { target, state });
+        self
+    }
+
+    pub fn actions(&self) -> &[SceneAction] {
+        &self.actions
+    }
+}
+
+/// Устройство, к которому была применена сцена
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneDevice {
+    pub id: DeviceId,
+    /// Ключ комнаты
+    pub room: String,
+    /// Ключ устройства в комнате
+    pub key: String,
+}
+
+/// Цель сцены, которую не удалось привести к нужному состоянию
+#[derive(Debug)]
+pub struct SceneFailure {
+    pub target: SceneTarget,
+    pub error: SmartHomeErrors,
+}
+
+/// Результат применения сцены. Устройства перечислены в порядке первого
+/// упоминания в сцене; ошибки неизвестных групп идут перед ошибками устройств.
+#[derive(Debug, Default)]
+pub struct SceneReport {
+    /// Устройства, состояние которых изменилось
+    pub changed: Vec<SceneDevice>,
+    /// Устройства, которые уже были в нужном состоянии
+    pub unchanged: Vec<SceneDevice>,
+    pub failed: Vec<SceneFailure>,
+}
+
+impl SceneReport {
+    /// Все цели сцены приведены к нужному состоянию
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for SceneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Изменено: {}, без изменений: {}, ошибок: {}",
+            self.changed.len(),
+            self.unchanged.len(),
+            self.failed.len()
+        )?;
+        for device in &self.changed {
+            writeln!(f, "| -- {}/{}: изменено", device.room, device.key)?;
+        }
+        for failure in &self.failed {
+            writeln!(f, "| -- {}: {}", failure.target, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Группы и сцены дома
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SceneBook {
+    pub(crate) groups: IndexMap<String, Vec<DeviceId>>,
+    pub(crate) scenes: IndexMap<String, Scene>,
+}
+
+/// Сцена с раскрытыми группами: итоговое состояние каждого устройства
+pub(crate) struct ScenePlan {
+    states: IndexMap<DeviceId, DeviceState>,
+    failed: Vec<SceneFailure>,
+}
+
+impl SceneBook {
+    pub(crate) fn plan_for(&self, name: &str) -> Result<ScenePlan, SmartHomeErrors> {
+        let scene = self
+            .scenes
+            .get(name)
+            .ok_or_else(|| SmartHomeErrors::SceneNotFound(name.to_string()))?;
+        Ok(self.plan(scene))
+    }
+
+    pub(crate) fn plan(&self, scene: &Scene) -> ScenePlan {
+        let mut plan = ScenePlan {
+            states: IndexMap::new(),
+            failed: Vec::new(),
+        };
+        for action in &scene.actions {
+            let devices = match &action.target {
+                SceneTarget::Device(id) => std::slice::from_ref(id),
+                SceneTarget::Group(name) => match self.groups.get(name) {
+                    Some(members) => members.as_slice(),
+                    None => {
+                        plan.failed.push(SceneFailure {
+                            target: action.target.clone(),
+                            error: SmartHomeErrors::GroupNotFound(name.clone()),
+                        });
+                        continue;
+                    }
+                },
+            };
+            for id in devices {
+                plan.states.insert(*id, action.state);
+            }
+        }
+        plan
+    }
+}
+
+impl ScenePlan {
+    /// Применяет состояния; `apply` меняет одно устройство и сообщает, изменилось ли оно
+    pub(crate) fn run(
+        self,
+        mut apply: impl FnMut(DeviceId, DeviceState) -> Result<(SceneDevice, bool), SmartHomeErrors>,
+    ) -> SceneReport {
+        let mut report = SceneReport {
+            failed: self.failed,
+            ..SceneReport::default()
+        };
+        for (id, state) in self.states {
+            match apply(id, state) {
+                Ok((device, true)) => report.changed.push(device),
+                Ok((device, false)) => report.unchanged.push(device),
+                Err(error) => report.failed.push(SceneFailure {
+                    target: SceneTarget::Device(id),
+                    error,
+                }),
+            }
+        }
+        report
+    }
+}
+
+impl SmartHome {
+    /// Задает группу устройств, заменяя группу с тем же именем.
+    /// Все устройства группы должны быть в доме.
+    pub fn define_group(
+        &mut self,
+        name: String,
+        devices: Vec<DeviceId>,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&name)?;
+        for id in &devices {
+            self.get_device_by_id(*id)?;
+        }
+        self.scene_book_mut().groups.insert(name, devices);
+        Ok(())
+    }
+
+    pub fn group(&self, name: &str) -> Option<&[DeviceId]> {
+        self.scene_book().groups.get(name).map(Vec::as_slice)
+    }
+
+    /// Группы в порядке их определения
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &[DeviceId])> {
+        self.scene_book()
+            .groups
+            .iter()
+            .map(|(name, devices)| (name.as_str(), devices.as_slice()))
+    }
+
+    /// Удаляет группу; сцены, которые на нее ссылаются, сообщат об ошибке при применении
+    pub fn remove_group(&mut self, name: &str) -> Result<(), SmartHomeErrors> {
+        self.scene_book_mut()
+            .groups
+            .shift_remove(name)
+            .map(|_| ())
+            .ok_or_else(|| SmartHomeErrors::GroupNotFound(name.to_string()))
+    }
+
+    /// Задает сцену, заменяя сцену с тем же именем.
+    /// Устройства и группы сцены должны быть в доме.
+    pub fn define_scene(&mut self, name: String, scene: Scene) -> Result<(), SmartHomeErrors> {
+        check_key(&name)?;
+        for action in &scene.actions {
+            match &action.target {
+                SceneTarget::Device(id) => {
+                    self.get_device_by_id(*id)?;
+                }
+                SceneTarget::Group(group) if self.group(group).is_none() => {
+                    return Err(SmartHomeErrors::GroupNotFound(group.clone()));
+                }
+                SceneTarget::Group(_) => {}
+            }
+        }
+        self.scene_book_mut().scenes.insert(name, scene);
+        Ok(())
+    }
+
+    pub fn scene(&self, name: &str) -> Option<&Scene> {
+        self.scene_book().scenes.get(name)
+    }
+
+    /// Сцены в порядке их определения
+    pub fn scenes(&self) -> impl Iterator<Item = (&str, &Scene)> {
+        self.scene_book()
+            .scenes
+            .iter()
+            .map(|(name, scene)| (name.as_str(), scene))
+    }
+
+    pub fn remove_scene(&mut self, name: &str) -> Result<(), SmartHomeErrors> {
+        self.scene_book_mut()
+            .scenes
+            .shift_remove(name)
+            .map(|_| ())
+            .ok_or_else(|| SmartHomeErrors::SceneNotFound(name.to_string()))
+    }
+
+    /// Применяет сохраненную сцену. Ошибки отдельных устройств не прерывают
+    /// применение, а попадают в отчет.
+    pub fn apply_scene(&mut self, name: &str) -> Result<SceneReport, SmartHomeErrors> {
+        let plan = self.scene_book().plan_for(name)?;
+        Ok(self.run_plan(plan))
+    }
+
+    /// Применяет сцену, не сохраняя ее в доме
+    pub fn apply(&mut self, scene: &Scene) -> SceneReport {
+        let plan = self.scene_book().plan(scene);
+        self.run_plan(plan)
+    }
+
+    fn run_plan(&mut self, plan: ScenePlan) -> SceneReport {
+        plan.run(|id, state| {
+            let (room, key) = self
+                .locate_device(id)
+                .map(|(room, key)| (room.to_string(), key.to_string()))
+                .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))?;
+            let changed = self.update_device_by_id(id, |device| state.apply(&key, device))??;
+            Ok((SceneDevice { id, room, key }, changed))
+        })
+    }
+}
@@ -0,0 +1,381 @@
+//! Планировщик включения и выключения розеток
+//!
+//! Задания выполняются в заданный момент, через задержку или по расписанию
+//! в формате cron. Текущее время берется из `Clock`, поэтому в тестах
+//! его можно подменить на `clock::ManualClock` и не ждать реального времени.
+
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::SmartHomeErrors,
+    rules::{Action, ActionError},
+    structures::SmartHome,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// Сколько дней вперед ищется следующее срабатывание расписания cron.
+/// Пять лет покрывают даже расписания только на 29 февраля.
+const CRON_SEARCH_DAYS: u64 = 5 * 366;
+
+/// Множество допустимых значений одного поля cron в виде битовой маски
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CronField {
+    mask: u64,
+    /// Поле начинается с `*` (например, `*` или `*/2`) и не ограничивает день
+    any: bool,
+}
+
+impl CronField {
+    fn parse(field: &str, min: u64, max: u64) -> Result<Self, String> {
+        let mut mask = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u64 = step
+                        .parse()
+                        .map_err(|_| format!("bad step in '{}'", part))?;
+                    if step == 0 {
+                        return Err(format!("zero step in '{}'", part));
+                    }
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (parse_value(start, part)?, parse_value(end, part)?)
+            } else {
+                let value = parse_value(range, part)?;
+                // "5/15" означает "начиная с 5 с шагом 15"
+                (value, if step > 1 { max } else { value })
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("'{}' is out of range {}-{}", part, min, max));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                mask |= 1 << value;
+            }
+        }
+        Ok(Self {
+            mask,
+            any: field.starts_with('*'),
+        })
+    }
+
+    fn contains(&self, value: u64) -> bool {
+        self.mask & (1 << value) != 0
+    }
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("bad value in '{}'", part))
+}
+
+/// Расписание в формате cron: `минута час день_месяца месяц день_недели`.
+/// Поддерживаются `*`, списки, диапазоны и шаги. Воскресенье - 0 или 7.
+/// Время считается в UTC. Если указаны и день месяца, и день недели,
+/// достаточно совпадения любого из них, как в классическом cron;
+/// поле, начинающееся с `*` (например, `*/2`), указанным не считается.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minute: CronField,
+    hour: CronField,
+    day_of_month: CronField,
+    month: CronField,
+    day_of_week: CronField,
+}
+
+impl FromStr for CronSchedule {
+    type Err = SmartHomeErrors;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: String| SmartHomeErrors::InvalidSchedule(format!("{}: {}", spec, reason));
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields.as_slice() else {
+            return Err(invalid(String::from("expected 5 fields")));
+        };
+        let mut day_of_week = CronField::parse(day_of_week, 0, 7).map_err(invalid)?;
+        if day_of_week.contains(7) {
+            day_of_week.mask |= 1;
+        }
+        Ok(Self {
+            minute: CronField::parse(minute, 0, 59).map_err(invalid)?,
+            hour: CronField::parse(hour, 0, 23).map_err(invalid)?,
+            day_of_month: CronField::parse(day_of_month, 1, 31).map_err(invalid)?,
+            month: CronField::parse(month, 1, 12).map_err(invalid)?,
+            day_of_week,
+        })
+    }
+}
+
+/// Дата по числу дней от 1970-01-01 (алгоритм Говарда Хиннанта)
+fn civil_from_days(days: u64) -> (u64, u64) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    (month, day)
+}
+
+impl CronSchedule {
+    fn matches_day(&self, days: u64) -> bool {
+        let (month, day) = civil_from_days(days);
+        if !self.month.contains(month) {
+            return false;
+        }
+        // 1970-01-01 - четверг
+        let weekday = (days + 4) % 7;
+        let by_month = self.day_of_month.contains(day);
+        let by_week = self.day_of_week.contains(weekday);
+        match (self.day_of_month.any, self.day_of_week.any) {
+            (false, false) => by_month || by_week,
+            _ => by_month && by_week,
+        }
+    }
+
+    /// Первое срабатывание строго после `after`
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let seconds = after.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let start_minute = seconds / 60 + 1;
+        let first_day = start_minute * 60 / SECONDS_PER_DAY;
+        for days in first_day..first_day + CRON_SEARCH_DAYS {
+            if !self.matches_day(days) {
+                continue;
+            }
+            for hour in (0..24).filter(|hour| self.hour.contains(*hour)) {
+                for minute in (0..60).filter(|minute| self.minute.contains(*minute)) {
+                    let candidate = days * 24 * 60 + hour * 60 + minute;
+                    if candidate >= start_minute {
+                        return Some(UNIX_EPOCH + Duration::from_secs(candidate * 60));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Когда выполнять задание
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Один раз в указанный момент
+    At(SystemTime),
+    /// Один раз через указанное время после добавления задания
+    After(Duration),
+    /// Повторно по расписанию cron
+    Cron(CronSchedule),
+}
+
+/// Идентификатор задания планировщика
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId(u64);
+
+#[derive(Debug)]
+struct Job {
+    id: JobId,
+    action: Action,
+    schedule: Schedule,
+    next_run: SystemTime,
+}
+
+/// Результат выполнения задания
+#[derive(Debug)]
+pub struct JobOutcome {
+    pub id: JobId,
+    pub action: Action,
+    pub scheduled_at: SystemTime,
+    pub result: Result<(), ActionError>,
+}
+
+/// Планировщик действий над розетками
+pub struct Scheduler<C: Clock = SystemClock> {
+    clock: C,
+    jobs: Vec<Job>,
+    next_id: u64,
+}
+
+impl Default for Scheduler<SystemClock> {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl<C: Clock> Scheduler<C> {
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            jobs: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Добавляет задание. Для расписания cron без будущих срабатываний возвращает ошибку.
+    pub fn schedule(
+        &mut self,
+        action: Action,
+        schedule: Schedule,
+    ) -> Result<JobId, SmartHomeErrors> {
+        let now = self.clock.now();
+        let next_run = match &schedule {
+            Schedule::At(at) => *at,
+            Schedule::After(delay) => now + *delay,
+            Schedule::Cron(cron) => cron.next_after(now).ok_or_else(|| {
+                SmartHomeErrors::InvalidSchedule(String::from("schedule never fires"))
+            })?,
+        };
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id,
+            action,
+            schedule,
+            next_run,
+        });
+        Ok(id)
+    }
+
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|job| job.id != id);
+        self.jobs.len() != before
+    }
+
+    /// Время следующего выполнения задания
+    pub fn next_run(&self, id: JobId) -> Option<SystemTime> {
+        self.jobs
+            .iter()
+            .find(|job| job.id == id)
+            .map(|job| job.next_run)
+    }
+
+    /// Сколько осталось до ближайшего задания; ноль, если задание уже пора выполнять
+    pub fn time_until_next(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        self.jobs
+            .iter()
+            .map(|job| job.next_run.duration_since(now).unwrap_or_default())
+            .min()
+    }
+
+    /// Выполняет задания, время которых наступило, в порядке их времени.
+    /// Разовые задания удаляются, повторяющиеся переносятся на следующее
+    /// срабатывание после текущего времени: пропущенные срабатывания не повторяются.
+    pub fn run_due(&mut self, home: &mut SmartHome) -> Vec<JobOutcome> {
+        let now = self.clock.now();
+        let mut due: Vec<usize> = (0..self.jobs.len())
+            .filter(|index| self.jobs[*index].next_run <= now)
+            .collect();
+        due.sort_by_key(|index| (self.jobs[*index].next_run, self.jobs[*index].id));
+
+        let mut outcomes = Vec::with_capacity(due.len());
+        for index in due {
+            let job = &self.jobs[index];
+            outcomes.push(JobOutcome {
+                id: job.id,
+                action: job.action.clone(),
+                scheduled_at: job.next_run,
+                result: job.action.apply(home),
+            });
+        }
+
+        self.jobs.retain_mut(|job| {
+            if job.next_run > now {
+                return true;
+            }
+            match &job.schedule {
+                Schedule::Cron(cron) => match cron.next_after(now) {
+                    Some(next_run) => {
+                        job.next_run = next_run;
+                        true
+                    }
+                    None => false,
+                },
+                Schedule::At(_) | Schedule::After(_) => false,
+            }
+        });
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1, 1));
+        // 2024-02-29
+        assert_eq!(civil_from_days(19_782), (2, 29));
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert!("* * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert!("a * * * *".parse::<CronSchedule>().is_err());
+        assert!("0-5,*/15 1-3 * * 1-5".parse::<CronSchedule>().is_ok());
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // 1970-01-01 00:00 UTC, четверг
+        let daily: CronSchedule = "30 7 * * *".parse().unwrap();
+        assert_eq!(daily.next_after(at(0)), Some(at(7 * 3600 + 30 * 60)));
+        assert_eq!(
+            daily.next_after(at(7 * 3600 + 30 * 60)),
+            Some(at(SECONDS_PER_DAY + 7 * 3600 + 30 * 60))
+        );
+
+        let every_quarter: CronSchedule = "*/15 * * * *".parse().unwrap();
+        assert_eq!(every_quarter.next_after(at(61)), Some(at(15 * 60)));
+
+        // Ближайший понедельник - 5 января 1970
+        let monday: CronSchedule = "0 0 * * 1".parse().unwrap();
+        assert_eq!(monday.next_after(at(0)), Some(at(4 * SECONDS_PER_DAY)));
+
+        let leap_day: CronSchedule = "0 0 29 2 *".parse().unwrap();
+        assert!(leap_day.next_after(at(0)).is_some());
+        let never: CronSchedule = "0 0 31 2 *".parse().unwrap();
+        assert_eq!(never.next_after(at(0)), None);
+    }
+
+    #[test]
+    fn test_cron_star_step_keeps_star_semantics() {
+        // "*/2" не ограничивает день, поэтому нужны оба условия:
+        // понедельник с нечетным числом - 5, затем 19 января 1970
+        let odd_mondays: CronSchedule = "0 0 */2 * 1".parse().unwrap();
+        assert_eq!(odd_mondays.next_after(at(0)), Some(at(4 * SECONDS_PER_DAY)));
+        assert_eq!(
+            odd_mondays.next_after(at(4 * SECONDS_PER_DAY)),
+            Some(at(18 * SECONDS_PER_DAY))
+        );
+
+        // Явно заданные оба поля объединяются: 3 января или понедельник 5 января
+        let either: CronSchedule = "0 0 3 * 1".parse().unwrap();
+        assert_eq!(either.next_after(at(0)), Some(at(2 * SECONDS_PER_DAY)));
+        assert_eq!(
+            either.next_after(at(2 * SECONDS_PER_DAY)),
+            Some(at(4 * SECONDS_PER_DAY))
+        );
+    }
+}
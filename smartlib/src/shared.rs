@@ -0,0 +1,420 @@
+//! Потокобезопасный доступ к дому
+//!
+//! `SharedHome` можно клонировать и передавать в разные потоки. Каждая комната
+//! защищена собственной блокировкой, поэтому изменения в разных комнатах не
+//! мешают друг другу, а отчеты строятся по согласованному снимку дома.
+
+use crate::{
+    errors::SmartHomeErrors,
+    events::{EventBus, HomeEvent},
+    report::ReportFormat,
+    scenes::{SceneBook, SceneDevice, SceneReport},
+    structures::{Device, DeviceId, Room, SmartHome, SortOrder, check_key},
+};
+use indexmap::IndexMap;
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+type SharedRoom = Arc<RwLock<Room>>;
+
+#[derive(Debug)]
+struct Rooms {
+    items: IndexMap<String, SharedRoom>,
+    order: SortOrder,
+}
+
+#[derive(Debug)]
+struct Inner {
+    name: String,
+    rooms: RwLock<Rooms>,
+    events: EventBus,
+    scenes: RwLock<SceneBook>,
+}
+
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|err| err.into_inner())
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Разделяемый дом с блокировкой на уровне комнат
+/// Клоны ссылаются на один и тот же дом.
+/// События об изменениях копятся под блокировкой комнаты и рассылаются после ее
+/// снятия, поэтому обработчики могут обращаться к дому, в том числе к той же комнате.
+#[derive(Debug, Clone)]
+pub struct SharedHome {
+    inner: Arc<Inner>,
+}
+
+impl From<SmartHome> for SharedHome {
+    fn from(home: SmartHome) -> Self {
+        let (name, rooms, order, events, scenes) = home.into_parts();
+        let items = rooms
+            .into_iter()
+            .map(|(key, mut room)| {
+                room.attach_deferred(events.clone(), key.clone());
+                (key, Arc::new(RwLock::new(room)))
+            })
+            .collect();
+        Self {
+            inner: Arc::new(Inner {
+                name,
+                rooms: RwLock::new(Rooms { items, order }),
+                events,
+                scenes: RwLock::new(scenes),
+            }),
+        }
+    }
+}
+
+impl SharedHome {
+    pub fn new(home: SmartHome) -> Self {
+        Self::from(home)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Шина событий дома; подписки, сделанные до создания `SharedHome`, сохраняются
+    pub fn events(&self) -> &EventBus {
+        &self.inner.events
+    }
+
+    fn emit_all(&self, events: Vec<HomeEvent>) {
+        for event in events {
+            self.inner.events.emit(event);
+        }
+    }
+
+    /// Изменяет комнату под блокировкой записи и рассылает события после ее снятия
+    fn update_room<R>(&self, room: &SharedRoom, update: impl FnOnce(&mut Room) -> R) -> R {
+        let mut guard = write(room);
+        let result = update(&mut guard);
+        let events = guard.take_deferred_events();
+        drop(guard);
+        self.emit_all(events);
+        result
+    }
+
+    fn room(&self, room_name: &str) -> Result<SharedRoom, SmartHomeErrors> {
+        read(&self.inner.rooms)
+            .items
+            .get(room_name)
+            .cloned()
+            .ok_or_else(|| SmartHomeErrors::RoomNotFound(room_name.to_string()))
+    }
+
+    /// Ключи комнат в порядке `SortOrder`
+    pub fn room_keys(&self) -> Vec<String> {
+        let rooms = read(&self.inner.rooms);
+        let mut keys: Vec<String> = rooms.items.keys().cloned().collect();
+        if rooms.order != SortOrder::Insertion {
+            keys.sort();
+        }
+        keys
+    }
+
+    /// Если ключ уже занят, возвращает `DuplicateRoom`
+    pub fn add_room_with_key(
+        &self,
+        room_key: String,
+        mut new_room: Room,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&room_key)?;
+        let mut rooms = write(&self.inner.rooms);
+        if rooms.items.contains_key(&room_key) {
+            return Err(SmartHomeErrors::DuplicateRoom(room_key));
+        }
+        let taken: HashSet<u64> = rooms
+            .items
+            .values()
+            .flat_map(|room| read(room).ids().collect::<Vec<u64>>())
+            .collect();
+        new_room.renew_taken_ids(taken);
+        if rooms.order != SortOrder::Insertion {
+            new_room.set_sort_order(rooms.order);
+        }
+        new_room.attach_deferred(self.inner.events.clone(), room_key.clone());
+        rooms
+            .items
+            .insert(room_key.clone(), Arc::new(RwLock::new(new_room)));
+        drop(rooms);
+        self.inner
+            .events
+            .emit(HomeEvent::RoomAdded { room: room_key });
+        Ok(())
+    }
+
+    /// Добавляет комнату с ключом, равным ее имени
+    pub fn add_room(&self, new_room: Room) -> Result<(), SmartHomeErrors> {
+        self.add_room_with_key(new_room.get_name().to_string(), new_room)
+    }
+
+    /// Удаляет комнату и возвращает ее копию, уже не привязанную к дому
+    pub fn delete_room(&self, room_name: &str) -> Result<Room, SmartHomeErrors> {
+        let room = write(&self.inner.rooms)
+            .items
+            .shift_remove(room_name)
+            .ok_or_else(|| SmartHomeErrors::RoomNotFound(room_name.to_string()))?;
+        let removed = read(&room).snapshot();
+        self.inner.events.emit(HomeEvent::RoomRemoved {
+            room: room_name.to_string(),
+        });
+        Ok(removed)
+    }
+
+    /// Меняет ключ комнаты, сохраняя ее устройства, идентификатор и позицию
+    pub fn rename_room(&self, room_key: &str, new_key: String) -> Result<(), SmartHomeErrors> {
+        check_key(&new_key)?;
+        let mut rooms = write(&self.inner.rooms);
+        let Some(index) = rooms.items.get_index_of(room_key) else {
+            return Err(SmartHomeErrors::RoomNotFound(room_key.to_string()));
+        };
+        if new_key == room_key {
+            return Ok(());
+        }
+        if rooms.items.contains_key(&new_key) {
+            return Err(SmartHomeErrors::DuplicateRoom(new_key));
+        }
+        let (_, room) = rooms
+            .items
+            .shift_remove_index(index)
+            .expect("index is valid");
+        write(&room).attach_deferred(self.inner.events.clone(), new_key.clone());
+        rooms.items.shift_insert(index, new_key.clone(), room);
+        drop(rooms);
+        self.inner.events.emit(HomeEvent::RoomRemoved {
+            room: room_key.to_string(),
+        });
+        self.inner
+            .events
+            .emit(HomeEvent::RoomAdded { room: new_key });
+        Ok(())
+    }
+
+    /// Переносит устройство между комнатами, сохраняя его состояние, ключ и идентификатор.
+    /// Обе комнаты блокируются на время переноса в порядке их следования в доме,
+    /// поэтому одновременные переносы не блокируют друг друга навсегда.
+    pub fn move_device(
+        &self,
+        from_room: &str,
+        to_room: &str,
+        device_key: &str,
+    ) -> Result<(), SmartHomeErrors> {
+        let rooms = read(&self.inner.rooms);
+        let index_of = |room: &str| {
+            rooms
+                .items
+                .get_index_of(room)
+                .ok_or_else(|| SmartHomeErrors::RoomNotFound(room.to_string()))
+        };
+        let (from, to) = (index_of(from_room)?, index_of(to_room)?);
+        if from == to {
+            drop(rooms);
+            return self.with_device(from_room, device_key, |_| ());
+        }
+        let (result, events) = {
+            let mut first = write(&rooms.items[from.min(to)]);
+            let mut second = write(&rooms.items[from.max(to)]);
+            let (source, target) = if from < to {
+                (&mut first, &mut second)
+            } else {
+                (&mut second, &mut first)
+            };
+            let result = source.move_device_to(device_key, target);
+            let mut events = source.take_deferred_events();
+            events.extend(target.take_deferred_events());
+            (result, events)
+        };
+        drop(rooms);
+        self.emit_all(events);
+        result
+    }
+
+    /// Меняет ключ устройства в комнате, как `Room::rename_device`
+    pub fn rename_device(
+        &self,
+        room_name: &str,
+        device_key: &str,
+        new_key: String,
+    ) -> Result<(), SmartHomeErrors> {
+        self.with_room_mut(room_name, |room| room.rename_device(device_key, new_key))?
+    }
+
+    /// Чтение комнаты под блокировкой только этой комнаты
+    pub fn with_room<R>(
+        &self,
+        room_name: &str,
+        read_room: impl FnOnce(&Room) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let room = self.room(room_name)?;
+        let guard = read(&room);
+        Ok(read_room(&guard))
+    }
+
+    /// Изменение комнаты под блокировкой только этой комнаты
+    pub fn with_room_mut<R>(
+        &self,
+        room_name: &str,
+        update: impl FnOnce(&mut Room) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let room = self.room(room_name)?;
+        Ok(self.update_room(&room, update))
+    }
+
+    pub fn with_device<R>(
+        &self,
+        room_name: &str,
+        device_name: &str,
+        read_device: impl FnOnce(&dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        self.with_room(room_name, |room| {
+            room.get_device(device_name)
+                .map(read_device)
+                .ok_or_else(|| SmartHomeErrors::DeviceNotFound(device_name.to_string()))
+        })?
+    }
+
+    /// Изменяет устройство и сообщает подписчикам об изменении состояния
+    pub fn update_device<R>(
+        &self,
+        room_name: &str,
+        device_name: &str,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        self.with_room_mut(room_name, |room| room.update_device(device_name, update))?
+    }
+
+    /// Комната, в которой сейчас находится устройство с идентификатором `id`
+    fn room_of(&self, id: DeviceId) -> Result<SharedRoom, SmartHomeErrors> {
+        let rooms: Vec<SharedRoom> = read(&self.inner.rooms).items.values().cloned().collect();
+        rooms
+            .into_iter()
+            .find(|room| read(room).device_key(id).is_some())
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))
+    }
+
+    /// Ключи комнаты и устройства с идентификатором `id`
+    pub fn locate_device(&self, id: DeviceId) -> Option<(String, String)> {
+        let rooms = read(&self.inner.rooms);
+        rooms.items.iter().find_map(|(room_key, room)| {
+            read(room)
+                .device_key(id)
+                .map(|device_key| (room_key.clone(), device_key.to_string()))
+        })
+    }
+
+    pub fn with_device_by_id<R>(
+        &self,
+        id: DeviceId,
+        read_device: impl FnOnce(&dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let room = self.room_of(id)?;
+        let guard = read(&room);
+        guard
+            .get_device_by_id(id)
+            .map(read_device)
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))
+    }
+
+    /// Изменяет устройство по идентификатору и сообщает подписчикам об изменении состояния
+    pub fn update_device_by_id<R>(
+        &self,
+        id: DeviceId,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let room = self.room_of(id)?;
+        self.update_room(&room, |room| room.update_device_by_id(id, update))
+    }
+
+    pub fn get_sort_order(&self) -> SortOrder {
+        read(&self.inner.rooms).order
+    }
+
+    pub fn set_sort_order(&self, order: SortOrder) {
+        let mut rooms = write(&self.inner.rooms);
+        rooms.order = order;
+        for room in rooms.items.values() {
+            write(room).set_sort_order(order);
+        }
+    }
+
+    /// Согласованная копия дома: все комнаты копируются под одновременно
+    /// удерживаемыми блокировками чтения, поэтому снимок не содержит
+    /// половины чьего-либо изменения. Изменения снимка не влияют на дом.
+    pub fn snapshot(&self) -> SmartHome {
+        let rooms = read(&self.inner.rooms);
+        let guards: Vec<(&String, RwLockReadGuard<'_, Room>)> = rooms
+            .items
+            .iter()
+            .map(|(key, room)| (key, read(room)))
+            .collect();
+        let copies = guards
+            .iter()
+            .map(|(key, room)| ((*key).clone(), room.snapshot()))
+            .collect();
+        drop(guards);
+        SmartHome::from_parts(
+            self.inner.name.clone(),
+            copies,
+            rooms.order,
+            EventBus::new(),
+            read(&self.inner.scenes).clone(),
+        )
+    }
+
+    /// Заменяет комнаты, порядок и сцены дома содержимым `home`, например снимком,
+    /// сделанным ранее. Подписчики получают удаление прежних комнат и добавление новых.
+    pub fn restore(&self, home: SmartHome) {
+        let (_, rooms, order, _, scenes) = home.into_parts();
+        let added: Vec<String> = rooms.keys().cloned().collect();
+        let items = rooms
+            .into_iter()
+            .map(|(key, mut room)| {
+                room.attach_deferred(self.inner.events.clone(), key.clone());
+                (key, Arc::new(RwLock::new(room)))
+            })
+            .collect();
+        let removed: Vec<String> = {
+            let mut rooms = write(&self.inner.rooms);
+            rooms.order = order;
+            std::mem::replace(&mut rooms.items, items)
+                .into_keys()
+                .collect()
+        };
+        *write(&self.inner.scenes) = scenes;
+        let events = removed
+            .into_iter()
+            .map(|room| HomeEvent::RoomRemoved { room })
+            .chain(added.into_iter().map(|room| HomeEvent::RoomAdded { room }))
+            .collect();
+        self.emit_all(events);
+    }
+
+    /// Имена сцен дома в порядке их определения
+    pub fn scene_names(&self) -> Vec<String> {
+        read(&self.inner.scenes).scenes.keys().cloned().collect()
+    }
+
+    /// Применяет сцену, блокируя по очереди комнаты затронутых устройств
+    pub fn apply_scene(&self, name: &str) -> Result<SceneReport, SmartHomeErrors> {
+        let plan = read(&self.inner.scenes).plan_for(name)?;
+        Ok(plan.run(|id, state| {
+            let (room, key) = self
+                .locate_device(id)
+                .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))?;
+            let changed = self.update_device_by_id(id, |device| state.apply(&key, device))??;
+            Ok((SceneDevice { id, room, key }, changed))
+        }))
+    }
+
+    /// Отчет строится по снимку, поэтому не задерживает изменения устройств
+    pub fn report_as(&self, format: ReportFormat) -> String {
+        self.snapshot().report_as(format)
+    }
+}
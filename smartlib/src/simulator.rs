@@ -0,0 +1,276 @@
+//! Симуляция показаний устройств без оборудования
+//!
+//! Симулятор меняет температуру термометров (случайный дрейф и суточный цикл),
+//! нагрузку розеток и случайно выводит устройства из строя. Генератор случайных
+//! чисел инициализируется зерном, поэтому при одинаковом зерне и часах
+//! симуляция повторяется в точности.
+
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::SmartHomeErrors,
+    smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
+    structures::{Device, SmartHome},
+};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::TAU,
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+const SECONDS_PER_DAY: f32 = 24.0 * 60.0 * 60.0;
+
+/// Параметры симуляции
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatorConfig {
+    /// Наибольшее изменение дрейфа температуры за шаг, ° C
+    pub drift_step: f32,
+    /// Доля дрейфа, которая сохраняется на следующем шаге; меньше 1, чтобы дрейф не уходил далеко
+    pub drift_retention: f32,
+    /// Амплитуда суточного цикла температуры, ° C
+    pub daily_amplitude: f32,
+    /// Час суток (UTC), когда температура максимальна
+    pub warmest_hour: f32,
+    /// Наибольшее отклонение нагрузки включенной розетки от полной
+    pub load_fluctuation: f32,
+    /// Вероятность отказа исправного устройства за шаг
+    pub failure_probability: f64,
+    /// Вероятность восстановления неисправного устройства за шаг
+    pub recovery_probability: f64,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            drift_step: 0.2,
+            drift_retention: 0.95,
+            daily_amplitude: 3.0,
+            warmest_hour: 15.0,
+            load_fluctuation: 0.1,
+            failure_probability: 0.001,
+            recovery_probability: 0.1,
+        }
+    }
+}
+
+impl SimulatorConfig {
+    /// Проверяет параметры: вероятности в [0, 1], разбросы конечные и неотрицательные
+    pub fn validate(&self) -> Result<(), SmartHomeErrors> {
+        for (name, value) in [
+            ("failure_probability", self.failure_probability),
+            ("recovery_probability", self.recovery_probability),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(SmartHomeErrors::invalid_value(
+                    name,
+                    format!("{} is not a probability in [0, 1]", value),
+                ));
+            }
+        }
+        for (name, value) in [
+            ("drift_step", self.drift_step),
+            ("load_fluctuation", self.load_fluctuation),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(SmartHomeErrors::invalid_value(
+                    name,
+                    format!("{} must be finite and non-negative", value),
+                ));
+            }
+        }
+        for (name, value) in [
+            ("drift_retention", self.drift_retention),
+            ("daily_amplitude", self.daily_amplitude),
+            ("warmest_hour", self.warmest_hour),
+        ] {
+            if !value.is_finite() {
+                return Err(SmartHomeErrors::invalid_value(
+                    name,
+                    format!("{} must be finite", value),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Адрес устройства: ключ комнаты и ключ устройства
+pub type DeviceAddress = (String, String);
+
+/// Что произошло за шаг симуляции
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepReport {
+    /// Сколько устройств получили новые показания
+    pub updated: usize,
+    pub failed: Vec<DeviceAddress>,
+    pub recovered: Vec<DeviceAddress>,
+}
+
+/// Состояние модели термометра
+#[derive(Debug, Clone)]
+struct ThermometerModel {
+    /// Средняя температура в ° C, от которой считаются цикл и дрейф
+    base: f32,
+    drift: f32,
+}
+
+/// Симулятор устройств дома
+pub struct Simulator {
+    rng: StdRng,
+    config: SimulatorConfig,
+    clock: Arc<dyn Clock>,
+    thermometers: HashMap<DeviceAddress, ThermometerModel>,
+    failed: HashSet<DeviceAddress>,
+}
+
+impl Simulator {
+    /// Создает симулятор
+    ///
+    /// # Panics
+    /// Если `config` не проходит `SimulatorConfig::validate`; для проверки без паники есть `try_new`
+    pub fn new(seed: u64, config: SimulatorConfig) -> Self {
+        match Self::try_new(seed, config) {
+            Ok(simulator) => simulator,
+            Err(err) => panic!("invalid simulator config: {}", err),
+        }
+    }
+
+    /// Создает симулятор, если параметры корректны
+    pub fn try_new(seed: u64, config: SimulatorConfig) -> Result<Self, SmartHomeErrors> {
+        config.validate()?;
+        Ok(Self {
+            rng: StdRng::seed_from_u64(seed),
+            config,
+            clock: Arc::new(SystemClock),
+            thermometers: HashMap::new(),
+            failed: HashSet::new(),
+        })
+    }
+
+    /// Заменяет часы, по которым считается суточный цикл
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &SimulatorConfig {
+        &self.config
+    }
+
+    /// Устройство неисправно и отмечено как не в сети: термометр не дает показаний,
+    /// розетка выключена и не включается
+    pub fn is_failed(&self, room: &str, device: &str) -> bool {
+        self.failed
+            .contains(&(room.to_string(), device.to_string()))
+    }
+
+    /// Суточная составляющая температуры в момент по часам симулятора
+    fn daily_offset(&self) -> f32 {
+        let seconds = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f32()
+            % SECONDS_PER_DAY;
+        let phase = (seconds / 3600.0 - self.config.warmest_hour) / 24.0;
+        self.config.daily_amplitude * (phase * TAU).cos()
+    }
+
+    /// Один шаг симуляции для всех термометров и розеток дома.
+    /// Изменения вносятся через `SmartHome::update_device`, поэтому подписчики получают события.
+    pub fn step(&mut self, home: &mut SmartHome) -> StepReport {
+        let mut report = StepReport::default();
+        let daily = self.daily_offset();
+        // Адреса собираются заранее в порядке дома, чтобы последовательность
+        // случайных чисел не зависела от порядка обхода хеш-таблиц
+        let addresses: Vec<DeviceAddress> = home
+            .rooms()
+            .flat_map(|(room_key, room)| {
+                room.devices()
+                    .map(move |(key, _)| (room_key.to_string(), key.to_string()))
+            })
+            .collect();
+
+        for address in addresses {
+            let (room, key) = (address.0.as_str(), address.1.as_str());
+            if self.failed.contains(&address) {
+                if self.rng.random_bool(self.config.recovery_probability) {
+                    let _ = home.update_device(room, key, |device| set_offline(device, false));
+                    self.failed.remove(&address);
+                    report.recovered.push(address);
+                }
+                continue;
+            }
+            if self.rng.random_bool(self.config.failure_probability) {
+                let _ = home.update_device(room, key, |device| set_offline(device, true));
+                self.failed.insert(address.clone());
+                report.failed.push(address);
+                continue;
+            }
+
+            let drift_delta = self
+                .rng
+                .random_range(-self.config.drift_step..=self.config.drift_step);
+            let load = 1.0
+                + self
+                    .rng
+                    .random_range(-self.config.load_fluctuation..=self.config.load_fluctuation);
+            let config = &self.config;
+            let thermometers = &mut self.thermometers;
+            let updated = home.update_device(room, key, |device| {
+                if let Some(thermo) = device.downcast_mut::<SmartThermometer>() {
+                    let model = thermometers
+                        .entry(address.clone())
+                        // Первое показание становится точкой отсчета без скачка
+                        .or_insert_with(|| ThermometerModel {
+                            base: thermo.get_tempreture_in(&TempMeasures::C) - daily,
+                            drift: 0.0,
+                        });
+                    model.drift = model.drift * config.drift_retention + drift_delta;
+                    let celsius = model.base + model.drift + daily;
+                    let value = TempMeasures::C.convert(celsius, thermo.get_temp_measure());
+                    thermo.set_tempreture(value);
+                    true
+                } else if let Some(socket) = device.downcast_mut::<SmartElectricalSoket>() {
+                    socket.set_load(load).is_ok() && socket.is_on()
+                } else {
+                    false
+                }
+            });
+            if updated.unwrap_or(false) {
+                report.updated += 1;
+            }
+        }
+        report
+    }
+}
+
+/// Отмечает отказ или восстановление на самом устройстве
+fn set_offline(device: &mut dyn Device, offline: bool) {
+    if let Some(thermo) = device.downcast_mut::<SmartThermometer>() {
+        thermo.set_offline(offline);
+    } else if let Some(socket) = device.downcast_mut::<SmartElectricalSoket>() {
+        socket.set_offline(offline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_offset_peaks_at_warmest_hour() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(15 * 3600));
+        let simulator =
+            Simulator::new(0, SimulatorConfig::default()).with_clock(Arc::new(clock.clone()));
+        assert!((simulator.daily_offset() - 3.0).abs() < 1e-4);
+
+        clock.advance(Duration::from_secs(12 * 3600));
+        assert!((simulator.daily_offset() + 3.0).abs() < 1e-4);
+    }
+}
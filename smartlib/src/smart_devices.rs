@@ -1,9 +1,29 @@
-use std::fmt;
+use crate::clock::{Clock, SystemClock};
+use crate::energy::EnergyMeter;
+use crate::errors::{ConnectionErrors, SmartHomeErrors};
+use crate::history::{TemperatureHistory, TemperatureStats};
+use crate::report::{FieldValue, ReportField};
+use crate::structures::{Device, Report};
+use crate::udp::{Telemetry, TelemetryStats};
+use std::{
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-#[derive(Debug, Clone)]
+/// Единицы измерения температуры
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TempMeasures {
+    /// Градусы Цельсия
     C,
+    /// Градусы Фаренгейта
     F,
+    /// Кельвины
+    K,
+    /// Градусы Ранкина
+    R,
 }
 
 impl fmt::Display for TempMeasures {
@@ -11,28 +31,178 @@ impl fmt::Display for TempMeasures {
         match self {
             TempMeasures::C => write!(f, "° C"),
             TempMeasures::F => write!(f, "° F"),
+            TempMeasures::K => write!(f, " K"),
+            TempMeasures::R => write!(f, "° R"),
+        }
+    }
+}
+
+impl TempMeasures {
+    /// Абсолютный ноль в градусах Цельсия
+    const ABSOLUTE_ZERO: f64 = -273.15;
+
+    fn celsius_of(&self, value: f64) -> f64 {
+        match self {
+            TempMeasures::C => value,
+            TempMeasures::F => (value - 32.0) * 5.0 / 9.0,
+            TempMeasures::K => value + Self::ABSOLUTE_ZERO,
+            TempMeasures::R => value * 5.0 / 9.0 + Self::ABSOLUTE_ZERO,
+        }
+    }
+
+    fn value_of_celsius(&self, celsius: f64) -> f64 {
+        match self {
+            TempMeasures::C => celsius,
+            TempMeasures::F => celsius * 9.0 / 5.0 + 32.0,
+            TempMeasures::K => celsius - Self::ABSOLUTE_ZERO,
+            TempMeasures::R => (celsius - Self::ABSOLUTE_ZERO) * 9.0 / 5.0,
+        }
+    }
+
+    /// Переводит значение из этих единиц в указанные.
+    /// Вычисления идут в `f64`, поэтому результат отличается от точного не больше чем на округление `f32`.
+    pub fn convert(&self, value: f32, to: &TempMeasures) -> f32 {
+        if self == to {
+            value
+        } else {
+            to.value_of_celsius(self.celsius_of(f64::from(value))) as f32
         }
     }
+
+    /// Краткое обозначение единиц: `C`, `F`, `K` или `R`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TempMeasures::C => "C",
+            TempMeasures::F => "F",
+            TempMeasures::K => "K",
+            TempMeasures::R => "R",
+        }
+    }
+}
+
+/// Показание температуры вместе с единицами измерения
+/// Показания в разных единицах сравниваются точно после перевода в градусы Цельсия;
+/// для сравнения с допуском есть `approx_eq`.
+#[derive(Debug, Clone, Copy)]
+pub struct Temperature {
+    value: f32,
+    measure: TempMeasures,
+}
+
+impl Temperature {
+    /// Допуск для `approx_eq`, достаточный для ошибок перевода между единицами, ° C
+    pub const EPSILON: f32 = 1e-3;
+
+    pub fn new(value: f32, measure: TempMeasures) -> Self {
+        Self { value, measure }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn measure(&self) -> TempMeasures {
+        self.measure
+    }
+
+    /// Значение в указанных единицах
+    pub fn value_in(&self, measure: &TempMeasures) -> f32 {
+        self.measure.convert(self.value, measure)
+    }
+
+    /// То же показание в других единицах
+    pub fn convert_to(&self, measure: TempMeasures) -> Self {
+        Self::new(self.value_in(&measure), measure)
+    }
+
+    /// Показания отличаются не больше чем на `eps` градуса Цельсия
+    pub fn approx_eq(&self, other: &Self, eps: f32) -> bool {
+        (self.value_in(&TempMeasures::C) - other.value_in(&TempMeasures::C)).abs() <= eps
+    }
+}
+
+impl PartialEq for Temperature {
+    fn eq(&self, other: &Self) -> bool {
+        self.value_in(&TempMeasures::C) == other.value_in(&TempMeasures::C)
+    }
+}
+
+impl PartialOrd for Temperature {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value_in(&TempMeasures::C)
+            .partial_cmp(&other.value_in(&TempMeasures::C))
+    }
+}
+
+/// Учитывает точность форматирования: `format!("{:.1}", reading)`
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*}{}", precision, self.value, self.measure),
+            None => write!(f, "{}{}", self.value, self.measure),
+        }
+    }
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[cfg(feature = "serde")]
+fn full_load() -> f32 {
+    1.0
 }
 
 /// Реализация умного термометра
 /// Возможно переключение различных мер измерений, при этом температура будет конвертироваться
+/// В режиме приёма телеметрии температура обновляется из датаграмм UDP
+/// Показания сохраняются в ограниченную историю для статистики
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SmartThermometer {
     name: String,
+    /// Последнее показание в единицах `reading_measure`
     tempreture: f32,
+    /// Единицы, в которых получено показание; в файлах старых версий отсутствуют
+    /// и совпадают с `measure`
+    #[cfg_attr(feature = "serde", serde(default))]
+    reading_measure: Option<TempMeasures>,
+    /// Единицы, в которых термометр показывает температуру
     measure: TempMeasures,
+    /// Число знаков после запятой в описании и отчетах; без значения не округляется
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    precision: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    telemetry: Option<Telemetry>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    history: TemperatureHistory,
+    /// Термометр не в сети и не дает показаний
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    offline: bool,
+    #[cfg_attr(feature = "serde", serde(skip, default = "system_clock"))]
+    clock: Arc<dyn Clock>,
 }
 
 impl fmt::Display for SmartThermometer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Термометр '{}', Температура: {}{}",
+            "Термометр '{}', Температура: {}",
             self.get_name(),
-            self.get_tempreture(),
-            self.measure
-        )
+            self.formatted_reading()
+        )?;
+        if self.offline {
+            write!(f, " (не в сети)")?;
+        } else if self.is_stale() {
+            write!(f, " (показания устарели)")?;
+        }
+        Ok(())
     }
 }
 
@@ -40,33 +210,220 @@ impl SmartThermometer {
     pub fn new(name: String, measure: TempMeasures, tempreture: f32) -> Self {
         Self {
             name,
-            measure,
             tempreture,
+            reading_measure: Some(measure),
+            measure,
+            precision: None,
+            telemetry: None,
+            history: TemperatureHistory::default(),
+            offline: false,
+            clock: system_clock(),
         }
     }
 
+    /// Заменяет часы, по которым отмечается время показаний
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Задает число знаков после запятой в описании и отчетах
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Задает размер истории показаний; уже сохраненные показания сбрасываются
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        let persistent = self.history.is_persistent();
+        self.history = TemperatureHistory::with_capacity(capacity);
+        self.history.set_persistent(persistent);
+        self
+    }
+
+    /// Сохраняет историю показаний вместе с домом; по умолчанию она не сохраняется
+    pub fn with_persistent_history(mut self) -> Self {
+        self.history.set_persistent(true);
+        self
+    }
+
+    /// Создает термометр, получающий показания по UDP на адресе `addr`.
+    /// Если датаграммы не приходят дольше `stale_after`, показания считаются устаревшими.
+    pub fn listen<A: ToSocketAddrs>(
+        name: String,
+        measure: TempMeasures,
+        addr: A,
+        stale_after: Duration,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            name,
+            tempreture: 0.0,
+            reading_measure: Some(measure),
+            measure,
+            precision: None,
+            telemetry: Some(Telemetry::listen(addr, stale_after)?),
+            history: TemperatureHistory::default(),
+            offline: false,
+            clock: system_clock(),
+        })
+    }
+
+    /// Переключает единицы между Цельсием и Фаренгейтом;
+    /// из Кельвинов и Ранкина переключает в Цельсий
     pub fn change_measure(&mut self) {
-        match self.measure {
-            TempMeasures::C => {
-                self.measure = TempMeasures::F;
-                self.tempreture = (self.tempreture * 9.0 / 5.0) + 32.0
-            }
-            TempMeasures::F => {
-                self.measure = TempMeasures::C;
-                self.tempreture = (self.tempreture - 32.0) * 5.0 / 9.0
+        let target = match self.measure {
+            TempMeasures::C => TempMeasures::F,
+            TempMeasures::F | TempMeasures::K | TempMeasures::R => TempMeasures::C,
+        };
+        self.convert_to(target);
+    }
+
+    /// Переводит термометр в указанные единицы.
+    /// Показание хранится в исходных единицах и пересчитывается только при чтении,
+    /// поэтому повторные переключения не накапливают ошибку округления.
+    pub fn convert_to(&mut self, measure: TempMeasures) {
+        self.measure = measure;
+    }
+
+    pub fn get_tempreture(&self) -> f32 {
+        self.get_tempreture_in(&self.measure)
+    }
+
+    /// Текущая температура или ошибка `DeviceOffline`, если термометр не в сети
+    /// или показания устарели: датаграммы телеметрии не приходили дольше заданного времени
+    pub fn current_tempreture(&self) -> Result<f32, SmartHomeErrors> {
+        if self.offline {
+            return Err(SmartHomeErrors::offline(&self.name));
+        }
+        if self.is_stale() {
+            return Err(SmartHomeErrors::connection(
+                &self.name,
+                ConnectionErrors::Timeout,
+            ));
+        }
+        Ok(self.get_tempreture())
+    }
+
+    /// Ждет новое показание телеметрии не дольше `timeout`; ожидание отменяется удалением future.
+    /// Если датаграмма не пришла, возвращается `DeviceOffline`.
+    /// Термометр без телеметрии сразу возвращает текущее показание.
+    #[cfg(feature = "tokio")]
+    pub async fn next_tempreture(&self, timeout: Duration) -> Result<f32, SmartHomeErrors> {
+        let Some(telemetry) = &self.telemetry else {
+            return Ok(self.get_tempreture());
+        };
+        match telemetry.next_reading(timeout).await {
+            Some((value, measure)) => Ok(Temperature::new(value, measure).value_in(&self.measure)),
+            None => Err(SmartHomeErrors::connection(
+                &self.name,
+                ConnectionErrors::Timeout,
+            )),
+        }
+    }
+
+    /// Обновляет показание в текущих единицах измерения и сохраняет его в историю
+    pub fn set_tempreture(&mut self, tempreture: f32) {
+        self.tempreture = tempreture;
+        self.reading_measure = Some(self.measure);
+        self.record_reading();
+    }
+
+    /// Сохраняет текущее показание в историю.
+    /// В режиме приёма телеметрии вызывается периодически, чтобы накапливать историю.
+    pub fn record_reading(&mut self) {
+        let celsius = self.get_tempreture_in(&TempMeasures::C);
+        self.history.record(self.clock.now(), celsius);
+    }
+
+    pub fn history(&self) -> &TemperatureHistory {
+        &self.history
+    }
+
+    /// Статистика показаний за период `[from, to)` в текущих единицах измерения
+    pub fn history_stats(&self, from: SystemTime, to: SystemTime) -> Option<TemperatureStats> {
+        self.history.stats(from, to, &self.measure)
+    }
+
+    pub fn get_temp_measure(&self) -> &TempMeasures {
+        &self.measure
+    }
+
+    /// Температура в указанных единицах, без переключения единиц термометра
+    pub fn get_tempreture_in(&self, measure: &TempMeasures) -> f32 {
+        self.source_reading().value_in(measure)
+    }
+
+    /// Показание в тех единицах, в которых оно получено
+    fn source_reading(&self) -> Temperature {
+        match self.telemetry.as_ref().and_then(Telemetry::reading) {
+            Some((value, measure)) => Temperature::new(value, measure),
+            None => Temperature::new(
+                self.tempreture,
+                self.reading_measure.unwrap_or(self.measure),
+            ),
+        }
+    }
+
+    pub fn get_precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    /// Задает число знаков после запятой; `None` отключает округление
+    pub fn set_precision(&mut self, precision: Option<usize>) {
+        self.precision = precision;
+    }
+
+    /// Показание для описания и отчетов с учетом точности
+    fn formatted_reading(&self) -> String {
+        match self.precision {
+            Some(precision) => format!("{:.*}", precision, self.reading()),
+            None => self.reading().to_string(),
+        }
+    }
+
+    /// Значение, округленное до заданной точности
+    fn rounded_tempreture(&self) -> f32 {
+        let tempreture = self.get_tempreture();
+        match self.precision {
+            Some(precision) => {
+                let scale = 10f64.powi(precision.min(9) as i32);
+                ((f64::from(tempreture) * scale).round() / scale) as f32
             }
+            None => tempreture,
         }
     }
 
-    pub fn get_tempreture(&self) -> f32 {
-        self.tempreture
+    /// Адрес, на котором термометр принимает телеметрию
+    pub fn telemetry_addr(&self) -> Option<SocketAddr> {
+        self.telemetry.as_ref().map(Telemetry::local_addr)
+    }
+
+    /// Статистика принятых и потерянных датаграмм
+    pub fn telemetry_stats(&self) -> Option<TelemetryStats> {
+        self.telemetry.as_ref().map(Telemetry::stats)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Отмечает потерю или восстановление связи с термометром
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Показания устарели: термометр в режиме приёма давно не получал датаграмм
+    pub fn is_stale(&self) -> bool {
+        self.telemetry.as_ref().is_some_and(Telemetry::is_stale)
     }
 
     pub fn get_measure(&self) -> &str {
-        match self.measure {
-            TempMeasures::F => "F",
-            TempMeasures::C => "C",
-        }
+        self.measure.symbol()
+    }
+
+    /// Текущее показание вместе с единицами измерения
+    pub fn reading(&self) -> Temperature {
+        Temperature::new(self.get_tempreture(), self.measure)
     }
 
     pub fn get_name(&self) -> &str {
@@ -74,18 +431,73 @@ impl SmartThermometer {
     }
 }
 
+impl Report for SmartThermometer {
+    fn report(&self) -> String {
+        format!("| -- {}", self)
+    }
+}
+
+impl Device for SmartThermometer {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+
+    fn kind(&self) -> &str {
+        "thermometer"
+    }
+
+    fn status(&self) -> String {
+        if self.offline {
+            format!("{} (не в сети)", self.formatted_reading())
+        } else if self.is_stale() {
+            format!("{} (устарело)", self.formatted_reading())
+        } else {
+            self.formatted_reading()
+        }
+    }
+
+    fn fields(&self) -> Vec<ReportField> {
+        vec![
+            ReportField::new("tempreture", FieldValue::Number(self.rounded_tempreture())),
+            ReportField::new("measure", FieldValue::Text(self.get_measure().to_string())),
+            ReportField::new("stale", FieldValue::Bool(self.is_stale())),
+        ]
+    }
+
+    fn is_offline(&self) -> bool {
+        self.offline
+    }
+}
+
 /// Реализация умной розетки
 /// Можно включить или выключить и посмотеть текущую мощность
+/// Розетка учитывает время во включенном состоянии и считает потребленную энергию
+/// Фактическая мощность равна номинальной, умноженной на коэффициент нагрузки
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SmartElectricalSoket {
     name: String,
     power: f32,
     is_on: bool,
+    #[cfg_attr(feature = "serde", serde(default = "full_load"))]
+    load: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    meter: EnergyMeter,
+    /// Розетка не в сети: она выключена, и включить ее нельзя
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    offline: bool,
+    #[cfg_attr(feature = "serde", serde(skip, default = "system_clock"))]
+    clock: Arc<dyn Clock>,
 }
 
 impl fmt::Display for SmartElectricalSoket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let status = if self.is_on() {
+        let status = if self.offline {
+            "не в сети"
+        } else if self.is_on() {
             "включена"
         } else {
             "выключена"
@@ -106,29 +518,165 @@ impl SmartElectricalSoket {
             name,
             is_on: false,
             power,
+            load: 1.0,
+            meter: EnergyMeter::default(),
+            offline: false,
+            clock: system_clock(),
         }
     }
+    /// Создает розетку, проверяя, что мощность конечна и неотрицательна
+    pub fn try_new(name: String, power: f32) -> Result<Self, SmartHomeErrors> {
+        if !power.is_finite() || power < 0.0 {
+            return Err(SmartHomeErrors::invalid_value(
+                "power",
+                format!("{} is not a non-negative number", power),
+            ));
+        }
+        Ok(Self::new(name, power))
+    }
+    /// Заменяет часы, по которым отмечаются включения и выключения
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    /// Сохраняет журнал включений вместе с домом; по умолчанию сохраняется
+    /// только суммарное потребление
+    pub fn with_persistent_history(mut self) -> Self {
+        self.meter.set_persistent(true);
+        self
+    }
     pub fn get_name(&self) -> &str {
         &self.name
     }
     pub fn is_on(&self) -> bool {
         self.is_on
     }
-    pub fn switch(&mut self) {
-        self.is_on = !self.is_on
+    fn set_on(&mut self, is_on: bool) {
+        let now = self.clock.now();
+        if is_on {
+            self.meter.switch_on(now, self.power * self.load);
+        } else {
+            self.meter.switch_off(now);
+        }
+        self.is_on = is_on
+    }
+    /// Переключает розетку; включить розетку не в сети нельзя
+    pub fn switch(&mut self) -> Result<(), SmartHomeErrors> {
+        if self.is_on {
+            self.turn_off();
+            Ok(())
+        } else {
+            self.turn_on()
+        }
     }
-    pub fn turn_on(&mut self) {
-        self.is_on = true
+    /// Включает розетку или возвращает `DeviceOffline`, если она не в сети
+    pub fn turn_on(&mut self) -> Result<(), SmartHomeErrors> {
+        if self.offline {
+            return Err(SmartHomeErrors::offline(&self.name));
+        }
+        self.set_on(true);
+        Ok(())
     }
     pub fn turn_off(&mut self) {
-        self.is_on = false
+        self.set_on(false)
+    }
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+    /// Отмечает потерю или восстановление связи; при потере розетка выключается
+    pub fn set_offline(&mut self, offline: bool) {
+        if offline {
+            self.turn_off();
+        }
+        self.offline = offline;
+    }
+    /// Номинальная мощность, не зависящая от состояния розетки
+    pub fn get_nominal_power(&self) -> f32 {
+        self.power
+    }
+    /// Коэффициент нагрузки: доля номинальной мощности, которую потребляет прибор
+    pub fn get_load(&self) -> f32 {
+        self.load
+    }
+    /// Задает коэффициент нагрузки; отрицательные значения считаются нулем,
+    /// бесконечность и NaN отклоняются
+    pub fn set_load(&mut self, load: f32) -> Result<(), SmartHomeErrors> {
+        if !load.is_finite() {
+            return Err(SmartHomeErrors::invalid_value(
+                "load",
+                format!("{} is not a finite number", load),
+            ));
+        }
+        self.load = load.max(0.0);
+        if self.is_on {
+            self.meter
+                .set_power(self.clock.now(), self.power * self.load);
+        }
+        Ok(())
     }
     pub fn get_power(&self) -> f32 {
         match self.is_on {
-            true => self.power,
+            true => self.power * self.load,
             false => 0f32,
         }
     }
+    /// Потребленная энергия в Вт·ч за период `[from, to)`
+    pub fn get_energy_between(&self, from: SystemTime, to: SystemTime) -> f32 {
+        self.meter.energy_between(from, to, self.clock.now())
+    }
+    /// Потребленная энергия в Вт·ч за все время
+    pub fn get_energy_total(&self) -> f32 {
+        self.meter.energy_total(self.clock.now())
+    }
+}
+
+impl Report for SmartElectricalSoket {
+    fn report(&self) -> String {
+        format!("| -- {}", self)
+    }
+}
+
+impl Device for SmartElectricalSoket {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+
+    fn kind(&self) -> &str {
+        "socket"
+    }
+
+    fn status(&self) -> String {
+        if self.offline {
+            String::from("не в сети")
+        } else if self.is_on() {
+            String::from("включена")
+        } else {
+            String::from("выключена")
+        }
+    }
+
+    fn fields(&self) -> Vec<ReportField> {
+        vec![
+            ReportField::new("is_on", FieldValue::Bool(self.is_on())),
+            ReportField::new("power", FieldValue::Number(self.get_power())),
+            ReportField::new(
+                "nominal_power",
+                FieldValue::Number(self.get_nominal_power()),
+            ),
+        ]
+    }
+
+    fn energy_between(&self, from: SystemTime, to: SystemTime) -> Option<f32> {
+        Some(self.get_energy_between(from, to))
+    }
+
+    fn energy_total(&self) -> Option<f32> {
+        Some(self.get_energy_total())
+    }
+
+    fn is_offline(&self) -> bool {
+        self.offline
+    }
 }
 
 #[cfg(test)]
@@ -162,19 +710,119 @@ mod tests {
         assert_eq!(termo.get_tempreture(), 32.0);
     }
 
+    #[test]
+    fn test_termometer_convert_to_kelvin_and_rankine() {
+        let mut termo = SmartThermometer::new("TestTermo".to_string(), TempMeasures::C, 100.0);
+        termo.convert_to(TempMeasures::K);
+        assert_eq!(termo.get_measure(), "K");
+        assert!((termo.get_tempreture() - 373.15).abs() < 1e-3);
+        assert_eq!(
+            termo.to_string(),
+            "Термометр 'TestTermo', Температура: 373.15 K"
+        );
+        termo.convert_to(TempMeasures::R);
+        assert!((termo.get_tempreture() - 671.67).abs() < 1e-3);
+        termo.convert_to(TempMeasures::F);
+        assert!((termo.get_tempreture() - 212.0).abs() < 1e-3);
+        // Из Кельвинов переключение идет в Цельсий
+        termo.convert_to(TempMeasures::K);
+        termo.change_measure();
+        assert_eq!(termo.get_measure(), "C");
+        assert!((termo.get_tempreture() - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_termometer_repeated_conversions_do_not_drift() {
+        let mut termo = SmartThermometer::new("TestTermo".to_string(), TempMeasures::C, 21.3);
+        for _ in 0..1000 {
+            termo.change_measure();
+            termo.convert_to(TempMeasures::K);
+            termo.convert_to(TempMeasures::R);
+        }
+        termo.convert_to(TempMeasures::C);
+        assert_eq!(termo.get_tempreture(), 21.3);
+
+        // Новое показание задается в текущих единицах
+        termo.convert_to(TempMeasures::F);
+        termo.set_tempreture(70.1);
+        termo.change_measure();
+        termo.change_measure();
+        assert_eq!(termo.get_tempreture(), 70.1);
+    }
+
+    #[test]
+    fn test_termometer_precision() {
+        let mut termo = SmartThermometer::new("TestTermo".to_string(), TempMeasures::C, 21.456)
+            .with_precision(1);
+        assert_eq!(
+            termo.to_string(),
+            "Термометр 'TestTermo', Температура: 21.5° C"
+        );
+        assert_eq!(termo.status(), "21.5° C");
+        assert_eq!(termo.fields()[0].value, FieldValue::Number(21.5));
+        // Округляется только представление
+        assert_eq!(termo.get_tempreture(), 21.456);
+
+        termo.set_precision(Some(0));
+        assert_eq!(termo.status(), "21° C");
+        termo.set_precision(None);
+        assert_eq!(termo.status(), "21.456° C");
+        assert_eq!(
+            format!("{:.2}", Temperature::new(-1.23456, TempMeasures::K)),
+            "-1.23 K"
+        );
+    }
+
+    #[test]
+    fn test_temperature_comparison_across_measures() {
+        let celsius = Temperature::new(20.0, TempMeasures::C);
+        let eps = Temperature::EPSILON;
+        assert!(celsius.approx_eq(&Temperature::new(68.0, TempMeasures::F), eps));
+        assert!(celsius.approx_eq(&Temperature::new(293.15, TempMeasures::K), eps));
+        assert!(celsius.approx_eq(&Temperature::new(527.67, TempMeasures::R), eps));
+        assert!(!celsius.approx_eq(&Temperature::new(20.01, TempMeasures::C), eps));
+        // Точное равенство транзитивно: близкие показания не равны
+        assert_eq!(celsius, Temperature::new(20.0, TempMeasures::C));
+        assert_ne!(celsius, Temperature::new(20.0005, TempMeasures::C));
+        assert!(celsius < Temperature::new(70.0, TempMeasures::F));
+        assert!(celsius > Temperature::new(290.0, TempMeasures::K));
+        assert_eq!(celsius.convert_to(TempMeasures::F).value(), 68.0);
+        assert_eq!(celsius.convert_to(TempMeasures::F).to_string(), "68° F");
+    }
+
+    #[test]
+    fn test_socket_rejects_invalid_power() {
+        let err = SmartElectricalSoket::try_new(String::from("TestSocket"), -1.0).unwrap_err();
+        assert_eq!(err.code(), "invalid_value");
+        assert!(SmartElectricalSoket::try_new(String::from("TestSocket"), f32::NAN).is_err());
+        assert!(SmartElectricalSoket::try_new(String::from("TestSocket"), 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_socket_rejects_invalid_load() {
+        let mut socket = SmartElectricalSoket::new(String::from("TestSocket"), 100.0);
+        socket.turn_on().unwrap();
+        let err = socket.set_load(f32::INFINITY).unwrap_err();
+        assert_eq!(err.code(), "invalid_value");
+        assert!(socket.set_load(f32::NAN).is_err());
+        assert_eq!(socket.get_power(), 100.0);
+        socket.set_load(-1.0).unwrap();
+        assert_eq!(socket.get_power(), 0.0);
+    }
+
     #[test]
     fn test_socket() {
         let mut new_socket = SmartElectricalSoket::new(String::from("TestSocket"), 220.0);
         assert!(!new_socket.is_on(), "Expected false, but get true");
         assert_eq!(new_socket.get_power(), 0.0);
-        new_socket.turn_on();
+        new_socket.turn_on().unwrap();
         assert_eq!(new_socket.get_power(), 220.0);
         assert!(new_socket.is_on(), "Expected true, but get false");
         new_socket.turn_off();
         assert!(!new_socket.is_on(), "Expected false, but get true");
-        new_socket.switch();
+        new_socket.switch().unwrap();
         assert!(new_socket.is_on(), "Expected true, but get false");
-        new_socket.switch();
+        new_socket.switch().unwrap();
         assert!(!new_socket.is_on(), "Expected false, but get true");
     }
 }
@@ -1,119 +1,785 @@
 use crate::{
     errors::SmartHomeErrors,
+    events::{EventBus, HomeEvent},
+    report::{HomeReport, ReportField, ReportFormat, RoomReport},
+    scenes::SceneBook,
     smart_devices::{SmartElectricalSoket, SmartThermometer},
 };
 
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt,
+    num::ParseIntError,
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
 
 /// Общий трейт формирования текстового отчёта
 pub trait Report {
     fn report(&self) -> String;
 }
 
-#[derive(Debug, Clone)]
-pub enum SmartDevice {
-    Thermometer(SmartThermometer),
-    ElectricalSocket(SmartElectricalSoket),
+impl<T: Report + ?Sized> Report for Box<T> {
+    fn report(&self) -> String {
+        (**self).report()
+    }
+}
+
+/// Общий трейт умного устройства
+/// Реализуя его, можно добавлять в комнату собственные типы устройств,
+/// не изменяя библиотеку.
+pub trait Device: Report + DeviceClone + fmt::Debug + fmt::Display + Any + Send + Sync {
+    /// Имя устройства
+    fn name(&self) -> &str;
+    /// Тип устройства, например `thermometer` или `socket`
+    fn kind(&self) -> &str;
+    /// Краткое текстовое описание текущего состояния
+    fn status(&self) -> String;
+    /// Типизированные поля состояния для структурированных отчетов
+    fn fields(&self) -> Vec<ReportField> {
+        Vec::new()
+    }
+    /// Энергия в Вт·ч, потребленная за период `[from, to)`; `None`, если устройство не ведет учет
+    fn energy_between(&self, _from: SystemTime, _to: SystemTime) -> Option<f32> {
+        None
+    }
+    /// Энергия в Вт·ч, потребленная за все время; `None`, если устройство не ведет учет
+    fn energy_total(&self) -> Option<f32> {
+        None
+    }
+    /// Устройство не в сети, например после отказа; такие устройства не принимают команд
+    fn is_offline(&self) -> bool {
+        false
+    }
 }
 
+/// Вспомогательный трейт для клонирования устройств за `Box<dyn Device>`.
+/// Реализуется автоматически для всех устройств, реализующих `Clone`.
+pub trait DeviceClone {
+    fn clone_box(&self) -> Box<dyn Device>;
+}
+
+impl<T: Device + Clone> DeviceClone for T {
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Device> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl dyn Device {
+    /// Проверка, что устройство имеет конкретный тип `T`
+    pub fn is<T: Device>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    /// Приведение к конкретному типу устройства
+    pub fn downcast_ref<T: Device>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    /// Приведение к конкретному типу устройства с возможностью изменения
+    pub fn downcast_mut<T: Device>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+}
+
+/// Устройство, хранящееся в комнате
+pub type SmartDevice = Box<dyn Device>;
+
 impl From<SmartThermometer> for SmartDevice {
     fn from(value: SmartThermometer) -> Self {
-        Self::Thermometer(value)
+        Box::new(value)
     }
 }
 
 impl From<SmartElectricalSoket> for SmartDevice {
     fn from(value: SmartElectricalSoket) -> Self {
-        Self::ElectricalSocket(value)
+        Box::new(value)
     }
 }
 
-impl Report for SmartDevice {
-    fn report(&self) -> String {
-        match self {
-            SmartDevice::Thermometer(thermo) => format!("| -- {}", thermo),
-            SmartDevice::ElectricalSocket(socket) => format!("| -- {}", socket),
+/// Порядок обхода комнат и устройств в отчетах, итераторах и сохраненных файлах
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SortOrder {
+    /// В порядке добавления
+    #[default]
+    Insertion,
+    /// По алфавиту ключей
+    ByKey,
+    /// По типу устройства, затем по ключу. Комнаты упорядочиваются по ключу.
+    ByKind,
+}
+
+/// Следующий свободный идентификатор; комнаты и устройства нумеруются вместе
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Запоминает идентификатор, полученный извне (например, из файла),
+/// чтобы новые идентификаторы с ним не совпадали
+#[cfg(feature = "serde")]
+pub(crate) fn reserve_id(id: u64) {
+    NEXT_ID.fetch_max(id.saturating_add(1), Ordering::Relaxed);
+}
+
+/// Постоянный идентификатор устройства
+/// Выдается при добавлении устройства в комнату и не меняется при переносе,
+/// переименовании и сохранении дома в файл.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct DeviceId(u64);
+
+/// Постоянный идентификатор комнаты, выдается при создании комнаты
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct RoomId(u64);
+
+macro_rules! impl_id {
+    ($id: ident) => {
+        impl $id {
+            fn generate() -> Self {
+                Self(next_id())
+            }
+
+            /// Числовое значение идентификатора
+            pub fn get(self) -> u64 {
+                self.0
+            }
+        }
+
+        impl From<u64> for $id {
+            fn from(value: u64) -> Self {
+                Self(value)
+            }
+        }
+
+        impl fmt::Display for $id {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl FromStr for $id {
+            type Err = ParseIntError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                value.parse().map(Self)
+            }
         }
+    };
+}
+
+impl_id!(DeviceId);
+impl_id!(RoomId);
+
+/// Устройство комнаты вместе с его идентификатором
+#[derive(Debug, Clone)]
+struct DeviceSlot {
+    id: DeviceId,
+    device: SmartDevice,
+}
+
+/// Проверяет ключ комнаты или устройства: ключ не пустой и не начинается
+/// и не заканчивается пробелами, чтобы опечатка не создавала похожий ключ
+pub(crate) fn check_key(key: &str) -> Result<(), SmartHomeErrors> {
+    if key.is_empty() || key.trim() != key {
+        return Err(SmartHomeErrors::invalid_value(
+            "key",
+            format!("'{}' is empty or has surrounding whitespace", key),
+        ));
     }
+    Ok(())
+}
+
+/// Привязка комнаты к шине событий дома
+#[derive(Debug, Clone)]
+struct RoomEvents {
+    bus: EventBus,
+    key: String,
+    /// Отложенные события; их рассылает владелец комнаты после снятия блокировки
+    deferred: Option<Vec<HomeEvent>>,
 }
 
+/// Комната с устройствами
+/// Устройства хранятся по ключам, уникальным в пределах комнаты. Ключ не обязан
+/// совпадать с именем устройства; при добавлении без ключа им становится имя.
+/// Кроме ключа, у комнаты и каждого устройства есть постоянный идентификатор.
 #[derive(Debug)]
 pub struct Room {
+    id: RoomId,
     name: String,
-    devices: HashMap<String, SmartDevice>,
+    devices: IndexMap<String, DeviceSlot>,
+    order: SortOrder,
+    events: Option<RoomEvents>,
 }
 
 impl Room {
     pub fn new(name: String) -> Self {
         Self {
+            id: RoomId::generate(),
             name,
-            devices: HashMap::new(),
+            devices: IndexMap::new(),
+            order: SortOrder::default(),
+            events: None,
         }
     }
 
-    pub fn add_device_with_key(&mut self, device_key: String, new_device: SmartDevice) {
-        self.devices.insert(device_key, new_device);
+    pub(crate) fn attach(&mut self, bus: EventBus, key: String) {
+        self.events = Some(RoomEvents {
+            bus,
+            key,
+            deferred: None,
+        });
+    }
+
+    /// Привязывает комнату к шине так, что события копятся до `take_deferred_events`
+    pub(crate) fn attach_deferred(&mut self, bus: EventBus, key: String) {
+        self.events = Some(RoomEvents {
+            bus,
+            key,
+            deferred: Some(Vec::new()),
+        });
+    }
+
+    /// Забирает отложенные события комнаты
+    pub(crate) fn take_deferred_events(&mut self) -> Vec<HomeEvent> {
+        self.events
+            .as_mut()
+            .and_then(|events| events.deferred.as_mut())
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn emit(&mut self, event: impl FnOnce(String) -> HomeEvent) {
+        if let Some(events) = &mut self.events {
+            let event = event(events.key.clone());
+            match &mut events.deferred {
+                Some(deferred) => deferred.push(event),
+                None => events.bus.emit(event),
+            }
+        }
+    }
+
+    /// Добавляет устройство; если ключ уже занят, возвращает `DuplicateDevice`
+    pub fn add_device_with_key(
+        &mut self,
+        device_key: String,
+        new_device: SmartDevice,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&device_key)?;
+        if self.devices.contains_key(&device_key) {
+            return Err(SmartHomeErrors::DuplicateDevice(device_key));
+        }
+        self.insert_device(device_key, DeviceId::generate(), new_device);
+        Ok(())
+    }
+
+    /// Добавляет устройство с сохраненным идентификатором; идентификатор
+    /// не должен совпадать с идентификаторами комнаты и ее устройств
+    #[cfg(feature = "serde")]
+    pub(crate) fn add_device_with_id(
+        &mut self,
+        device_key: String,
+        id: DeviceId,
+        new_device: SmartDevice,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&device_key)?;
+        if self.devices.contains_key(&device_key) {
+            return Err(SmartHomeErrors::DuplicateDevice(device_key));
+        }
+        if self.ids().any(|taken| taken == id.0) {
+            return Err(SmartHomeErrors::invalid_value(
+                "id",
+                format!("{} is used more than once", id),
+            ));
+        }
+        reserve_id(id.0);
+        self.insert_device(device_key, id, new_device);
+        Ok(())
+    }
+
+    /// Добавляет устройство с ключом, равным его имени
+    pub fn add_device(&mut self, new_device: SmartDevice) -> Result<(), SmartHomeErrors> {
+        self.add_device_with_key(new_device.name().to_string(), new_device)
+    }
+
+    /// Заменяет существующее устройство и возвращает прежнее.
+    /// Новое устройство получает новый идентификатор.
+    /// Подписчики получают удаление прежнего устройства и добавление нового.
+    pub fn replace_device(
+        &mut self,
+        device_key: &str,
+        new_device: SmartDevice,
+    ) -> Result<SmartDevice, SmartHomeErrors> {
+        let Some(slot) = self.devices.get_mut(device_key) else {
+            return Err(SmartHomeErrors::DeviceNotFound(device_key.to_string()));
+        };
+        let old_device = std::mem::replace(
+            slot,
+            DeviceSlot {
+                id: DeviceId::generate(),
+                device: new_device,
+            },
+        )
+        .device;
+        self.emit(|room| HomeEvent::DeviceRemoved {
+            room,
+            device: device_key.to_string(),
+        });
+        self.emit(|room| HomeEvent::DeviceAdded {
+            room,
+            device: device_key.to_string(),
+        });
+        Ok(old_device)
+    }
+
+    /// Добавляет устройство или заменяет существующее, возвращая прежнее
+    pub fn upsert_device(
+        &mut self,
+        device_key: String,
+        new_device: SmartDevice,
+    ) -> Result<Option<SmartDevice>, SmartHomeErrors> {
+        check_key(&device_key)?;
+        if self.devices.contains_key(&device_key) {
+            return self.replace_device(&device_key, new_device).map(Some);
+        }
+        self.insert_device(device_key, DeviceId::generate(), new_device);
+        Ok(None)
+    }
+
+    fn insert_device(&mut self, device_key: String, id: DeviceId, new_device: SmartDevice) {
+        self.devices.insert(
+            device_key.clone(),
+            DeviceSlot {
+                id,
+                device: new_device,
+            },
+        );
+        self.emit(|room| HomeEvent::DeviceAdded {
+            room,
+            device: device_key,
+        });
+    }
+
+    fn remove_device(&mut self, device_key: &str) -> Option<DeviceSlot> {
+        let slot = self.devices.shift_remove(device_key)?;
+        self.emit(|room| HomeEvent::DeviceRemoved {
+            room,
+            device: device_key.to_string(),
+        });
+        Some(slot)
     }
 
     pub fn delete_device(&mut self, device_name: &str) -> Result<(), SmartHomeErrors> {
-        if !self.devices.contains_key(device_name) {
-            return Err(SmartHomeErrors::DeviceNotFound(device_name.to_string()));
+        self.remove_device(device_name)
+            .map(|_| ())
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(device_name.to_string()))
+    }
+
+    /// Меняет ключ устройства, сохраняя его состояние, идентификатор и позицию.
+    /// Подписчики получают удаление устройства со старым ключом и добавление с новым.
+    pub fn rename_device(
+        &mut self,
+        device_key: &str,
+        new_key: String,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&new_key)?;
+        let Some(index) = self.devices.get_index_of(device_key) else {
+            return Err(SmartHomeErrors::DeviceNotFound(device_key.to_string()));
         };
-        self.devices.remove(device_name);
+        if new_key == device_key {
+            return Ok(());
+        }
+        if self.devices.contains_key(&new_key) {
+            return Err(SmartHomeErrors::DuplicateDevice(new_key));
+        }
+        let slot = self.remove_device(device_key).expect("index is valid");
+        self.devices.shift_insert(index, new_key.clone(), slot);
+        self.emit(|room| HomeEvent::DeviceAdded {
+            room,
+            device: new_key,
+        });
+        Ok(())
+    }
+
+    /// Переносит устройство в комнату `target` под тем же ключом, сохраняя его
+    /// состояние и идентификатор. При ошибке обе комнаты остаются без изменений.
+    pub fn move_device_to(
+        &mut self,
+        device_key: &str,
+        target: &mut Room,
+    ) -> Result<(), SmartHomeErrors> {
+        if !self.devices.contains_key(device_key) {
+            return Err(SmartHomeErrors::DeviceNotFound(device_key.to_string()));
+        }
+        if target.devices.contains_key(device_key) {
+            return Err(SmartHomeErrors::DuplicateDevice(device_key.to_string()));
+        }
+        let slot = self.remove_device(device_key).expect("checked above");
+        target.insert_device(device_key.to_string(), slot.id, slot.device);
         Ok(())
     }
 
-    pub fn get_device(&self, device_name: &str) -> Option<&SmartDevice> {
-        self.devices.get(device_name)
+    /// Изменяет устройство и сообщает подписчикам дома, если изменилось его описание
+    /// или поля отчета (например, мощность розетки при том же описании).
+    /// Изменения через `get_mutable_device` событий не порождают.
+    pub fn update_device<R>(
+        &mut self,
+        device_name: &str,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let device = &mut self
+            .devices
+            .get_mut(device_name)
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(device_name.to_string()))?
+            .device;
+        let (old_status, old_fields) = (device.status(), device.fields());
+        let result = update(device.as_mut());
+        let new_status = device.status();
+        if old_status != new_status || old_fields != device.fields() {
+            self.emit(|room| HomeEvent::StateChanged {
+                room,
+                device: device_name.to_string(),
+                old_status,
+                new_status,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Изменяет устройство по идентификатору, как `update_device`
+    pub fn update_device_by_id<R>(
+        &mut self,
+        id: DeviceId,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        let key = self
+            .device_key(id)
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))?
+            .to_string();
+        self.update_device(&key, update)
+    }
+
+    pub fn get_device(&self, device_name: &str) -> Option<&dyn Device> {
+        self.devices
+            .get(device_name)
+            .map(|slot| slot.device.as_ref())
+    }
+
+    pub fn get_mutable_device(&mut self, device_name: &str) -> Option<&mut dyn Device> {
+        self.devices
+            .get_mut(device_name)
+            .map(|slot| slot.device.as_mut())
+    }
+
+    pub fn get_device_by_id(&self, id: DeviceId) -> Option<&dyn Device> {
+        self.devices
+            .values()
+            .find(|slot| slot.id == id)
+            .map(|slot| slot.device.as_ref())
+    }
+
+    pub fn get_mutable_device_by_id(&mut self, id: DeviceId) -> Option<&mut dyn Device> {
+        self.devices
+            .values_mut()
+            .find(|slot| slot.id == id)
+            .map(|slot| slot.device.as_mut())
+    }
+
+    pub fn id(&self) -> RoomId {
+        self.id
+    }
+
+    /// Идентификатор устройства с ключом `device_key`
+    pub fn device_id(&self, device_key: &str) -> Option<DeviceId> {
+        self.devices.get(device_key).map(|slot| slot.id)
+    }
+
+    /// Ключ устройства с идентификатором `id`
+    pub fn device_key(&self, id: DeviceId) -> Option<&str> {
+        self.devices
+            .iter()
+            .find(|(_, slot)| slot.id == id)
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Идентификаторы комнаты и всех ее устройств
+    pub(crate) fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(self.id.0).chain(self.devices.values().map(|slot| slot.id.0))
     }
 
-    pub fn get_mutable_device(&mut self, device_name: &str) -> Option<&mut SmartDevice> {
-        self.devices.get_mut(device_name)
+    /// Выдает новые идентификаторы комнате и устройствам, чьи идентификаторы
+    /// уже заняты в доме или повторяются внутри самой комнаты
+    pub(crate) fn renew_taken_ids(&mut self, mut taken: HashSet<u64>) {
+        if !taken.insert(self.id.0) {
+            self.id = RoomId::generate();
+        }
+        for slot in self.devices.values_mut() {
+            if !taken.insert(slot.id.0) {
+                slot.id = DeviceId::generate();
+            }
+        }
+    }
+
+    /// Точная копия комнаты с теми же идентификаторами, не привязанная к шине
+    /// событий; нужна для снимков дома, которые сохраняются в файл
+    pub(crate) fn snapshot(&self) -> Self {
+        Self {
+            id: self.id,
+            name: self.name.clone(),
+            devices: self.devices.clone(),
+            order: self.order,
+            events: None,
+        }
+    }
+
+    /// Восстанавливает сохраненный идентификатор комнаты
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_id(&mut self, id: RoomId) {
+        reserve_id(id.0);
+        self.id = id;
     }
 
     pub fn get_name(&self) -> &str {
         &self.name
     }
+
+    pub fn get_sort_order(&self) -> SortOrder {
+        self.order
+    }
+
+    pub fn set_sort_order(&mut self, order: SortOrder) {
+        self.order = order;
+    }
+
+    /// Устройства комнаты вместе с их ключами в порядке `SortOrder`
+    pub fn devices(&self) -> impl Iterator<Item = (&str, &dyn Device)> {
+        let mut devices: Vec<(&str, &dyn Device)> = self
+            .devices
+            .iter()
+            .map(|(key, slot)| (key.as_str(), slot.device.as_ref()))
+            .collect();
+        match self.order {
+            SortOrder::Insertion => {}
+            SortOrder::ByKey => devices.sort_by_key(|(key, _)| *key),
+            SortOrder::ByKind => devices.sort_by(|(left_key, left), (right_key, right)| {
+                (left.kind(), left_key).cmp(&(right.kind(), right_key))
+            }),
+        }
+        devices.into_iter()
+    }
+}
+
+/// Копия комнаты не привязана к шине событий дома и получает новые
+/// идентификаторы, поэтому ее можно добавить в дом рядом с оригиналом
+impl Clone for Room {
+    fn clone(&self) -> Self {
+        let mut copy = self.snapshot();
+        copy.id = RoomId::generate();
+        for slot in copy.devices.values_mut() {
+            slot.id = DeviceId::generate();
+        }
+        copy
+    }
 }
 
 impl Report for Room {
     fn report(&self) -> String {
-        let mut out = String::new();
-        out.push_str(&format!("Комната '{}': \n", self.name));
-        for device in self.devices.values() {
-            out.push_str(&device.report());
-            out.push('\n');
-        }
-        out
+        RoomReport::from(self).render(ReportFormat::Text)
     }
 }
 
+/// Дом с комнатами
+/// Комнаты хранятся по ключам, уникальным в пределах дома. Как и у устройств,
+/// ключ комнаты, добавленной без явного ключа, равен ее имени.
 #[derive(Debug)]
 pub struct SmartHome {
     name: String,
-    rooms: HashMap<String, Room>,
+    rooms: IndexMap<String, Room>,
+    order: SortOrder,
+    events: EventBus,
+    scenes: SceneBook,
 }
 
 impl SmartHome {
+    /// Создает дом; ключами комнат становятся их имена.
+    /// Комната с повторяющимся именем заменяет предыдущую, имена не проверяются.
+    #[deprecated(note = "use `SmartHome::try_new`, which rejects duplicate and invalid room names")]
     pub fn new(home_name: String, rooms: Vec<Room>) -> Self {
-        let mut added_rooms = HashMap::new();
+        let mut home = Self::empty(home_name);
         for room in rooms {
-            added_rooms.insert(room.name.clone(), room);
+            let key = room.name.clone();
+            let replaced = home.rooms.get_index_of(&key);
+            let room = home.prepare_room(&key, room, replaced);
+            home.rooms.insert(key, room);
+        }
+        home
+    }
+
+    /// Пустой дом без комнат
+    fn empty(home_name: String) -> Self {
+        Self::from_parts(
+            home_name,
+            IndexMap::new(),
+            SortOrder::default(),
+            EventBus::new(),
+            SceneBook::default(),
+        )
+    }
+
+    /// Создает дом, возвращая ошибку при повторяющихся именах комнат
+    pub fn try_new(home_name: String, rooms: Vec<Room>) -> Result<Self, SmartHomeErrors> {
+        let mut home = Self::empty(home_name);
+        for room in rooms {
+            home.add_room(room)?;
+        }
+        Ok(home)
+    }
+
+    /// Собирает дом из готовых комнат, привязывая их к шине `events`
+    pub(crate) fn from_parts(
+        name: String,
+        mut rooms: IndexMap<String, Room>,
+        order: SortOrder,
+        events: EventBus,
+        scenes: SceneBook,
+    ) -> Self {
+        for (key, room) in rooms.iter_mut() {
+            room.attach(events.clone(), key.clone());
         }
         Self {
-            name: home_name,
-            rooms: added_rooms,
+            name,
+            rooms,
+            order,
+            events,
+            scenes,
+        }
+    }
+
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        String,
+        IndexMap<String, Room>,
+        SortOrder,
+        EventBus,
+        SceneBook,
+    ) {
+        (self.name, self.rooms, self.order, self.events, self.scenes)
+    }
+
+    /// Группы и сцены дома
+    pub(crate) fn scene_book(&self) -> &SceneBook {
+        &self.scenes
+    }
+
+    pub(crate) fn scene_book_mut(&mut self) -> &mut SceneBook {
+        &mut self.scenes
+    }
+
+    /// Шина событий дома для подписки на изменения
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Добавляет комнату; если ключ уже занят, возвращает `DuplicateRoom`.
+    /// Если дом использует порядок, отличный от порядка добавления, комната упорядочивает устройства так же.
+    pub fn add_room_with_key(
+        &mut self,
+        room_key: String,
+        new_room: Room,
+    ) -> Result<(), SmartHomeErrors> {
+        check_key(&room_key)?;
+        if self.rooms.contains_key(&room_key) {
+            return Err(SmartHomeErrors::DuplicateRoom(room_key));
         }
+        self.insert_room(room_key, new_room);
+        Ok(())
+    }
+
+    /// Добавляет комнату с ключом, равным ее имени
+    pub fn add_room(&mut self, new_room: Room) -> Result<(), SmartHomeErrors> {
+        self.add_room_with_key(new_room.name.clone(), new_room)
     }
 
-    pub fn add_room_with_key(&mut self, room_key: String, new_room: Room) {
-        self.rooms.insert(room_key, new_room);
+    /// Заменяет существующую комнату и возвращает прежнюю, уже не привязанную к дому.
+    /// Подписчики получают удаление прежней комнаты и добавление новой.
+    pub fn replace_room(
+        &mut self,
+        room_key: &str,
+        new_room: Room,
+    ) -> Result<Room, SmartHomeErrors> {
+        let Some(index) = self.rooms.get_index_of(room_key) else {
+            return Err(SmartHomeErrors::RoomNotFound(room_key.to_string()));
+        };
+        let new_room = self.prepare_room(room_key, new_room, Some(index));
+        let mut old_room = std::mem::replace(&mut self.rooms[index], new_room);
+        old_room.events = None;
+        self.events.emit(HomeEvent::RoomRemoved {
+            room: room_key.to_string(),
+        });
+        self.events.emit(HomeEvent::RoomAdded {
+            room: room_key.to_string(),
+        });
+        Ok(old_room)
+    }
+
+    /// Добавляет комнату или заменяет существующую, возвращая прежнюю
+    pub fn upsert_room(
+        &mut self,
+        room_key: String,
+        new_room: Room,
+    ) -> Result<Option<Room>, SmartHomeErrors> {
+        check_key(&room_key)?;
+        if self.rooms.contains_key(&room_key) {
+            return self.replace_room(&room_key, new_room).map(Some);
+        }
+        self.insert_room(room_key, new_room);
+        Ok(None)
+    }
+
+    /// Готовит комнату к добавлению; `replaced` — индекс заменяемой комнаты,
+    /// идентификаторы которой можно занять
+    fn prepare_room(&self, room_key: &str, mut new_room: Room, replaced: Option<usize>) -> Room {
+        let taken: HashSet<u64> = self
+            .rooms
+            .values()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != replaced)
+            .flat_map(|(_, room)| room.ids())
+            .collect();
+        new_room.renew_taken_ids(taken);
+        if self.order != SortOrder::Insertion {
+            new_room.set_sort_order(self.order);
+        }
+        new_room.attach(self.events.clone(), room_key.to_string());
+        new_room
+    }
+
+    fn insert_room(&mut self, room_key: String, new_room: Room) {
+        let new_room = self.prepare_room(&room_key, new_room, None);
+        self.rooms.insert(room_key.clone(), new_room);
+        self.events.emit(HomeEvent::RoomAdded { room: room_key });
     }
     pub fn get_device_from_room(
         &self,
         room_name: &str,
         device_name: &str,
-    ) -> Result<&SmartDevice, SmartHomeErrors> {
+    ) -> Result<&dyn Device, SmartHomeErrors> {
         match self.get_room(room_name) {
             Some(room) => match room.get_device(device_name) {
                 Some(device) => Ok(device),
@@ -123,30 +789,185 @@ impl SmartHome {
         }
     }
 
+    /// Переносит устройство между комнатами, сохраняя его состояние, ключ и идентификатор.
+    /// Если комнаты или устройства нет либо ключ в целевой комнате занят, дом не меняется.
+    pub fn move_device(
+        &mut self,
+        from_room: &str,
+        to_room: &str,
+        device_key: &str,
+    ) -> Result<(), SmartHomeErrors> {
+        let index_of = |room: &str| {
+            self.rooms
+                .get_index_of(room)
+                .ok_or_else(|| SmartHomeErrors::RoomNotFound(room.to_string()))
+        };
+        let (from, to) = (index_of(from_room)?, index_of(to_room)?);
+        if from == to {
+            return self.rooms[from]
+                .get_device(device_key)
+                .map(|_| ())
+                .ok_or_else(|| SmartHomeErrors::DeviceNotFound(device_key.to_string()));
+        }
+        let [(_, source), (_, target)] = self
+            .rooms
+            .get_disjoint_indices_mut([from, to])
+            .expect("indices are valid and distinct");
+        source.move_device_to(device_key, target)
+    }
+
+    /// Меняет ключ устройства в комнате, как `Room::rename_device`
+    pub fn rename_device(
+        &mut self,
+        room_name: &str,
+        device_key: &str,
+        new_key: String,
+    ) -> Result<(), SmartHomeErrors> {
+        self.get_mutable_room(room_name)
+            .ok_or_else(|| SmartHomeErrors::RoomNotFound(room_name.to_string()))?
+            .rename_device(device_key, new_key)
+    }
+
+    /// Меняет ключ комнаты, сохраняя ее устройства, идентификатор и позицию.
+    /// Подписчики получают удаление комнаты со старым ключом и добавление с новым.
+    pub fn rename_room(&mut self, room_key: &str, new_key: String) -> Result<(), SmartHomeErrors> {
+        check_key(&new_key)?;
+        let Some(index) = self.rooms.get_index_of(room_key) else {
+            return Err(SmartHomeErrors::RoomNotFound(room_key.to_string()));
+        };
+        if new_key == room_key {
+            return Ok(());
+        }
+        if self.rooms.contains_key(&new_key) {
+            return Err(SmartHomeErrors::DuplicateRoom(new_key));
+        }
+        let (_, mut room) = self
+            .rooms
+            .shift_remove_index(index)
+            .expect("index is valid");
+        room.attach(self.events.clone(), new_key.clone());
+        self.rooms.shift_insert(index, new_key.clone(), room);
+        self.events.emit(HomeEvent::RoomRemoved {
+            room: room_key.to_string(),
+        });
+        self.events.emit(HomeEvent::RoomAdded { room: new_key });
+        Ok(())
+    }
+
     pub fn delete_room(&mut self, room_name: &str) -> Result<(), SmartHomeErrors> {
         if !self.rooms.contains_key(room_name) {
             return Err(SmartHomeErrors::RoomNotFound(room_name.to_string()));
         };
-        self.rooms.remove(room_name);
+        if let Some(mut room) = self.rooms.shift_remove(room_name) {
+            room.events = None;
+        }
+        self.events.emit(HomeEvent::RoomRemoved {
+            room: room_name.to_string(),
+        });
         Ok(())
     }
 
+    /// Изменяет устройство в комнате и сообщает подписчикам об изменении состояния
+    pub fn update_device<R>(
+        &mut self,
+        room_name: &str,
+        device_name: &str,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        self.get_mutable_room(room_name)
+            .ok_or_else(|| SmartHomeErrors::RoomNotFound(room_name.to_string()))?
+            .update_device(device_name, update)
+    }
+
     pub fn get_room(&self, room_name: &str) -> Option<&Room> {
         self.rooms.get(room_name)
     }
     pub fn get_mutable_room(&mut self, room_name: &str) -> Option<&mut Room> {
         self.rooms.get_mut(room_name)
     }
+
+    /// Ключ комнаты с идентификатором `id`
+    pub fn room_key(&self, id: RoomId) -> Option<&str> {
+        self.rooms
+            .iter()
+            .find(|(_, room)| room.id == id)
+            .map(|(key, _)| key.as_str())
+    }
+
+    pub fn get_room_by_id(&self, id: RoomId) -> Option<&Room> {
+        self.rooms.values().find(|room| room.id == id)
+    }
+
+    pub fn get_mutable_room_by_id(&mut self, id: RoomId) -> Option<&mut Room> {
+        self.rooms.values_mut().find(|room| room.id == id)
+    }
+
+    /// Ключи комнаты и устройства с идентификатором `id`
+    pub fn locate_device(&self, id: DeviceId) -> Option<(&str, &str)> {
+        self.rooms.iter().find_map(|(room_key, room)| {
+            room.device_key(id)
+                .map(|device_key| (room_key.as_str(), device_key))
+        })
+    }
+
+    pub fn get_device_by_id(&self, id: DeviceId) -> Result<&dyn Device, SmartHomeErrors> {
+        self.rooms
+            .values()
+            .find_map(|room| room.get_device_by_id(id))
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))
+    }
+
+    /// Изменяет устройство по идентификатору и сообщает подписчикам об изменении состояния
+    pub fn update_device_by_id<R>(
+        &mut self,
+        id: DeviceId,
+        update: impl FnOnce(&mut dyn Device) -> R,
+    ) -> Result<R, SmartHomeErrors> {
+        self.rooms
+            .values_mut()
+            .find(|room| room.device_key(id).is_some())
+            .ok_or_else(|| SmartHomeErrors::DeviceNotFound(id.to_string()))?
+            .update_device_by_id(id, update)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_sort_order(&self) -> SortOrder {
+        self.order
+    }
+
+    /// Задает порядок обхода комнат дома и устройств во всех его комнатах
+    pub fn set_sort_order(&mut self, order: SortOrder) {
+        self.order = order;
+        for room in self.rooms.values_mut() {
+            room.set_sort_order(order);
+        }
+    }
+
+    /// Восстанавливает сохраненный порядок дома, не меняя порядок комнат
+    #[cfg(feature = "serde")]
+    pub(crate) fn restore_sort_order(&mut self, order: SortOrder) {
+        self.order = order;
+    }
+
+    /// Комнаты дома вместе с их ключами в порядке `SortOrder`
+    pub fn rooms(&self) -> impl Iterator<Item = (&str, &Room)> {
+        let mut rooms: Vec<(&str, &Room)> = self
+            .rooms
+            .iter()
+            .map(|(key, room)| (key.as_str(), room))
+            .collect();
+        if self.order != SortOrder::Insertion {
+            rooms.sort_by_key(|(key, _)| *key);
+        }
+        rooms.into_iter()
+    }
 }
 
 impl Report for SmartHome {
     fn report(&self) -> String {
-        let mut out = String::new();
-        out.push_str(&format!("Отчет для дома: {}\n", self.name).to_string());
-        out.push('\n');
-        for room in self.rooms.values() {
-            out.push_str(&room.report());
-        }
-        out
+        HomeReport::from(self).render(ReportFormat::Text)
     }
 }
@@ -0,0 +1,206 @@
+//! Сервер и клиент для управления умной розеткой по TCP
+
+use crate::{
+    errors::{ConnectionErrors, SmartHomeErrors},
+    protocol::{Command, ErrorCode, PROTOCOL_VERSION, Response, read_frame, write_frame},
+    smart_devices::SmartElectricalSoket,
+};
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// Сервер, предоставляющий доступ к розетке по сети
+/// Каждое подключение обслуживается в отдельном потоке.
+pub struct SocketServer {
+    listener: TcpListener,
+    socket: Arc<Mutex<SmartElectricalSoket>>,
+}
+
+impl SocketServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A, socket: SmartElectricalSoket) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            socket: Arc::new(Mutex::new(socket)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Общий доступ к обслуживаемой розетке
+    pub fn socket(&self) -> Arc<Mutex<SmartElectricalSoket>> {
+        Arc::clone(&self.socket)
+    }
+
+    /// Принимает подключения, блокируя текущий поток
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let socket = Arc::clone(&self.socket);
+            thread::spawn(move || {
+                // Ошибка одного клиента не должна останавливать сервер
+                let _ = handle_connection(stream, socket);
+            });
+        }
+    }
+
+    /// Запускает сервер в фоновом потоке
+    pub fn spawn(self) -> JoinHandle<()> {
+        thread::spawn(move || self.run())
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    socket: Arc<Mutex<SmartElectricalSoket>>,
+) -> Result<(), ConnectionErrors> {
+    loop {
+        let (version, payload) = match read_frame(&mut stream) {
+            Ok(frame) => frame,
+            Err(ConnectionErrors::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+        let response = respond(version, &payload, &socket);
+        write_frame(&mut stream, &response.encode())?;
+    }
+}
+
+/// Ответ на полученный кадр
+pub(crate) fn respond(
+    version: u8,
+    payload: &[u8],
+    socket: &Mutex<SmartElectricalSoket>,
+) -> Response {
+    if version != PROTOCOL_VERSION {
+        return Response::Error(ErrorCode::UnsupportedVersion);
+    }
+    match Command::decode(payload) {
+        Ok(command) => execute(command, socket),
+        Err(code) => Response::Error(code),
+    }
+}
+
+fn execute(command: Command, socket: &Mutex<SmartElectricalSoket>) -> Response {
+    let Ok(mut socket) = socket.lock() else {
+        return Response::Error(ErrorCode::Internal);
+    };
+    match command {
+        Command::TurnOn => match socket.turn_on() {
+            Ok(()) => Response::Ok,
+            Err(_) => Response::Error(ErrorCode::DeviceOffline),
+        },
+        Command::TurnOff => {
+            socket.turn_off();
+            Response::Ok
+        }
+        Command::Switch => match socket.switch() {
+            Ok(()) => Response::Ok,
+            Err(_) => Response::Error(ErrorCode::DeviceOffline),
+        },
+        Command::IsOn => Response::State(socket.is_on()),
+        Command::GetPower => Response::Power(socket.get_power()),
+        Command::GetName => Response::Name(socket.get_name().to_string()),
+    }
+}
+
+/// Клиент удалённой розетки
+/// Повторяет API `SmartElectricalSoket`, но каждая операция выполняется по сети.
+/// Недоступность розетки возвращается как `SmartHomeErrors::DeviceOffline`.
+pub struct SocketClient {
+    stream: TcpStream,
+    device: String,
+}
+
+impl SocketClient {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, SmartHomeErrors> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let device = addrs
+            .first()
+            .map_or_else(String::new, SocketAddr::to_string);
+        let stream = TcpStream::connect(addrs.as_slice())
+            .map_err(|err| SmartHomeErrors::connection(&device, err.into()))?;
+        Ok(Self { stream, device })
+    }
+
+    /// Адрес розетки, которым она обозначается в ошибках
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    fn request(&mut self, command: Command) -> Result<Response, SmartHomeErrors> {
+        self.exchange(command)
+            .map_err(|err| SmartHomeErrors::connection(&self.device, err))
+    }
+
+    fn exchange(&mut self, command: Command) -> Result<Response, ConnectionErrors> {
+        write_frame(&mut self.stream, &command.encode())?;
+        let (version, payload) = read_frame(&mut self.stream)?;
+        parse_response(version, &payload)
+    }
+
+    fn unexpected(&self, response: Response) -> SmartHomeErrors {
+        SmartHomeErrors::connection(
+            &self.device,
+            ConnectionErrors::UnexpectedResponse(format!("{:?}", response)),
+        )
+    }
+
+    fn expect_ok(&mut self, command: Command) -> Result<(), SmartHomeErrors> {
+        match self.request(command)? {
+            Response::Ok => Ok(()),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub fn turn_on(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::TurnOn)
+    }
+
+    pub fn turn_off(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::TurnOff)
+    }
+
+    pub fn switch(&mut self) -> Result<(), SmartHomeErrors> {
+        self.expect_ok(Command::Switch)
+    }
+
+    pub fn is_on(&mut self) -> Result<bool, SmartHomeErrors> {
+        match self.request(Command::IsOn)? {
+            Response::State(is_on) => Ok(is_on),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub fn get_power(&mut self) -> Result<f32, SmartHomeErrors> {
+        match self.request(Command::GetPower)? {
+            Response::Power(power) => Ok(power),
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    pub fn get_name(&mut self) -> Result<String, SmartHomeErrors> {
+        match self.request(Command::GetName)? {
+            Response::Name(name) => Ok(name),
+            other => Err(self.unexpected(other)),
+        }
+    }
+}
+
+/// Разбирает ответ сервера; ошибки сервера превращаются в `ConnectionErrors::Remote`
+pub(crate) fn parse_response(version: u8, payload: &[u8]) -> Result<Response, ConnectionErrors> {
+    if version != PROTOCOL_VERSION {
+        return Err(ConnectionErrors::UnsupportedVersion(version));
+    }
+    match Response::decode(payload)? {
+        Response::Error(code) => Err(ConnectionErrors::Remote(code)),
+        response => Ok(response),
+    }
+}
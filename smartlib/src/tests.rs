@@ -1,6 +1,7 @@
 use crate::{
+    errors::SmartHomeErrors,
     smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures},
-    structures::{Room, SmartDevice, SmartHome},
+    structures::{Report, Room, SmartDevice, SmartHome, SortOrder},
 };
 
 fn create_room() -> Room {
@@ -8,18 +9,20 @@ fn create_room() -> Room {
         SmartThermometer::new(String::from("RoomThermometer"), TempMeasures::C, 24.0);
     let some_electrical_soket = SmartElectricalSoket::new(String::from("ComputerSoket"), 220.0);
 
-    let another_soket =
-        SmartDevice::ElectricalSocket(SmartElectricalSoket::new(String::from("Router"), 210.0));
+    let another_soket = SmartDevice::from(SmartElectricalSoket::new(String::from("Router"), 210.0));
 
     let mut room = Room::new(String::from("Гостинная"));
-    room.add_device_with_key(String::from("RoomThermometer"), new_termometer.into());
-    room.add_device_with_key(String::from("ComputerSoket"), some_electrical_soket.into());
-    room.add_device_with_key(String::from("Router"), another_soket);
+    room.add_device_with_key(String::from("RoomThermometer"), new_termometer.into())
+        .unwrap();
+    room.add_device_with_key(String::from("ComputerSoket"), some_electrical_soket.into())
+        .unwrap();
+    room.add_device_with_key(String::from("Router"), another_soket)
+        .unwrap();
     room
 }
 
 fn create_home(rooms: Vec<Room>) -> SmartHome {
-    SmartHome::new(String::from("TestHome"), rooms)
+    SmartHome::try_new(String::from("TestHome"), rooms).unwrap()
 }
 
 #[test]
@@ -34,12 +37,26 @@ fn test_mut_room_get_device_option() {
     assert!(room.get_mutable_device("RoomThermometer").is_some());
 }
 
+#[test]
+fn test_room_downcast_mutable_device() {
+    let mut room = create_room();
+    let socket = room
+        .get_mutable_device("ComputerSoket")
+        .and_then(|device| device.downcast_mut::<SmartElectricalSoket>())
+        .unwrap();
+    socket.turn_on().unwrap();
+    let device = room.get_device("ComputerSoket").unwrap();
+    assert_eq!(device.status(), "включена");
+    assert_eq!(device.name(), "ComputerSoket");
+}
+
 #[test]
 fn test_room_add_and_delete_device() {
     let mut room = Room::new(String::from("Bedroom"));
     // add device
     let thermo = SmartThermometer::new(String::from("T"), TempMeasures::C, 20.0);
-    room.add_device_with_key(String::from("Thermo"), thermo.into());
+    room.add_device_with_key(String::from("Thermo"), thermo.into())
+        .unwrap();
     assert!(room.get_device("Thermo").is_some());
 
     // delete existing
@@ -90,10 +107,9 @@ fn test_home_get_device_and_errors() {
     let device = home
         .get_device_from_room("Гостинная", "RoomThermometer")
         .unwrap();
-    match device {
-        SmartDevice::Thermometer(_) => {}
-        _ => panic!("unexpected device type"),
-    }
+    assert!(device.is::<SmartThermometer>());
+    assert_eq!(device.kind(), "thermometer");
+    assert!(device.downcast_ref::<SmartElectricalSoket>().is_none());
 
     // missing device
     let err = home
@@ -118,7 +134,8 @@ fn test_home_get_device_and_errors() {
 fn test_home_add_and_delete_room() {
     let mut home = create_home(vec![]);
     let room = Room::new(String::from("Балкон"));
-    home.add_room_with_key(String::from("Балкон"), room);
+    home.add_room_with_key(String::from("Балкон"), room)
+        .unwrap();
     assert!(home.get_room("Балкон").is_some());
 
     assert!(home.delete_room("Балкон").is_ok());
@@ -130,3 +147,383 @@ fn test_home_add_and_delete_room() {
         _ => panic!("unexpected error variant"),
     }
 }
+
+fn create_unordered_room() -> Room {
+    let mut room = Room::new(String::from("Кухня"));
+    room.add_device_with_key(
+        String::from("S2"),
+        SmartElectricalSoket::new(String::from("Kettle"), 2000.0).into(),
+    )
+    .unwrap();
+    room.add_device_with_key(
+        String::from("T1"),
+        SmartThermometer::new(String::from("Termo"), TempMeasures::C, 22.0).into(),
+    )
+    .unwrap();
+    room.add_device_with_key(
+        String::from("A1"),
+        SmartElectricalSoket::new(String::from("Fridge"), 150.0).into(),
+    )
+    .unwrap();
+    room
+}
+
+fn device_keys(room: &Room) -> Vec<&str> {
+    room.devices().map(|(key, _)| key).collect()
+}
+
+#[test]
+fn test_room_sort_orders() {
+    let mut room = create_unordered_room();
+    assert_eq!(device_keys(&room), vec!["S2", "T1", "A1"]);
+
+    room.set_sort_order(SortOrder::ByKey);
+    assert_eq!(device_keys(&room), vec!["A1", "S2", "T1"]);
+
+    room.set_sort_order(SortOrder::ByKind);
+    assert_eq!(device_keys(&room), vec!["A1", "S2", "T1"]);
+
+    // Удаление сохраняет порядок добавления остальных устройств
+    room.set_sort_order(SortOrder::Insertion);
+    room.delete_device("S2").unwrap();
+    assert_eq!(device_keys(&room), vec!["T1", "A1"]);
+}
+
+#[test]
+fn test_home_report_is_deterministic() {
+    let mut home = create_home(vec![]);
+    home.add_room_with_key(String::from("Кухня"), create_unordered_room())
+        .unwrap();
+    home.add_room_with_key(String::from("Балкон"), Room::new(String::from("Балкон")))
+        .unwrap();
+
+    let expected = "Отчет для дома: TestHome\n\n\
+        Комната 'Кухня': \n\
+        | -- Розетка 'Kettle': выключена, мощность 0.0 Вт\n\
+        | -- Термометр 'Termo', Температура: 22° C\n\
+        | -- Розетка 'Fridge': выключена, мощность 0.0 Вт\n\
+        Комната 'Балкон': \n";
+    assert_eq!(home.report(), expected);
+
+    home.set_sort_order(SortOrder::ByKind);
+    let expected = "Отчет для дома: TestHome\n\n\
+        Комната 'Балкон': \n\
+        Комната 'Кухня': \n\
+        | -- Розетка 'Fridge': выключена, мощность 0.0 Вт\n\
+        | -- Розетка 'Kettle': выключена, мощность 0.0 Вт\n\
+        | -- Термометр 'Termo', Температура: 22° C\n";
+    assert_eq!(home.report(), expected);
+
+    // Новые комнаты получают порядок дома
+    home.add_room_with_key(String::from("Зал"), create_unordered_room())
+        .unwrap();
+    let room = home.get_room("Зал").unwrap();
+    assert_eq!(room.get_sort_order(), SortOrder::ByKind);
+}
+
+#[test]
+fn test_duplicate_keys_are_rejected() {
+    let mut room = create_room();
+    let err = room
+        .add_device_with_key(
+            String::from("Router"),
+            SmartElectricalSoket::new(String::from("Lamp"), 60.0).into(),
+        )
+        .unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::DuplicateDevice(ref key) if key == "Router"));
+    assert_eq!(err.code(), "duplicate_device");
+    // Прежнее устройство осталось на месте
+    assert_eq!(room.get_device("Router").unwrap().name(), "Router");
+
+    let mut home = create_home(vec![room]);
+    let err = home
+        .add_room(Room::new(String::from("Гостинная")))
+        .unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::DuplicateRoom(ref key) if key == "Гостинная"));
+    assert_eq!(err.code(), "duplicate_room");
+    assert_eq!(home.get_room("Гостинная").unwrap().devices().count(), 3);
+
+    let rooms = vec![
+        Room::new(String::from("Зал")),
+        Room::new(String::from("Зал")),
+    ];
+    assert!(matches!(
+        SmartHome::try_new(String::from("TestHome"), rooms),
+        Err(SmartHomeErrors::DuplicateRoom(_))
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_new_keeps_last_room_with_same_name() {
+    let rooms = vec![create_room(), Room::new(String::from("Гостинная"))];
+    let home = SmartHome::new(String::from("TestHome"), rooms);
+    assert_eq!(home.rooms().count(), 1);
+    assert_eq!(home.get_room("Гостинная").unwrap().devices().count(), 0);
+
+    let home = SmartHome::new(String::from("TestHome"), vec![Room::new(String::from(" "))]);
+    assert!(home.get_room(" ").is_some());
+}
+
+#[test]
+fn test_invalid_keys_are_rejected() {
+    let mut room = Room::new(String::from("Зал"));
+    for key in ["", " Lamp", "Lamp "] {
+        let device = SmartElectricalSoket::new(String::from("Lamp"), 60.0);
+        let err = room
+            .add_device_with_key(String::from(key), device.into())
+            .unwrap_err();
+        assert_eq!(err.code(), "invalid_value");
+    }
+    let mut home = create_home(vec![]);
+    assert!(
+        home.add_room_with_key(String::new(), Room::new(String::from("Зал")))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_add_without_key_uses_name() {
+    let mut room = Room::new(String::from("Зал"));
+    room.add_device(SmartElectricalSoket::new(String::from("Lamp"), 60.0).into())
+        .unwrap();
+    assert!(room.get_device("Lamp").is_some());
+    assert!(
+        room.add_device(SmartElectricalSoket::new(String::from("Lamp"), 40.0).into())
+            .is_err()
+    );
+
+    let mut home = create_home(vec![]);
+    home.add_room(room).unwrap();
+    assert!(home.get_device_from_room("Зал", "Lamp").is_ok());
+}
+
+#[test]
+fn test_replace_and_upsert() {
+    let mut room = create_room();
+    let lamp = SmartDevice::from(SmartElectricalSoket::new(String::from("Lamp"), 60.0));
+    let old = room.replace_device("Router", lamp).unwrap();
+    assert_eq!(old.name(), "Router");
+    assert_eq!(room.get_device("Router").unwrap().name(), "Lamp");
+    // Замена сохраняет позицию устройства
+    assert_eq!(
+        device_keys(&room),
+        vec!["RoomThermometer", "ComputerSoket", "Router"]
+    );
+
+    let lamp = SmartDevice::from(SmartElectricalSoket::new(String::from("Lamp"), 60.0));
+    assert!(matches!(
+        room.replace_device("Lamp", lamp),
+        Err(SmartHomeErrors::DeviceNotFound(_))
+    ));
+
+    let fan = SmartDevice::from(SmartElectricalSoket::new(String::from("Fan"), 40.0));
+    assert!(
+        room.upsert_device(String::from("Fan"), fan)
+            .unwrap()
+            .is_none()
+    );
+    let heater = SmartDevice::from(SmartElectricalSoket::new(String::from("Heater"), 900.0));
+    let old = room.upsert_device(String::from("Fan"), heater).unwrap();
+    assert_eq!(old.unwrap().name(), "Fan");
+    assert_eq!(room.devices().count(), 4);
+
+    let mut home = create_home(vec![room]);
+    let old = home
+        .replace_room("Гостинная", Room::new(String::from("Зал")))
+        .unwrap();
+    assert_eq!(old.devices().count(), 4);
+    assert_eq!(home.get_room("Гостинная").unwrap().get_name(), "Зал");
+    assert!(
+        home.upsert_room(String::from("Балкон"), Room::new(String::from("Балкон")))
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(home.rooms().count(), 2);
+}
+
+#[test]
+fn test_ids_are_unique_and_resolve_to_keys() {
+    let room = create_room();
+    let router = room.device_id("Router").unwrap();
+    assert_ne!(router, room.device_id("ComputerSoket").unwrap());
+    assert_eq!(room.device_key(router), Some("Router"));
+    assert_eq!(room.get_device_by_id(router).unwrap().name(), "Router");
+
+    let room_id = room.id();
+    let mut home = create_home(vec![room]);
+    assert_eq!(home.room_key(room_id), Some("Гостинная"));
+    assert_eq!(home.locate_device(router), Some(("Гостинная", "Router")));
+    assert_eq!(home.get_device_by_id(router).unwrap().name(), "Router");
+    let status = home
+        .update_device_by_id(router, |device| {
+            device
+                .downcast_mut::<SmartElectricalSoket>()
+                .unwrap()
+                .turn_on()
+                .unwrap();
+            device.status()
+        })
+        .unwrap();
+    assert_eq!(status, "включена");
+
+    let missing = crate::DeviceId::from(u64::MAX);
+    assert!(matches!(
+        home.get_device_by_id(missing),
+        Err(SmartHomeErrors::DeviceNotFound(_))
+    ));
+    assert_eq!("42".parse::<crate::DeviceId>().unwrap().get(), 42);
+}
+
+#[test]
+fn test_copies_get_new_ids_next_to_original() {
+    let room = create_room();
+    let copy = room.clone();
+    assert_ne!(copy.id(), room.id());
+    assert_ne!(copy.device_id("Router"), room.device_id("Router"));
+
+    let original_router = room.device_id("Router").unwrap();
+    let mut home = create_home(vec![room]);
+    home.add_room_with_key(
+        String::from("Копия"),
+        home.get_room("Гостинная").unwrap().clone(),
+    )
+    .unwrap();
+    let copy_router = home.get_room("Копия").unwrap().device_id("Router").unwrap();
+    assert_ne!(copy_router, original_router);
+    assert_eq!(
+        home.locate_device(original_router),
+        Some(("Гостинная", "Router"))
+    );
+    assert_eq!(home.locate_device(copy_router), Some(("Копия", "Router")));
+    home.update_device_by_id(copy_router, |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+            .unwrap()
+    })
+    .unwrap();
+    assert_eq!(
+        home.get_device_by_id(copy_router).unwrap().status(),
+        "включена"
+    );
+    assert_eq!(
+        home.get_device_by_id(original_router).unwrap().status(),
+        "выключена"
+    );
+
+    // Замененное устройство получает новый идентификатор
+    let room = home.get_mutable_room("Гостинная").unwrap();
+    let lamp = SmartDevice::from(SmartElectricalSoket::new(String::from("Lamp"), 60.0));
+    room.replace_device("Router", lamp).unwrap();
+    assert_ne!(room.device_id("Router").unwrap(), original_router);
+}
+
+#[test]
+fn test_move_device_keeps_state_and_id() {
+    let mut home = create_home(vec![create_room()]);
+    home.add_room(Room::new(String::from("Кухня"))).unwrap();
+    home.update_device("Гостинная", "Router", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+            .unwrap()
+    })
+    .unwrap();
+    let id = home
+        .get_room("Гостинная")
+        .unwrap()
+        .device_id("Router")
+        .unwrap();
+
+    home.move_device("Гостинная", "Кухня", "Router").unwrap();
+    assert!(home.get_device_from_room("Гостинная", "Router").is_err());
+    assert_eq!(home.locate_device(id), Some(("Кухня", "Router")));
+    assert_eq!(
+        home.get_device_from_room("Кухня", "Router")
+            .unwrap()
+            .status(),
+        "включена"
+    );
+
+    // Ошибки не меняют дом
+    assert!(matches!(
+        home.move_device("Нет", "Кухня", "Router"),
+        Err(SmartHomeErrors::RoomNotFound(_))
+    ));
+    assert!(matches!(
+        home.move_device("Кухня", "Нет", "Router"),
+        Err(SmartHomeErrors::RoomNotFound(_))
+    ));
+    assert!(matches!(
+        home.move_device("Гостинная", "Кухня", "Router"),
+        Err(SmartHomeErrors::DeviceNotFound(_))
+    ));
+    home.get_mutable_room("Гостинная")
+        .unwrap()
+        .add_device_with_key(
+            String::from("Router"),
+            SmartElectricalSoket::new(String::from("Spare"), 10.0).into(),
+        )
+        .unwrap();
+    assert!(matches!(
+        home.move_device("Кухня", "Гостинная", "Router"),
+        Err(SmartHomeErrors::DuplicateDevice(_))
+    ));
+    assert_eq!(home.locate_device(id), Some(("Кухня", "Router")));
+    assert!(home.move_device("Кухня", "Кухня", "Router").is_ok());
+}
+
+#[test]
+fn test_rename_room_and_device() {
+    let mut home = create_home(vec![create_room()]);
+    home.add_room(Room::new(String::from("Кухня"))).unwrap();
+    let room_id = home.get_room("Гостинная").unwrap().id();
+    let device_id = home
+        .get_room("Гостинная")
+        .unwrap()
+        .device_id("ComputerSoket")
+        .unwrap();
+
+    home.rename_device("Гостинная", "ComputerSoket", String::from("PC"))
+        .unwrap();
+    home.rename_room("Гостинная", String::from("Зал")).unwrap();
+    assert_eq!(home.room_key(room_id), Some("Зал"));
+    assert_eq!(home.locate_device(device_id), Some(("Зал", "PC")));
+    // Позиции комнаты и устройства сохраняются
+    assert_eq!(
+        home.rooms().map(|(key, _)| key).collect::<Vec<&str>>(),
+        vec!["Зал", "Кухня"]
+    );
+    assert_eq!(
+        device_keys(home.get_room("Зал").unwrap()),
+        vec!["RoomThermometer", "PC", "Router"]
+    );
+    // Переименованная комната по-прежнему сообщает о событиях под новым ключом
+    let events = home.events().channel();
+    home.get_mutable_room("Зал")
+        .unwrap()
+        .delete_device("PC")
+        .unwrap();
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        crate::events::HomeEvent::DeviceRemoved { room, .. } if room == "Зал"
+    ));
+
+    assert!(matches!(
+        home.rename_room("Зал", String::from("Кухня")),
+        Err(SmartHomeErrors::DuplicateRoom(_))
+    ));
+    assert!(matches!(
+        home.rename_device("Зал", "Router", String::from("RoomThermometer")),
+        Err(SmartHomeErrors::DuplicateDevice(_))
+    ));
+    assert!(matches!(
+        home.rename_device("Зал", "PC", String::from("Computer")),
+        Err(SmartHomeErrors::DeviceNotFound(_))
+    ));
+    assert!(home.rename_room("Зал", String::from(" Зал")).is_err());
+    assert!(home.get_room("Зал").is_some());
+}
@@ -0,0 +1,379 @@
+//! Передача показаний термометра по UDP
+//!
+//! Эмиттер периодически отправляет датаграммы с показаниями,
+//! а термометр в режиме приёма обновляет значение в фоновом потоке.
+//! Формат датаграммы:
+//! `[версия: u8][сеанс: u32 BE][номер: u64 BE][температура: f32 BE][единицы: u8]`.
+//! Сеанс выбирается эмиттером при запуске; когда он меняется, получатель
+//! начинает отсчет номеров заново, поэтому перезапуск эмиттера не останавливает приём.
+
+use crate::smart_devices::{SmartThermometer, TempMeasures};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Текущая версия формата датаграмм
+pub const TELEMETRY_VERSION: u8 = 2;
+
+const PACKET_LEN: usize = 18;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Одно показание термометра, передаваемое по сети
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryPacket {
+    /// Случайный номер запуска эмиттера
+    pub session: u32,
+    pub sequence: u64,
+    pub tempreture: f32,
+    pub measure: TempMeasures,
+}
+
+impl TelemetryPacket {
+    pub fn encode(&self) -> [u8; PACKET_LEN] {
+        let mut out = [0u8; PACKET_LEN];
+        out[0] = TELEMETRY_VERSION;
+        out[1..5].copy_from_slice(&self.session.to_be_bytes());
+        out[5..13].copy_from_slice(&self.sequence.to_be_bytes());
+        out[13..17].copy_from_slice(&self.tempreture.to_be_bytes());
+        out[17] = match self.measure {
+            TempMeasures::C => 0,
+            TempMeasures::F => 1,
+            TempMeasures::K => 2,
+            TempMeasures::R => 3,
+        };
+        out
+    }
+
+    /// Возвращает `None` для датаграмм другой версии или длины
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() != PACKET_LEN || data[0] != TELEMETRY_VERSION {
+            return None;
+        }
+        let measure = match data[17] {
+            0 => TempMeasures::C,
+            1 => TempMeasures::F,
+            2 => TempMeasures::K,
+            3 => TempMeasures::R,
+            _ => return None,
+        };
+        Some(Self {
+            session: u32::from_be_bytes(data[1..5].try_into().ok()?),
+            sequence: u64::from_be_bytes(data[5..13].try_into().ok()?),
+            tempreture: f32::from_be_bytes(data[13..17].try_into().ok()?),
+            measure,
+        })
+    }
+}
+
+/// Статистика принятых датаграмм
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TelemetryStats {
+    pub received: u64,
+    pub lost: u64,
+    pub last_sequence: Option<u64>,
+    /// Сколько раз менялся сеанс эмиттера
+    pub restarts: u64,
+}
+
+#[derive(Debug, Default)]
+struct TelemetryState {
+    session: Option<u32>,
+    reading: Option<(f32, TempMeasures)>,
+    received_at: Option<Instant>,
+    stats: TelemetryStats,
+}
+
+impl TelemetryState {
+    fn accept(&mut self, packet: TelemetryPacket) {
+        if self.session != Some(packet.session) {
+            // Эмиттер перезапущен: номера начинаются заново
+            if self.session.is_some() {
+                self.stats.restarts += 1;
+            }
+            self.session = Some(packet.session);
+            self.stats.last_sequence = None;
+        }
+        if let Some(last) = self.stats.last_sequence {
+            // Повторы и опоздавшие датаграммы игнорируются
+            if packet.sequence <= last {
+                return;
+            }
+            self.stats.lost += packet.sequence - last - 1;
+        }
+        self.stats.received += 1;
+        self.stats.last_sequence = Some(packet.sequence);
+        self.reading = Some((packet.tempreture, packet.measure));
+        self.received_at = Some(Instant::now());
+    }
+}
+
+/// Приёмная сторона телеметрии, которой владеет термометр
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    state: Arc<Mutex<TelemetryState>>,
+    local_addr: SocketAddr,
+    stale_after: Duration,
+}
+
+impl Telemetry {
+    /// Открывает UDP-сокет и запускает фоновый поток приёма.
+    /// Поток завершается, когда удалён последний термометр, использующий эту телеметрию.
+    pub fn listen<A: ToSocketAddrs>(addr: A, stale_after: Duration) -> io::Result<Self> {
+        let udp = UdpSocket::bind(addr)?;
+        udp.set_read_timeout(Some(POLL_INTERVAL))?;
+        let local_addr = udp.local_addr()?;
+        let state = Arc::new(Mutex::new(TelemetryState::default()));
+        let weak = Arc::downgrade(&state);
+        thread::spawn(move || receive_loop(udp, weak));
+        Ok(Self {
+            state,
+            local_addr,
+            stale_after,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stats(&self) -> TelemetryStats {
+        self.lock().stats.clone()
+    }
+
+    /// Последнее принятое показание
+    pub fn reading(&self) -> Option<(f32, TempMeasures)> {
+        self.lock().reading
+    }
+
+    /// Показание устарело, если датаграммы не приходили дольше `stale_after`
+    pub fn is_stale(&self) -> bool {
+        match self.lock().received_at {
+            Some(received_at) => received_at.elapsed() > self.stale_after,
+            None => true,
+        }
+    }
+
+    /// Ждет датаграмму новее уже принятых не дольше `timeout`.
+    /// Поток приёма синхронный, поэтому состояние опрашивается с шагом `POLL_INTERVAL`;
+    /// блокировка не удерживается через `.await`.
+    #[cfg(feature = "tokio")]
+    pub async fn next_reading(&self, timeout: Duration) -> Option<(f32, TempMeasures)> {
+        let last = self.lock().stats.last_sequence;
+        let wait = async {
+            loop {
+                if let Some(reading) = self.fresh_reading(last) {
+                    return reading;
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.ok()
+    }
+
+    #[cfg(feature = "tokio")]
+    fn fresh_reading(&self, last: Option<u64>) -> Option<(f32, TempMeasures)> {
+        let state = self.lock();
+        if state.stats.last_sequence == last {
+            return None;
+        }
+        state.reading
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TelemetryState> {
+        // Поток приёма не паникует, удерживая блокировку, но на всякий случай
+        // продолжаем работать с последним сохранённым состоянием.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn receive_loop(udp: UdpSocket, state: Weak<Mutex<TelemetryState>>) {
+    let mut buffer = [0u8; 64];
+    loop {
+        let received = udp.recv(&mut buffer);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let len = match received {
+            Ok(len) => len,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue;
+            }
+            Err(_) => return,
+        };
+        if let Some(packet) = TelemetryPacket::decode(&buffer[..len]) {
+            state
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .accept(packet);
+        }
+    }
+}
+
+/// Эмиттер, периодически отправляющий показания термометра
+pub struct ThermometerEmitter {
+    thermometer: Arc<Mutex<SmartThermometer>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ThermometerEmitter {
+    /// Запускает отправку показаний на `target` с заданным интервалом.
+    /// Для широковещательной рассылки достаточно указать широковещательный адрес.
+    pub fn spawn<A: ToSocketAddrs>(
+        thermometer: SmartThermometer,
+        target: A,
+        interval: Duration,
+    ) -> io::Result<Self> {
+        let target = target
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no target address"))?;
+        let bind_addr: SocketAddr = if target.is_ipv4() {
+            "0.0.0.0:0".parse().expect("valid address")
+        } else {
+            "[::]:0".parse().expect("valid address")
+        };
+        let udp = UdpSocket::bind(bind_addr)?;
+        udp.set_broadcast(true)?;
+
+        let thermometer = Arc::new(Mutex::new(thermometer));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let thermometer = Arc::clone(&thermometer);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let session = rand::random::<u32>();
+                let mut sequence = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let packet = {
+                        let thermo = thermometer.lock().unwrap_or_else(|err| err.into_inner());
+                        TelemetryPacket {
+                            session,
+                            sequence,
+                            tempreture: thermo.get_tempreture(),
+                            measure: *thermo.get_temp_measure(),
+                        }
+                    };
+                    // Потеря датаграммы обнаруживается получателем по номеру
+                    let _ = udp.send_to(&packet.encode(), target);
+                    sequence += 1;
+                    thread::sleep(interval);
+                }
+            })
+        };
+        Ok(Self {
+            thermometer,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Термометр, показания которого отправляются
+    pub fn thermometer(&self) -> Arc<Mutex<SmartThermometer>> {
+        Arc::clone(&self.thermometer)
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for ThermometerEmitter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence: u64) -> TelemetryPacket {
+        TelemetryPacket {
+            session: 7,
+            sequence,
+            tempreture: 21.5,
+            measure: TempMeasures::C,
+        }
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let packet = TelemetryPacket {
+            session: 0xDEAD_BEEF,
+            sequence: 42,
+            tempreture: -3.5,
+            measure: TempMeasures::F,
+        };
+        assert_eq!(
+            TelemetryPacket::decode(&packet.encode()),
+            Some(packet.clone())
+        );
+        let packet = TelemetryPacket {
+            measure: TempMeasures::K,
+            ..packet
+        };
+        assert_eq!(TelemetryPacket::decode(&packet.encode()), Some(packet));
+        assert_eq!(TelemetryPacket::decode(&[TELEMETRY_VERSION, 1, 2]), None);
+    }
+
+    #[test]
+    fn test_state_counts_lost_packets() {
+        let mut state = TelemetryState::default();
+        state.accept(packet(0));
+        state.accept(packet(1));
+        state.accept(packet(4));
+        // Опоздавшая датаграмма не меняет состояние
+        state.accept(packet(3));
+        assert_eq!(
+            state.stats,
+            TelemetryStats {
+                received: 3,
+                lost: 2,
+                last_sequence: Some(4),
+                restarts: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_state_resyncs_after_restart() {
+        let mut state = TelemetryState::default();
+        state.accept(packet(10));
+        // Новый сеанс начинает номера с нуля и не считается потерей
+        state.accept(TelemetryPacket {
+            session: 8,
+            tempreture: 30.0,
+            ..packet(0)
+        });
+        assert_eq!(state.reading, Some((30.0, TempMeasures::C)));
+        assert_eq!(
+            state.stats,
+            TelemetryStats {
+                received: 2,
+                lost: 0,
+                last_sequence: Some(0),
+                restarts: 1,
+            }
+        );
+    }
+}
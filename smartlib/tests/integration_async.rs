@@ -0,0 +1,173 @@
+#![cfg(feature = "tokio")]
+
+use smartlib::async_tcp::{AsyncSocketClient, AsyncSocketServer};
+use smartlib::errors::{ConnectionErrors, SmartHomeErrors};
+use smartlib::protocol::{Command, Response};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::tcp::SocketServer;
+use smartlib::udp::ThermometerEmitter;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+async fn start_server(name: &str, power: f32) -> std::net::SocketAddr {
+    let socket = SmartElectricalSoket::new(String::from(name), power);
+    let server = AsyncSocketServer::bind("127.0.0.1:0", socket)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+    addr
+}
+
+#[tokio::test]
+async fn async_client_controls_remote_socket() {
+    let addr = start_server("RemoteSocket", 1500.0).await;
+    let mut client = AsyncSocketClient::connect(addr).await.unwrap();
+    assert_eq!(client.get_name().await.unwrap(), "RemoteSocket");
+    assert!(!client.is_on().await.unwrap());
+
+    client.turn_on().await.unwrap();
+    assert!(client.is_on().await.unwrap());
+    assert_eq!(client.get_power().await.unwrap(), 1500.0);
+
+    client.switch().await.unwrap();
+    client.turn_off().await.unwrap();
+    assert!(!client.is_on().await.unwrap());
+}
+
+#[tokio::test]
+async fn async_client_works_with_blocking_server() {
+    let socket = SmartElectricalSoket::new(String::from("Blocking"), 100.0);
+    let server = SocketServer::bind("127.0.0.1:0", socket).unwrap();
+    let addr = server.local_addr().unwrap();
+    let shared = server.socket();
+    server.spawn();
+
+    let mut client = AsyncSocketClient::connect(addr).await.unwrap();
+    client.turn_on().await.unwrap();
+    assert!(shared.lock().unwrap().is_on());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn many_devices_are_controlled_concurrently() {
+    let mut tasks = Vec::new();
+    for index in 0..16 {
+        let addr = start_server(&format!("Socket {}", index), 100.0 * index as f32).await;
+        tasks.push(tokio::spawn(async move {
+            let mut client = AsyncSocketClient::connect(addr).await?;
+            client.turn_on().await?;
+            client.get_power().await
+        }));
+    }
+    for (index, task) in tasks.into_iter().enumerate() {
+        assert_eq!(task.await.unwrap().unwrap(), 100.0 * index as f32);
+    }
+}
+
+#[tokio::test]
+async fn silent_device_times_out() {
+    // Сервер принимает подключение, но никогда не отвечает
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let mut client = AsyncSocketClient::connect(addr)
+        .await
+        .unwrap()
+        .with_timeout(Duration::from_millis(50));
+    let err = client.is_on().await.unwrap_err();
+    assert!(matches!(
+        err,
+        SmartHomeErrors::DeviceOffline {
+            source: Some(ConnectionErrors::Timeout),
+            ..
+        }
+    ));
+    assert_eq!(err.code(), "device_offline");
+}
+
+#[tokio::test]
+async fn cancelled_request_does_not_break_client() {
+    let addr = start_server("RemoteSocket", 1500.0).await;
+    let mut client = AsyncSocketClient::connect(addr).await.unwrap();
+
+    let (cancel, cancelled) = oneshot::channel::<()>();
+    cancel.send(()).unwrap();
+    let result = client
+        .request_until(Command::TurnOn, async {
+            let _ = cancelled.await;
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(SmartHomeErrors::Connection {
+            source: ConnectionErrors::Cancelled,
+            ..
+        })
+    ));
+
+    // После отмены клиент остается рабочим
+    assert_eq!(
+        client.request(Command::GetName).await.unwrap(),
+        Response::Name(String::from("RemoteSocket"))
+    );
+}
+
+#[tokio::test]
+async fn server_stops_on_shutdown() {
+    let socket = SmartElectricalSoket::new(String::from("RemoteSocket"), 1500.0);
+    let server = AsyncSocketServer::bind("127.0.0.1:0", socket)
+        .await
+        .unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let task = tokio::spawn(server.run_until(async {
+        let _ = stopped.await;
+    }));
+    stop.send(()).unwrap();
+    assert!(task.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn thermometer_waits_for_next_reading() {
+    let receiver = SmartThermometer::listen(
+        String::from("Remote"),
+        TempMeasures::F,
+        "127.0.0.1:0",
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    let err = receiver
+        .next_tempreture(Duration::from_millis(100))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        SmartHomeErrors::DeviceOffline {
+            source: Some(ConnectionErrors::Timeout),
+            ..
+        }
+    ));
+
+    let source = SmartThermometer::new(String::from("Sensor"), TempMeasures::C, 100.0);
+    let _emitter = ThermometerEmitter::spawn(
+        source,
+        receiver.telemetry_addr().unwrap(),
+        Duration::from_millis(10),
+    )
+    .unwrap();
+    let value = receiver
+        .next_tempreture(Duration::from_secs(2))
+        .await
+        .unwrap();
+    assert_eq!(value, 212.0);
+
+    // Термометр без телеметрии отвечает сразу
+    let local = SmartThermometer::new(String::from("Local"), TempMeasures::C, 21.0);
+    assert_eq!(local.next_tempreture(Duration::ZERO).await.unwrap(), 21.0);
+}
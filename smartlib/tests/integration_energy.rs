@@ -0,0 +1,169 @@
+use smartlib::clock::{Clock, ManualClock};
+use smartlib::report::{HomeReport, ReportFormat, RoomReport};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::structures::Report;
+use smartlib::{SmartHome, add_room};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn socket(name: &str, power: f32, clock: &ManualClock) -> SmartElectricalSoket {
+    SmartElectricalSoket::new(String::from(name), power).with_clock(Arc::new(clock.clone()))
+}
+
+/// Чайник на 2000 Вт и обогреватель на 1000 Вт в разных комнатах
+fn create_home(clock: &ManualClock) -> SmartHome {
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        ("Kettle", socket("Kettle", 2000.0, clock)),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 22.0)
+        ),
+    );
+    let bedroom = add_room!(
+        String::from("Спальня"),
+        ("Heater", socket("Heater", 1000.0, clock)),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen, bedroom]).unwrap()
+}
+
+fn switch(home: &mut SmartHome, room: &str, key: &str) {
+    home.update_device(room, key, |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .switch()
+            .unwrap()
+    })
+    .unwrap();
+}
+
+#[test]
+fn energy_is_accumulated_per_socket_room_and_home() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+
+    // Чайник работает полчаса, обогреватель - с первого часа и до сих пор
+    switch(&mut home, "Кухня", "Kettle");
+    clock.advance(HOUR / 2);
+    switch(&mut home, "Кухня", "Kettle");
+    clock.advance(HOUR / 2);
+    switch(&mut home, "Спальня", "Heater");
+    clock.advance(HOUR * 2);
+
+    let kettle = home.get_device_from_room("Кухня", "Kettle").unwrap();
+    assert_eq!(kettle.energy_total(), Some(1000.0));
+    let thermo = home.get_device_from_room("Кухня", "T1").unwrap();
+    assert_eq!(thermo.energy_total(), None);
+
+    let bedroom = home.get_room("Спальня").unwrap();
+    assert_eq!(bedroom.energy_total(), Some(2000.0));
+    assert_eq!(home.energy_total(), Some(3000.0));
+
+    // Только первый час: чайник работал, обогреватель еще нет
+    let first_hour = home.energy_between(UNIX_EPOCH, UNIX_EPOCH + HOUR);
+    assert_eq!(first_hour, 1000.0);
+    let second_hour = home.energy_between(UNIX_EPOCH + HOUR, UNIX_EPOCH + HOUR * 2);
+    assert_eq!(second_hour, 1000.0);
+}
+
+#[test]
+fn reports_include_kwh_totals_on_request() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    switch(&mut home, "Кухня", "Kettle");
+    clock.advance(HOUR);
+
+    let report = HomeReport::with_energy_until(&home, clock.now());
+    assert_eq!(report.energy_kwh, Some(2.0));
+    assert_eq!(report.rooms[0].energy_kwh, Some(2.0));
+    assert_eq!(report.rooms[1].energy_kwh, Some(0.0));
+
+    let text = report.render(ReportFormat::Text);
+    assert!(text.contains("| Потреблено: 2.000 кВт·ч\n"));
+    assert!(text.ends_with("Всего потреблено: 2.000 кВт·ч\n"));
+
+    let json = report.render(ReportFormat::Json);
+    assert!(json.contains("\"energy_kwh\":2"));
+    assert!(json.ends_with("],\"energy_kwh\":2}"));
+
+    // Отчет до прошедшего момента не меняется со временем
+    clock.advance(HOUR);
+    let earlier = HomeReport::with_energy_until(&home, UNIX_EPOCH + HOUR);
+    assert_eq!(earlier.render(ReportFormat::Text), text);
+
+    // В комнате без розеток потребление не выводится
+    let hall = add_room!(
+        String::from("Холл"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 22.0)
+        ),
+    );
+    let hall_report = RoomReport::from(&hall).with_energy_until(&hall, clock.now());
+    assert!(
+        !hall_report
+            .render(ReportFormat::Text)
+            .contains("Потреблено")
+    );
+    assert!(
+        hall_report
+            .render(ReportFormat::Json)
+            .ends_with("\"energy_kwh\":null}")
+    );
+}
+
+#[test]
+fn default_reports_do_not_depend_on_time() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    switch(&mut home, "Кухня", "Kettle");
+    let reports: Vec<String> = [ReportFormat::Text, ReportFormat::Json, ReportFormat::Csv]
+        .into_iter()
+        .map(|format| home.report_as(format))
+        .collect();
+    clock.advance(HOUR);
+    for (format, report) in [ReportFormat::Text, ReportFormat::Json, ReportFormat::Csv]
+        .into_iter()
+        .zip(reports)
+    {
+        assert_eq!(home.report_as(format), report);
+    }
+    assert!(!home.report().contains("Потреблено"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn consumption_history_is_persisted() {
+    use smartlib::persistence::HomeFormat;
+
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    home.get_mutable_room("Спальня")
+        .unwrap()
+        .add_device_with_key(
+            String::from("Lamp"),
+            socket("Lamp", 100.0, &clock)
+                .with_persistent_history()
+                .into(),
+        )
+        .unwrap();
+    switch(&mut home, "Кухня", "Kettle");
+    switch(&mut home, "Спальня", "Lamp");
+    clock.advance(HOUR);
+    switch(&mut home, "Кухня", "Kettle");
+    switch(&mut home, "Спальня", "Lamp");
+
+    for format in [HomeFormat::Json, HomeFormat::Toml] {
+        let content = home.to_string_with(format).unwrap();
+        let loaded = SmartHome::from_str_with(&content, format).unwrap();
+        // Журнал чайника сохранен только суммой, журнал лампы - полностью
+        assert_eq!(loaded.energy_total(), Some(2100.0));
+        assert_eq!(
+            loaded.energy_between(UNIX_EPOCH, UNIX_EPOCH + HOUR * 2),
+            100.0
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use smartlib::events::HomeEvent;
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{Room, SmartHome, add_room};
+use std::sync::{Arc, Mutex};
+
+fn create_home() -> SmartHome {
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "S1",
+            SmartElectricalSoket::new(String::from("Kettle"), 2000.0)
+        ),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+        ),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap()
+}
+
+#[test]
+fn topology_changes_are_published() {
+    let mut home = create_home();
+    let events = home.events().channel();
+
+    home.add_room_with_key(String::from("Зал"), Room::new(String::from("Зал")))
+        .unwrap();
+    home.get_mutable_room("Зал")
+        .unwrap()
+        .add_device_with_key(
+            String::from("S"),
+            SmartElectricalSoket::new(String::from("Lamp"), 60.0).into(),
+        )
+        .unwrap();
+    home.get_mutable_room("Кухня")
+        .unwrap()
+        .delete_device("T1")
+        .unwrap();
+    home.delete_room("Зал").unwrap();
+
+    let received: Vec<HomeEvent> = events.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            HomeEvent::RoomAdded {
+                room: String::from("Зал")
+            },
+            HomeEvent::DeviceAdded {
+                room: String::from("Зал"),
+                device: String::from("S")
+            },
+            HomeEvent::DeviceRemoved {
+                room: String::from("Кухня"),
+                device: String::from("T1")
+            },
+            HomeEvent::RoomRemoved {
+                room: String::from("Зал")
+            },
+        ]
+    );
+}
+
+#[test]
+fn state_changes_are_published_to_listeners() {
+    let mut home = create_home();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    {
+        let seen = Arc::clone(&seen);
+        home.events()
+            .subscribe(move |event| seen.lock().unwrap().push(event.clone()));
+    }
+
+    home.update_device("Кухня", "S1", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .switch()
+            .unwrap()
+    })
+    .unwrap();
+    home.update_device("Кухня", "T1", |device| {
+        device
+            .downcast_mut::<SmartThermometer>()
+            .unwrap()
+            .change_measure()
+    })
+    .unwrap();
+    // Чтение без изменения состояния событий не порождает
+    home.update_device("Кухня", "S1", |device| device.status())
+        .unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(
+        seen[0],
+        HomeEvent::StateChanged {
+            room: String::from("Кухня"),
+            device: String::from("S1"),
+            old_status: String::from("выключена"),
+            new_status: String::from("включена"),
+        }
+    );
+    assert!(matches!(&seen[1], HomeEvent::StateChanged { device, .. } if device == "T1"));
+}
+
+#[test]
+fn detached_rooms_stop_publishing() {
+    let mut home = create_home();
+    let events = home.events().channel();
+    assert!(home.update_device("Кухня", "X", |_| ()).is_err());
+    assert!(home.update_device("Нет", "S1", |_| ()).is_err());
+
+    let mut room = Room::new(String::from("Отдельная"));
+    room.add_device_with_key(
+        String::from("S"),
+        SmartElectricalSoket::new(String::from("Lamp"), 60.0).into(),
+    )
+    .unwrap();
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn replacements_are_published_as_remove_and_add() {
+    let mut home = create_home();
+    let events = home.events().channel();
+
+    let kettle = SmartElectricalSoket::new(String::from("Kettle"), 1800.0);
+    home.get_mutable_room("Кухня")
+        .unwrap()
+        .upsert_device(String::from("S1"), kettle.into())
+        .unwrap();
+    let old = home
+        .replace_room("Кухня", Room::new(String::from("Кухня")))
+        .unwrap();
+
+    // Изменения в замененной комнате больше не публикуются
+    let mut old = old;
+    old.delete_device("T1").unwrap();
+
+    let received: Vec<HomeEvent> = events.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            HomeEvent::DeviceRemoved {
+                room: String::from("Кухня"),
+                device: String::from("S1")
+            },
+            HomeEvent::DeviceAdded {
+                room: String::from("Кухня"),
+                device: String::from("S1")
+            },
+            HomeEvent::RoomRemoved {
+                room: String::from("Кухня")
+            },
+            HomeEvent::RoomAdded {
+                room: String::from("Кухня")
+            },
+        ]
+    );
+}
@@ -0,0 +1,158 @@
+use smartlib::clock::ManualClock;
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{Room, add_room};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn thermometer(name: &str, measure: TempMeasures, clock: &ManualClock) -> SmartThermometer {
+    SmartThermometer::new(String::from(name), measure, 0.0).with_clock(Arc::new(clock.clone()))
+}
+
+fn set(room: &mut Room, key: &str, value: f32) {
+    room.update_device(key, |device| {
+        device
+            .downcast_mut::<SmartThermometer>()
+            .unwrap()
+            .set_tempreture(value)
+    })
+    .unwrap();
+}
+
+/// Два термометра: у окна (в Цельсиях) и у батареи (в Фаренгейтах)
+fn create_room(clock: &ManualClock) -> Room {
+    add_room!(
+        String::from("Кухня"),
+        ("Window", thermometer("Window", TempMeasures::C, clock)),
+        ("Heater", thermometer("Heater", TempMeasures::F, clock)),
+        ("Fan", SmartElectricalSoket::new(String::from("Fan"), 50.0)),
+    )
+}
+
+#[test]
+fn thermometer_history_statistics() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut room = create_room(&clock);
+    for value in [18.0, 20.0, 22.0] {
+        set(&mut room, "Window", value);
+        clock.advance(HOUR);
+    }
+
+    let window = room
+        .get_device("Window")
+        .unwrap()
+        .downcast_ref::<SmartThermometer>()
+        .unwrap();
+    assert_eq!(window.history().len(), 3);
+
+    let stats = window
+        .history_stats(UNIX_EPOCH, UNIX_EPOCH + HOUR * 3)
+        .unwrap();
+    assert_eq!((stats.min, stats.max, stats.mean), (18.0, 22.0, 20.0));
+    assert!((stats.trend_per_hour - 2.0).abs() < 1e-4);
+
+    // Окно по времени отбрасывает первое показание
+    let last_hours = window
+        .history_stats(UNIX_EPOCH + HOUR, UNIX_EPOCH + HOUR * 3)
+        .unwrap();
+    assert_eq!(last_hours.count, 2);
+    assert_eq!(last_hours.min, 20.0);
+}
+
+#[test]
+fn room_aggregates_and_export() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut room = create_room(&clock);
+    set(&mut room, "Window", 20.0);
+    set(&mut room, "Heater", 86.0);
+    clock.advance(HOUR);
+    set(&mut room, "Window", 22.0);
+
+    let stats = room
+        .temperature_stats(UNIX_EPOCH, UNIX_EPOCH + HOUR * 2, &TempMeasures::C)
+        .unwrap();
+    assert_eq!(stats.count, 3);
+    assert_eq!(stats.min, 20.0);
+    assert!((stats.max - 30.0).abs() < 1e-4);
+    assert!(
+        room.temperature_stats(
+            UNIX_EPOCH + HOUR * 2,
+            UNIX_EPOCH + HOUR * 3,
+            &TempMeasures::C
+        )
+        .is_none()
+    );
+
+    let csv = room.temperature_history_csv(&TempMeasures::C);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "device_key,timestamp,tempreture");
+    assert_eq!(lines[1], "Window,0,20");
+    assert_eq!(lines[2], "Window,3600,22");
+    assert!(lines[3].starts_with("Heater,0,30"));
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn history_capacity_is_bounded() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut thermo = thermometer("Termo", TempMeasures::C, &clock).with_history_capacity(2);
+    for value in [1.0, 2.0, 3.0] {
+        thermo.set_tempreture(value);
+        clock.advance(HOUR);
+    }
+    assert_eq!(thermo.history().capacity(), 2);
+    assert_eq!(
+        thermo.history().to_csv(&TempMeasures::C),
+        "timestamp,tempreture\n3600,2\n7200,3\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn history_is_saved_only_on_request() {
+    use smartlib::SmartHome;
+    use smartlib::persistence::HomeFormat;
+
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut room = add_room!(
+        String::from("Кухня"),
+        ("Window", thermometer("Window", TempMeasures::C, &clock)),
+        (
+            "Heater",
+            thermometer("Heater", TempMeasures::C, &clock)
+                .with_persistent_history()
+                .with_history_capacity(3)
+        ),
+    );
+    for value in [1.0, 2.0, 3.0] {
+        set(&mut room, "Window", value);
+        set(&mut room, "Heater", value);
+        clock.advance(HOUR);
+    }
+    let home = SmartHome::try_new(String::from("MyHome"), vec![room]).unwrap();
+    let history = |home: &SmartHome, key: &str| {
+        let thermo = home.get_device_from_room("Кухня", key).unwrap();
+        let history = thermo.downcast_ref::<SmartThermometer>().unwrap().history();
+        (history.capacity(), history.len())
+    };
+
+    let content = home.to_string_with(HomeFormat::Json).unwrap();
+    let loaded = SmartHome::from_str_with(&content, HomeFormat::Json).unwrap();
+    assert_eq!(history(&loaded, "Window"), (1024, 0));
+    assert_eq!(history(&loaded, "Heater"), (3, 3));
+
+    // Показания сверх размера истории при загрузке отбрасываются
+    let edited = content.replace("\"capacity\": 3", "\"capacity\": 2");
+    let loaded = SmartHome::from_str_with(&edited, HomeFormat::Json).unwrap();
+    assert_eq!(history(&loaded, "Heater"), (2, 2));
+    let thermo = loaded.get_device_from_room("Кухня", "Heater").unwrap();
+    assert_eq!(
+        thermo
+            .downcast_ref::<SmartThermometer>()
+            .unwrap()
+            .history()
+            .to_csv(&TempMeasures::C),
+        "timestamp,tempreture\n3600,2\n7200,3\n"
+    );
+}
@@ -0,0 +1,160 @@
+use smartlib::mqtt::{Broker, DEFAULT_PREFIX, LocalBroker, Message, MqttBridge};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{SharedHome, SmartHome, add_room};
+use std::time::Duration;
+
+fn create_home() -> SharedHome {
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "S1",
+            SmartElectricalSoket::new(String::from("Kettle"), 2000.0)
+        ),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+        ),
+    );
+    SharedHome::new(SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap())
+}
+
+fn state(broker: &LocalBroker, topic: &str) -> String {
+    broker.retained(topic).unwrap().payload
+}
+
+#[test]
+fn bridge_publishes_state_and_executes_commands() {
+    let home = create_home();
+    let broker = LocalBroker::new();
+    let mut bridge = MqttBridge::new(home.clone(), broker.clone(), DEFAULT_PREFIX).unwrap();
+
+    // Текущее состояние доступно новым подписчикам сразу после подключения
+    let states = broker.channel("home/+/+/state").unwrap();
+    assert_eq!(states.try_iter().count(), 2);
+    assert!(state(&broker, "home/Кухня/S1/state").contains("\"is_on\":false"));
+
+    broker.publish(Message::new("home/Кухня/S1/set", "on"));
+    broker.publish(Message::new("home/Кухня/T1/set", "F"));
+    bridge.poll();
+    assert!(state(&broker, "home/Кухня/S1/state").contains("\"is_on\":true"));
+    assert!(state(&broker, "home/Кухня/T1/state").contains("\"status\":\"68° F\""));
+    assert_eq!(
+        home.with_device("Кухня", "S1", |device| device.status())
+            .unwrap(),
+        "включена"
+    );
+
+    // Изменения, сделанные в обход брокера, тоже публикуются
+    home.update_device("Кухня", "T1", |device| {
+        device
+            .downcast_mut::<SmartThermometer>()
+            .unwrap()
+            .set_tempreture(70.0)
+    })
+    .unwrap();
+    bridge.poll();
+    assert!(state(&broker, "home/Кухня/T1/state").contains("\"status\":\"70° F\""));
+}
+
+#[test]
+fn bridge_reports_command_errors() {
+    let broker = LocalBroker::new();
+    let mut bridge = MqttBridge::new(create_home(), broker.clone(), "house").unwrap();
+    let errors = broker.channel("house/+/+/error").unwrap();
+
+    broker.publish(Message::new("house/Кухня/T1/set", "on"));
+    broker.publish(Message::new("house/Кухня/S1/set", "boil"));
+    broker.publish(Message::new("house/Кухня/S9/set", "on"));
+    bridge.poll();
+
+    let errors: Vec<Message> = errors.try_iter().collect();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].topic, "house/Кухня/T1/error");
+    assert_eq!(errors[0].payload, "Device T1 is not a socket");
+    assert_eq!(errors[1].payload, "Unknown command boil");
+    assert_eq!(errors[2].payload, "Device S9 not found");
+    assert!(MqttBridge::new(create_home(), broker, "house/#").is_err());
+}
+
+#[test]
+fn removed_devices_are_cleared() {
+    let home = create_home();
+    let broker = LocalBroker::new();
+    let mut bridge = MqttBridge::new(home.clone(), broker.clone(), DEFAULT_PREFIX).unwrap();
+    assert_eq!(bridge.topic("a/b", "c#", "state"), "home/a%2Fb/c%23/state");
+
+    home.with_room_mut("Кухня", |room| room.delete_device("S1"))
+        .unwrap()
+        .unwrap();
+    bridge.poll();
+    assert!(broker.retained("home/Кухня/S1/state").is_none());
+    assert!(broker.retained("home/Кухня/T1/state").is_some());
+
+    home.delete_room("Кухня").unwrap();
+    bridge.poll();
+    assert!(broker.retained("home/Кухня/T1/state").is_none());
+}
+
+#[test]
+fn bridge_runs_in_background() {
+    let home = create_home();
+    let broker = LocalBroker::new();
+    let handle = MqttBridge::new(home.clone(), broker.clone(), DEFAULT_PREFIX)
+        .unwrap()
+        .spawn();
+    let states = broker.channel("home/Кухня/S1/state").unwrap();
+    states.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    broker.publish(Message::new("home/Кухня/S1/set", "switch"));
+    let message = states.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(message.payload.contains("\"is_on\":true"));
+    handle.stop();
+
+    // После остановки команды не выполняются
+    broker.publish(Message::new("home/Кухня/S1/set", "off"));
+    assert_eq!(
+        home.with_device("Кухня", "S1", |device| device.status())
+            .unwrap(),
+        "включена"
+    );
+}
+
+#[test]
+fn dropped_handle_stops_bridge() {
+    let home = create_home();
+    let broker = LocalBroker::new();
+    let handle = MqttBridge::new(home.clone(), broker.clone(), DEFAULT_PREFIX)
+        .unwrap()
+        .spawn();
+    drop(handle);
+
+    broker.publish(Message::new("home/Кухня/S1/set", "on"));
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(
+        home.with_device("Кухня", "S1", |device| device.status())
+            .unwrap(),
+        "выключена"
+    );
+}
+
+#[test]
+fn load_changes_are_republished() {
+    let home = create_home();
+    let broker = LocalBroker::new();
+    let mut bridge = MqttBridge::new(home.clone(), broker.clone(), DEFAULT_PREFIX).unwrap();
+    broker.publish(Message::new("home/Кухня/S1/set", "ON"));
+    bridge.poll();
+    assert!(state(&broker, "home/Кухня/S1/state").contains("\"power\":2000"));
+
+    // Описание розетки не меняется, но мощность в сохраненном состоянии обновляется
+    home.update_device("Кухня", "S1", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .set_load(0.5)
+    })
+    .unwrap()
+    .unwrap();
+    bridge.poll();
+    assert!(state(&broker, "home/Кухня/S1/state").contains("\"power\":1000"));
+}
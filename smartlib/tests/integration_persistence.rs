@@ -0,0 +1,259 @@
+#![cfg(feature = "serde")]
+
+use smartlib::errors::SmartHomeErrors;
+use smartlib::persistence::HomeFormat;
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::structures::Report;
+use smartlib::{Device, Room, SmartHome, SortOrder, add_room};
+use std::error::Error;
+
+fn create_home() -> SmartHome {
+    let mut socket = SmartElectricalSoket::new(String::from("Freezer"), 220.0);
+    socket.turn_on().unwrap();
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("KitchenTermo"), TempMeasures::F, 77.0)
+        ),
+        ("S1", socket),
+    );
+    let mut home = SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap();
+    home.add_room_with_key(String::from("Balcony"), Room::new(String::from("Балкон")))
+        .unwrap();
+    home
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("smartlib-persistence-{}", name))
+}
+
+#[test]
+fn home_roundtrips_through_both_formats() {
+    let home = create_home();
+    for format in [HomeFormat::Json, HomeFormat::Toml] {
+        let content = home.to_string_with(format).unwrap();
+        let loaded = SmartHome::from_str_with(&content, format).unwrap();
+
+        let socket = loaded.get_device_from_room("Кухня", "S1").unwrap();
+        let socket = socket.downcast_ref::<SmartElectricalSoket>().unwrap();
+        assert!(socket.is_on());
+        assert_eq!(socket.get_power(), 220.0);
+
+        let thermo = loaded.get_device_from_room("Кухня", "T1").unwrap();
+        let thermo = thermo.downcast_ref::<SmartThermometer>().unwrap();
+        assert_eq!(thermo.get_measure(), "F");
+        assert_eq!(thermo.get_tempreture(), 77.0);
+
+        assert_eq!(loaded.get_room("Balcony").unwrap().get_name(), "Балкон");
+        assert!(loaded.report().contains("Отчет для дома: MyHome"));
+    }
+}
+
+#[test]
+fn save_and_load_use_file_extension() {
+    let home = create_home();
+    for name in ["home.json", "home.toml"] {
+        let path = temp_path(name);
+        home.save(&path).unwrap();
+        let loaded = SmartHome::load(&path).unwrap();
+        assert!(loaded.get_device_from_room("Кухня", "T1").is_ok());
+    }
+
+    let err = home.save(temp_path("home.yaml")).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::UnsupportedFormat(_)));
+
+    let err = SmartHome::load(temp_path("missing.json")).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::Io(_)));
+}
+
+#[test]
+fn thermometer_keeps_reading_units_and_precision() {
+    let mut thermo =
+        SmartThermometer::new(String::from("Termo"), TempMeasures::C, 21.3).with_precision(1);
+    thermo.convert_to(TempMeasures::F);
+    let home = SmartHome::try_new(
+        String::from("MyHome"),
+        vec![add_room!(String::from("Кухня"), ("T1", thermo))],
+    )
+    .unwrap();
+    for format in [HomeFormat::Json, HomeFormat::Toml] {
+        let content = home.to_string_with(format).unwrap();
+        let mut loaded = SmartHome::from_str_with(&content, format).unwrap();
+        let thermo = loaded
+            .get_mutable_room("Кухня")
+            .unwrap()
+            .get_mutable_device("T1")
+            .unwrap()
+            .downcast_mut::<SmartThermometer>()
+            .unwrap();
+        assert_eq!(thermo.get_precision(), Some(1));
+        assert_eq!(thermo.status(), "70.3° F");
+        thermo.convert_to(TempMeasures::C);
+        assert_eq!(thermo.get_tempreture(), 21.3);
+    }
+
+    // В файлах без единиц показания они совпадают с единицами термометра
+    let content = r#"{"version": 1, "name": "H", "rooms": [{"key": "R", "name": "R",
+        "devices": [{"key": "T1", "kind": "thermometer",
+        "name": "Termo", "tempreture": 77.0, "measure": "F"}]}]}"#;
+    let loaded = SmartHome::from_str_with(content, HomeFormat::Json).unwrap();
+    let thermo = loaded.get_device_from_room("R", "T1").unwrap();
+    assert_eq!(thermo.status(), "77° F");
+}
+
+#[test]
+fn incompatible_and_malformed_files_are_rejected() {
+    let err = SmartHome::from_str_with(
+        r#"{"version": 2, "name": "H", "rooms": []}"#,
+        HomeFormat::Json,
+    )
+    .unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::UnsupportedVersion(2)));
+
+    let err = SmartHome::from_str_with("version = 7\nname = \"H\"", HomeFormat::Toml).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::UnsupportedVersion(7)));
+
+    let err = SmartHome::from_str_with(r#"{"name": "H"}"#, HomeFormat::Json).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::MalformedFile(_)));
+
+    // Ошибка разбора сохраняет исходную ошибку
+    let err = SmartHome::from_str_with("{", HomeFormat::Json).unwrap_err();
+    assert_eq!(err.code(), "parse");
+    assert!(err.source().unwrap().is::<serde_json::Error>());
+    let err = SmartHome::from_str_with("name = ", HomeFormat::Toml).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::Parse(_)));
+
+    let unknown_device = r#"{"version": 1, "name": "H", "rooms": [
+        {"key": "R", "name": "R", "devices": [{"key": "L", "kind": "lamp", "name": "L"}]}
+    ]}"#;
+    let err = SmartHome::from_str_with(unknown_device, HomeFormat::Json).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::MalformedFile(_)));
+}
+
+#[test]
+fn serialized_output_follows_sort_order() {
+    let mut home = create_home();
+    let json = home.to_string_with(HomeFormat::Json).unwrap();
+    assert!(json.find("\"Кухня\"").unwrap() < json.find("\"Balcony\"").unwrap());
+    assert!(json.find("\"T1\"").unwrap() < json.find("\"S1\"").unwrap());
+
+    home.set_sort_order(SortOrder::ByKey);
+    let json = home.to_string_with(HomeFormat::Json).unwrap();
+    assert!(json.find("\"Balcony\"").unwrap() < json.find("\"Кухня\"").unwrap());
+    assert!(json.find("\"S1\"").unwrap() < json.find("\"T1\"").unwrap());
+
+    // Порядок сохраняется вместе с домом и дает одинаковый результат
+    let loaded = SmartHome::from_str_with(&json, HomeFormat::Json).unwrap();
+    assert_eq!(loaded.get_sort_order(), SortOrder::ByKey);
+    assert_eq!(loaded.to_string_with(HomeFormat::Json).unwrap(), json);
+}
+
+#[test]
+fn room_sort_order_survives_save_and_load() {
+    let mut home = create_home();
+    home.get_mutable_room("Кухня")
+        .unwrap()
+        .set_sort_order(SortOrder::ByKind);
+    let json = home.to_string_with(HomeFormat::Json).unwrap();
+    let loaded = SmartHome::from_str_with(&json, HomeFormat::Json).unwrap();
+    assert_eq!(loaded.get_sort_order(), SortOrder::Insertion);
+    assert_eq!(
+        loaded.get_room("Кухня").unwrap().get_sort_order(),
+        SortOrder::ByKind
+    );
+    assert_eq!(
+        loaded.get_room("Balcony").unwrap().get_sort_order(),
+        SortOrder::Insertion
+    );
+
+    // Комнаты без сохраненного порядка берут порядок дома
+    let content = r#"{"version": 1, "name": "H", "order": "bykey", "rooms": [
+        {"key": "A", "name": "A"},
+        {"key": "B", "name": "B", "order": "insertion"}
+    ]}"#;
+    let loaded = SmartHome::from_str_with(content, HomeFormat::Json).unwrap();
+    assert_eq!(
+        loaded.get_room("A").unwrap().get_sort_order(),
+        SortOrder::ByKey
+    );
+    assert_eq!(
+        loaded.get_room("B").unwrap().get_sort_order(),
+        SortOrder::Insertion
+    );
+}
+
+#[test]
+fn save_replaces_file_without_leaving_temp_copy() {
+    let path = temp_path("atomic.json");
+    let mut home = create_home();
+    home.save(&path).unwrap();
+    home.add_room_with_key(String::from("Hall"), Room::new(String::from("Холл")))
+        .unwrap();
+    home.save(&path).unwrap();
+
+    let loaded = SmartHome::load(&path).unwrap();
+    assert!(loaded.get_room("Hall").is_some());
+    assert!(!temp_path("atomic.json.tmp").exists());
+}
+
+#[test]
+fn ids_survive_save_and_load() {
+    let home = create_home();
+    let kitchen = home.get_room("Кухня").unwrap();
+    let thermo = kitchen.device_id("T1").unwrap();
+    for format in [HomeFormat::Json, HomeFormat::Toml] {
+        let content = home.to_string_with(format).unwrap();
+        let loaded = SmartHome::from_str_with(&content, format).unwrap();
+        assert_eq!(loaded.get_room("Кухня").unwrap().id(), kitchen.id());
+        assert_eq!(loaded.locate_device(thermo), Some(("Кухня", "T1")));
+    }
+
+    // Устройства из файлов без идентификаторов получают новые, не совпадающие с загруженными
+    let content = r#"{"version": 1, "name": "H", "rooms": [{"key": "R", "name": "R", "id": 900000,
+        "devices": [{"key": "A", "kind": "socket", "name": "A", "power": 1, "is_on": false, "id": 900001},
+                    {"key": "B", "kind": "socket", "name": "B", "power": 1, "is_on": false}]}]}"#;
+    let loaded = SmartHome::from_str_with(content, HomeFormat::Json).unwrap();
+    let room = loaded.get_room("R").unwrap();
+    assert_eq!(room.device_id("A").unwrap().get(), 900001);
+    assert!(room.device_id("B").unwrap().get() > 900001);
+
+    // Идентификатор, повторяющийся в разных комнатах, делает файл некорректным
+    let content = r#"{"version": 1, "name": "H", "rooms": [
+        {"key": "R1", "name": "R1", "devices": [{"key": "A", "kind": "socket", "name": "A", "power": 1, "is_on": false, "id": 910001}]},
+        {"key": "R2", "name": "R2", "devices": [{"key": "A", "kind": "socket", "name": "A", "power": 1, "is_on": false, "id": 910001}]}]}"#;
+    let err = SmartHome::from_str_with(content, HomeFormat::Json).unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::MalformedFile(_)));
+    assert!(err.to_string().contains("910001"));
+}
+
+#[test]
+fn groups_and_scenes_are_saved_with_the_home() {
+    use smartlib::scenes::{DeviceState, Scene};
+
+    let mut home = create_home();
+    let kitchen = home.get_room("Кухня").unwrap();
+    let (thermo, socket) = (
+        kitchen.device_id("T1").unwrap(),
+        kitchen.device_id("S1").unwrap(),
+    );
+    home.define_group(String::from("kitchen"), vec![thermo, socket])
+        .unwrap();
+    let scene = Scene::new()
+        .device(socket, DeviceState::Off)
+        .device(thermo, DeviceState::Measure(TempMeasures::C));
+    home.define_scene(String::from("night"), scene.clone())
+        .unwrap();
+
+    for format in [HomeFormat::Json, HomeFormat::Toml] {
+        let content = home.to_string_with(format).unwrap();
+        let mut loaded = SmartHome::from_str_with(&content, format).unwrap();
+        assert_eq!(loaded.group("kitchen").unwrap(), &[thermo, socket][..]);
+        assert_eq!(loaded.scene("night"), Some(&scene));
+        assert_eq!(loaded.apply_scene("night").unwrap().changed.len(), 2);
+    }
+
+    // Файлы без групп и сцен не меняются
+    let content = create_home().to_string_with(HomeFormat::Json).unwrap();
+    assert!(!content.contains("scenes"));
+}
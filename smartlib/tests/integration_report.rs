@@ -0,0 +1,90 @@
+use smartlib::report::{FieldValue, HomeReport, ReportFormat};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::structures::Report;
+use smartlib::{SmartHome, add_room};
+
+fn create_home() -> SmartHome {
+    let mut socket = SmartElectricalSoket::new(String::from("Freezer"), 220.0);
+    socket.turn_on().unwrap();
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo, kitchen"), TempMeasures::C, 21.5)
+        ),
+        ("S1", socket),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap()
+}
+
+#[test]
+fn model_contains_typed_device_fields() {
+    let report = HomeReport::from(&create_home());
+    assert_eq!(report.name, "MyHome");
+    let room = &report.rooms[0];
+    assert_eq!(room.key, "Кухня");
+
+    let socket = room
+        .devices
+        .iter()
+        .find(|device| device.key == "S1")
+        .unwrap();
+    assert_eq!(socket.kind, "socket");
+    assert!(
+        socket
+            .fields
+            .iter()
+            .any(|field| field.name == "power" && field.value == FieldValue::Number(220.0))
+    );
+
+    let thermo = room
+        .devices
+        .iter()
+        .find(|device| device.key == "T1")
+        .unwrap();
+    assert!(
+        thermo
+            .fields
+            .iter()
+            .any(|field| field.name == "measure"
+                && field.value == FieldValue::Text(String::from("C")))
+    );
+}
+
+#[test]
+fn text_format_matches_report_trait() {
+    let home = create_home();
+    assert_eq!(home.report_as(ReportFormat::Text), home.report());
+    let room = home.get_room("Кухня").unwrap();
+    assert_eq!(room.report_as(ReportFormat::Text), room.report());
+}
+
+#[test]
+fn machine_readable_formats() {
+    let home = create_home();
+
+    let json = home.report_as(ReportFormat::Json);
+    assert!(json.starts_with("{\"name\":\"MyHome\",\"rooms\":[{\"key\":\"Кухня\""));
+    assert!(json.contains("\"fields\":{\"is_on\":true,\"power\":220,\"nominal_power\":220}"));
+    assert!(json.contains("\"tempreture\":21.5"));
+
+    let csv = home.report_as(ReportFormat::Csv);
+    let header = csv.lines().next().unwrap();
+    assert!(header.starts_with("room_key,room_name,device_key,device_name,kind,status,"));
+    for column in [
+        "tempreture",
+        "measure",
+        "stale",
+        "is_on",
+        "power",
+        "nominal_power",
+    ] {
+        assert!(header.split(',').any(|name| name == column));
+    }
+    assert!(csv.contains("Кухня,Кухня,T1,\"Termo, kitchen\",thermometer,"));
+    assert_eq!(csv.lines().count(), 3);
+
+    let markdown = home.report_as(ReportFormat::Markdown);
+    assert!(markdown.starts_with("# Отчет для дома: MyHome\n\n## Комната 'Кухня'"));
+    assert!(markdown.contains("| S1 | Freezer | socket | включена |"));
+}
@@ -0,0 +1,264 @@
+use smartlib::rules::{Action, ActionError, Condition, PlannedAction, Rule, RuleEngine};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{SharedHome, SmartHome, add_room};
+use std::time::Duration;
+
+fn create_home() -> SmartHome {
+    let mut heater = SmartElectricalSoket::new(String::from("Heater"), 1500.0);
+    heater.turn_on().unwrap();
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 30.0)
+        ),
+        ("Fan", SmartElectricalSoket::new(String::from("Fan"), 50.0)),
+        ("Heater", heater),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap()
+}
+
+fn socket(room: &str, device: &str) -> (String, String) {
+    (room.to_string(), device.to_string())
+}
+
+fn turn_on(room: &str, device: &str) -> Action {
+    let (room, device) = socket(room, device);
+    Action::TurnOn { room, device }
+}
+
+fn turn_off(room: &str, device: &str) -> Action {
+    let (room, device) = socket(room, device);
+    Action::TurnOff { room, device }
+}
+
+fn hot_kitchen() -> Condition {
+    Condition::TemperatureAbove {
+        room: String::from("Кухня"),
+        device: String::from("T1"),
+        value: 28.0,
+        measure: TempMeasures::C,
+    }
+}
+
+fn is_on(home: &SmartHome, device: &str) -> bool {
+    home.get_device_from_room("Кухня", device)
+        .unwrap()
+        .downcast_ref::<SmartElectricalSoket>()
+        .unwrap()
+        .is_on()
+}
+
+#[test]
+fn dry_run_reports_without_changing_home() {
+    let home = create_home();
+    let engine = RuleEngine::new(vec![
+        Rule::new("cool", hot_kitchen(), vec![turn_on("Кухня", "Fan")]),
+        // Обогреватель уже включен - действие не сработает
+        Rule::new("heat", hot_kitchen(), vec![turn_on("Кухня", "Heater")]),
+    ]);
+    assert_eq!(
+        engine.dry_run(&home),
+        vec![PlannedAction {
+            rule: String::from("cool"),
+            action: turn_on("Кухня", "Fan"),
+            conflict: None,
+        }]
+    );
+    assert!(!is_on(&home, "Fan"));
+}
+
+#[test]
+fn power_condition_and_evaluation() {
+    let mut home = create_home();
+    let engine = RuleEngine::new(vec![Rule::new(
+        "overload",
+        Condition::PowerAbove {
+            room: None,
+            watts: 1000.0,
+        },
+        vec![turn_off("Кухня", "Heater")],
+    )]);
+    let outcomes = engine.evaluate(&mut home);
+    assert_eq!(outcomes.len(), 1);
+    assert!(outcomes[0].result.is_ok());
+    assert!(!is_on(&home, "Heater"));
+    assert!(engine.evaluate(&mut home).is_empty());
+}
+
+#[test]
+fn conflicting_rules_are_detected_and_skipped() {
+    let mut home = create_home();
+    let engine = RuleEngine::new(vec![
+        Rule::new("on", hot_kitchen(), vec![turn_on("Кухня", "Fan")]),
+        Rule::new(
+            "off",
+            Condition::Not(Box::new(Condition::SocketIs {
+                room: String::from("Кухня"),
+                device: String::from("Fan"),
+                on: false,
+            })),
+            vec![turn_off("Кухня", "Fan")],
+        ),
+        Rule::new("quiet", hot_kitchen(), vec![turn_off("Кухня", "Fan")]),
+    ]);
+
+    let conflicts = engine.conflicts();
+    assert_eq!(conflicts.len(), 2);
+    assert_eq!(conflicts[0].first, "on");
+    assert_eq!(conflicts[0].second, "off");
+
+    // "off" не срабатывает, пока вентилятор выключен, а "on" и "quiet" противоречат друг другу
+    let planned = engine.dry_run(&home);
+    assert_eq!(planned.len(), 1);
+    assert_eq!(planned[0].conflict.as_deref(), Some("quiet"));
+
+    let outcomes = engine.evaluate(&mut home);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].rule, "on");
+    assert!(matches!(
+        &outcomes[0].result,
+        Err(ActionError::Conflict { rule }) if rule == "quiet"
+    ));
+    assert!(!is_on(&home, "Fan"));
+}
+
+#[test]
+fn rules_run_on_state_changes() {
+    let mut home = create_home();
+    home.update_device("Кухня", "T1", |device| {
+        device
+            .downcast_mut::<SmartThermometer>()
+            .unwrap()
+            .set_tempreture(20.0)
+    })
+    .unwrap();
+
+    let mut engine = RuleEngine::new(vec![
+        Rule::new("cool", hot_kitchen(), vec![turn_on("Кухня", "Fan")]),
+        // Цепочка: включенный вентилятор переводит термометр в Фаренгейты
+        Rule::new(
+            "imperial",
+            Condition::SocketIs {
+                room: String::from("Кухня"),
+                device: String::from("Fan"),
+                on: true,
+            },
+            vec![Action::SetMeasure {
+                room: String::from("Кухня"),
+                device: String::from("T1"),
+                measure: TempMeasures::F,
+            }],
+        ),
+        Rule::new("broken", hot_kitchen(), vec![turn_on("Кухня", "T1")]),
+    ]);
+    engine.watch(&home);
+    assert!(engine.process(&mut home).is_empty());
+
+    home.update_device("Кухня", "T1", |device| {
+        device
+            .downcast_mut::<SmartThermometer>()
+            .unwrap()
+            .set_tempreture(29.0)
+    })
+    .unwrap();
+    let outcomes = engine.process(&mut home);
+    assert_eq!(outcomes.len(), 3);
+    assert!(is_on(&home, "Fan"));
+    let failed = outcomes
+        .iter()
+        .find_map(|outcome| outcome.result.as_ref().err())
+        .unwrap();
+    assert!(matches!(
+        failed,
+        ActionError::Home(err) if err.code() == "wrong_device_type"
+    ));
+    assert_eq!(failed.code(), "wrong_device_type");
+    let thermo = home.get_device_from_room("Кухня", "T1").unwrap();
+    assert_eq!(
+        thermo
+            .downcast_ref::<SmartThermometer>()
+            .unwrap()
+            .get_measure(),
+        "F"
+    );
+}
+
+#[test]
+fn spawned_engine_fires_on_state_change() {
+    let home = SharedHome::new(create_home());
+    let engine = RuleEngine::new(vec![Rule::new(
+        "overload",
+        Condition::PowerAbove {
+            room: None,
+            watts: 1500.0,
+        },
+        vec![turn_off("Кухня", "Heater")],
+    )]);
+    let handle = engine.spawn(home.clone());
+
+    // Правило срабатывает только от изменения дома, без вызова движка
+    home.update_device("Кухня", "Fan", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+    })
+    .unwrap()
+    .unwrap();
+    let outcome = handle
+        .outcomes()
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(outcome.rule, "overload");
+    assert!(outcome.result.is_ok());
+    let heater = home.with_device("Кухня", "Heater", |device| device.status());
+    assert_eq!(heater.unwrap(), "выключена");
+
+    handle.stop();
+    home.update_device("Кухня", "Heater", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+    })
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        home.with_device("Кухня", "Heater", |device| device.status())
+            .unwrap(),
+        "включена"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn rules_load_from_config() {
+    use smartlib::persistence::HomeFormat;
+
+    let config = r#"
+        [[rules]]
+        name = "cool"
+        then = [{ turn_on = { room = "Кухня", device = "Fan" } }]
+
+        [rules.when.temperature_above]
+        room = "Кухня"
+        device = "T1"
+        value = 82.0
+        measure = "F"
+    "#;
+    let engine = RuleEngine::from_str_with(config, HomeFormat::Toml).unwrap();
+    assert_eq!(engine.rules().len(), 1);
+    assert_eq!(engine.dry_run(&create_home()).len(), 1);
+
+    let json = r#"{"rules": [{"name": "x", "when": {"power_above": {"watts": 10}}, "then": []}]}"#;
+    let engine = RuleEngine::from_str_with(json, HomeFormat::Json).unwrap();
+    assert_eq!(
+        engine.rules()[0].when,
+        Condition::PowerAbove {
+            room: None,
+            watts: 10.0
+        }
+    );
+    assert!(RuleEngine::from_str_with("{\"rules\": 1}", HomeFormat::Json).is_err());
+}
@@ -0,0 +1,188 @@
+use smartlib::errors::SmartHomeErrors;
+use smartlib::events::HomeEvent;
+use smartlib::scenes::{DeviceState, Scene, SceneTarget};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{DeviceId, SharedHome, SmartHome, add_room};
+
+fn create_home() -> SmartHome {
+    let mut kettle = SmartElectricalSoket::new(String::from("Kettle"), 2000.0);
+    kettle.turn_on().unwrap();
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        ("S1", kettle),
+        (
+            "S2",
+            SmartElectricalSoket::new(String::from("Oven"), 3000.0)
+        ),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+        ),
+    );
+    let hall = add_room!(
+        String::from("Зал"),
+        ("S1", SmartElectricalSoket::new(String::from("Lamp"), 60.0)),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen, hall]).unwrap()
+}
+
+fn id(home: &SmartHome, room: &str, key: &str) -> DeviceId {
+    home.get_room(room).unwrap().device_id(key).unwrap()
+}
+
+fn status(home: &SmartHome, room: &str, key: &str) -> String {
+    home.get_device_from_room(room, key).unwrap().status()
+}
+
+#[test]
+fn scene_applies_to_groups_across_rooms() {
+    let mut home = create_home();
+    let sockets: Vec<DeviceId> = home
+        .devices()
+        .filter(|entry| entry.device.kind() == "socket")
+        .map(|entry| entry.id)
+        .collect();
+    home.define_group(String::from("night load"), sockets)
+        .unwrap();
+    let scene = Scene::new()
+        .group("night load", DeviceState::Off)
+        .device(id(&home, "Зал", "S1"), DeviceState::On)
+        .device(
+            id(&home, "Кухня", "T1"),
+            DeviceState::Measure(TempMeasures::F),
+        );
+    home.define_scene(String::from("evening"), scene).unwrap();
+    let events = home.events().channel();
+
+    let report = home.apply_scene("evening").unwrap();
+    assert!(report.is_success());
+    let changed: Vec<(&str, &str)> = report
+        .changed
+        .iter()
+        .map(|device| (device.room.as_str(), device.key.as_str()))
+        .collect();
+    assert_eq!(
+        changed,
+        vec![("Кухня", "S1"), ("Зал", "S1"), ("Кухня", "T1")]
+    );
+    // Кухонная розетка S2 уже выключена; лампа изменена один раз, хотя входит в группу
+    assert_eq!(report.unchanged.len(), 1);
+    assert_eq!(status(&home, "Кухня", "S1"), "выключена");
+    assert_eq!(status(&home, "Зал", "S1"), "включена");
+    assert_eq!(status(&home, "Кухня", "T1"), "68° F");
+    // Изменения публикуются как обычные изменения состояния
+    assert_eq!(
+        events
+            .try_iter()
+            .filter(|event| matches!(event, HomeEvent::StateChanged { .. }))
+            .count(),
+        3
+    );
+
+    // Повторное применение ничего не меняет
+    let report = home.apply_scene("evening").unwrap();
+    assert!(report.changed.is_empty());
+    assert!(
+        report
+            .to_string()
+            .starts_with("Изменено: 0, без изменений: 4")
+    );
+}
+
+#[test]
+fn failures_are_reported_without_stopping_the_scene() {
+    let mut home = create_home();
+    let thermo = id(&home, "Кухня", "T1");
+    let oven = id(&home, "Кухня", "S2");
+    home.define_group(String::from("kitchen"), vec![thermo, oven])
+        .unwrap();
+    home.get_mutable_room("Зал")
+        .unwrap()
+        .delete_device("S1")
+        .unwrap();
+
+    let report = home.apply(
+        &Scene::new()
+            .group("kitchen", DeviceState::On)
+            .group("missing", DeviceState::Off)
+            .device(DeviceId::from(u64::MAX), DeviceState::Off),
+    );
+    assert_eq!(report.changed.len(), 1);
+    assert_eq!(report.changed[0].key, "S2");
+    let codes: Vec<&str> = report
+        .failed
+        .iter()
+        .map(|failure| failure.error.code())
+        .collect();
+    assert_eq!(
+        codes,
+        vec!["group_not_found", "wrong_device_type", "device_not_found"]
+    );
+    assert_eq!(report.failed[1].target, SceneTarget::Device(thermo));
+    assert!(!report.is_success());
+
+    assert!(matches!(
+        home.apply_scene("nope"),
+        Err(SmartHomeErrors::SceneNotFound(_))
+    ));
+    assert!(matches!(
+        home.define_scene(
+            String::from("broken"),
+            Scene::new().group("missing", DeviceState::On)
+        ),
+        Err(SmartHomeErrors::GroupNotFound(_))
+    ));
+    assert!(
+        home.define_group(String::from("gone"), vec![DeviceId::from(u64::MAX)])
+            .is_err()
+    );
+}
+
+#[test]
+fn groups_follow_moved_devices() {
+    let mut home = create_home();
+    let oven = id(&home, "Кухня", "S2");
+    home.define_group(String::from("appliances"), vec![oven])
+        .unwrap();
+    home.define_scene(
+        String::from("cook"),
+        Scene::new().group("appliances", DeviceState::On),
+    )
+    .unwrap();
+    home.move_device("Кухня", "Зал", "S2").unwrap();
+    home.rename_device("Зал", "S2", String::from("Oven"))
+        .unwrap();
+
+    let report = home.apply_scene("cook").unwrap();
+    assert_eq!(report.changed[0].room, "Зал");
+    assert_eq!(report.changed[0].key, "Oven");
+
+    home.remove_group("appliances").unwrap();
+    assert!(home.remove_group("appliances").is_err());
+    assert!(!home.apply_scene("cook").unwrap().is_success());
+    home.remove_scene("cook").unwrap();
+    assert_eq!(home.scenes().count(), 0);
+}
+
+#[test]
+fn shared_home_applies_scenes() {
+    let mut home = create_home();
+    let lamp = id(&home, "Зал", "S1");
+    home.define_scene(
+        String::from("light"),
+        Scene::new().device(lamp, DeviceState::On),
+    )
+    .unwrap();
+    let home = SharedHome::new(home);
+    assert_eq!(home.scene_names(), vec![String::from("light")]);
+
+    let report = home.apply_scene("light").unwrap();
+    assert_eq!(report.changed.len(), 1);
+    assert_eq!(
+        home.with_device("Зал", "S1", |device| device.status())
+            .unwrap(),
+        "включена"
+    );
+    // Снимок сохраняет сцены дома
+    assert!(home.snapshot().scene("light").is_some());
+}
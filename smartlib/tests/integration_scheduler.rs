@@ -0,0 +1,141 @@
+use smartlib::clock::ManualClock;
+use smartlib::events::HomeEvent;
+use smartlib::rules::{Action, ActionError};
+use smartlib::scheduler::{Schedule, Scheduler};
+use smartlib::smart_devices::SmartElectricalSoket;
+use smartlib::{SmartHome, add_room};
+use std::time::{Duration, UNIX_EPOCH};
+
+const HOUR: u64 = 60 * 60;
+
+fn create_home() -> SmartHome {
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        ("Fan", SmartElectricalSoket::new(String::from("Fan"), 50.0)),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen]).unwrap()
+}
+
+fn turn_on() -> Action {
+    Action::TurnOn {
+        room: String::from("Кухня"),
+        device: String::from("Fan"),
+    }
+}
+
+fn turn_off() -> Action {
+    Action::TurnOff {
+        room: String::from("Кухня"),
+        device: String::from("Fan"),
+    }
+}
+
+fn fan_is_on(home: &SmartHome) -> bool {
+    home.get_device_from_room("Кухня", "Fan")
+        .unwrap()
+        .downcast_ref::<SmartElectricalSoket>()
+        .unwrap()
+        .is_on()
+}
+
+/// Планировщик с часами, стоящими на полуночи 1 января 1970 UTC
+fn create_scheduler() -> Scheduler<ManualClock> {
+    Scheduler::new(ManualClock::new(UNIX_EPOCH))
+}
+
+#[test]
+fn one_shot_jobs_run_once_in_time_order() {
+    let mut home = create_home();
+    let mut scheduler = create_scheduler();
+    let off = scheduler
+        .schedule(turn_off(), Schedule::After(Duration::from_secs(20)))
+        .unwrap();
+    let on = scheduler
+        .schedule(
+            turn_on(),
+            Schedule::At(UNIX_EPOCH + Duration::from_secs(10)),
+        )
+        .unwrap();
+    assert_eq!(scheduler.time_until_next(), Some(Duration::from_secs(10)));
+
+    scheduler.clock().advance(Duration::from_secs(5));
+    assert!(scheduler.run_due(&mut home).is_empty());
+
+    scheduler.clock().advance(Duration::from_secs(5));
+    let outcomes = scheduler.run_due(&mut home);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].id, on);
+    assert!(fan_is_on(&home));
+    assert_eq!(scheduler.next_run(on), None);
+
+    // Разовое задание на включение уже выполнено и не повторяется
+    scheduler.clock().advance(Duration::from_secs(60));
+    let outcomes = scheduler.run_due(&mut home);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].id, off);
+    assert!(!fan_is_on(&home));
+    assert_eq!(scheduler.time_until_next(), None);
+}
+
+#[test]
+fn cron_jobs_repeat_and_skip_missed_runs() {
+    let mut home = create_home();
+    let receiver = home.events().channel();
+    let mut scheduler = create_scheduler();
+    let on = scheduler
+        .schedule(turn_on(), Schedule::Cron("0 8 * * *".parse().unwrap()))
+        .unwrap();
+    scheduler
+        .schedule(turn_off(), Schedule::Cron("0 20 * * *".parse().unwrap()))
+        .unwrap();
+    assert_eq!(
+        scheduler.next_run(on),
+        Some(UNIX_EPOCH + Duration::from_secs(8 * HOUR))
+    );
+
+    scheduler.clock().advance(Duration::from_secs(8 * HOUR));
+    scheduler.run_due(&mut home);
+    assert!(fan_is_on(&home));
+    assert!(matches!(
+        receiver.try_recv(),
+        Ok(HomeEvent::StateChanged { .. })
+    ));
+
+    scheduler.clock().advance(Duration::from_secs(12 * HOUR));
+    scheduler.run_due(&mut home);
+    assert!(!fan_is_on(&home));
+
+    // Пропущено несколько дней: каждое задание выполняется один раз
+    scheduler
+        .clock()
+        .advance(Duration::from_secs(3 * 24 * HOUR));
+    assert_eq!(scheduler.run_due(&mut home).len(), 2);
+    assert_eq!(
+        scheduler.next_run(on),
+        Some(UNIX_EPOCH + Duration::from_secs(4 * 24 * HOUR + 8 * HOUR))
+    );
+}
+
+#[test]
+fn cancel_and_errors() {
+    let mut home = create_home();
+    let mut scheduler = create_scheduler();
+    let job = scheduler
+        .schedule(turn_on(), Schedule::After(Duration::from_secs(1)))
+        .unwrap();
+    assert!(scheduler.cancel(job));
+    assert!(!scheduler.cancel(job));
+
+    let missing = Action::TurnOn {
+        room: String::from("Кухня"),
+        device: String::from("Lamp"),
+    };
+    scheduler
+        .schedule(missing, Schedule::After(Duration::ZERO))
+        .unwrap();
+    let outcomes = scheduler.run_due(&mut home);
+    assert!(matches!(outcomes[0].result, Err(ActionError::Home(_))));
+
+    let never = Schedule::Cron("0 0 30 2 *".parse().unwrap());
+    assert!(scheduler.schedule(turn_on(), never).is_err());
+}
@@ -0,0 +1,336 @@
+use smartlib::events::HomeEvent;
+use smartlib::report::ReportFormat;
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{Room, SharedHome, SmartHome, add_room};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+fn create_home() -> SmartHome {
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "S1",
+            SmartElectricalSoket::new(String::from("Kettle"), 2000.0)
+        ),
+        (
+            "S2",
+            SmartElectricalSoket::new(String::from("Toaster"), 800.0)
+        ),
+    );
+    let bedroom = add_room!(
+        String::from("Спальня"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+        ),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen, bedroom]).unwrap()
+}
+
+fn switch(home: &SharedHome, room: &str, key: &str) {
+    home.update_device(room, key, |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .switch()
+            .unwrap()
+    })
+    .unwrap();
+}
+
+fn is_on(home: &SmartHome, room: &str, key: &str) -> bool {
+    home.get_device_from_room(room, key)
+        .unwrap()
+        .downcast_ref::<SmartElectricalSoket>()
+        .unwrap()
+        .is_on()
+}
+
+#[test]
+fn handle_is_shared_between_threads() {
+    let home = SharedHome::new(create_home());
+    let receiver = home.events().channel();
+
+    let workers: Vec<_> = ["S1", "S2"]
+        .into_iter()
+        .map(|key| {
+            let home = home.clone();
+            thread::spawn(move || {
+                for _ in 0..101 {
+                    switch(&home, "Кухня", key);
+                }
+            })
+        })
+        .collect();
+    let thermo = {
+        let home = home.clone();
+        thread::spawn(move || {
+            for step in 0..100 {
+                home.update_device("Спальня", "T1", |device| {
+                    device
+                        .downcast_mut::<SmartThermometer>()
+                        .unwrap()
+                        .set_tempreture(21.0 + step as f32)
+                })
+                .unwrap();
+            }
+        })
+    };
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    thermo.join().unwrap();
+
+    let snapshot = home.snapshot();
+    assert!(is_on(&snapshot, "Кухня", "S1"));
+    assert!(is_on(&snapshot, "Кухня", "S2"));
+    let tempreture = home
+        .with_device("Спальня", "T1", |device| device.status())
+        .unwrap();
+    assert_eq!(tempreture, "120° C");
+    assert_eq!(receiver.try_iter().count(), 2 * 101 + 100);
+}
+
+#[test]
+fn snapshot_is_consistent_and_detached() {
+    let home = SharedHome::new(create_home());
+
+    // Писатель переключает обе розетки кухни за одно изменение комнаты
+    let writer = {
+        let home = home.clone();
+        thread::spawn(move || {
+            for _ in 0..500 {
+                home.with_room_mut("Кухня", |room| {
+                    for key in ["S1", "S2"] {
+                        room.update_device(key, |device| {
+                            device
+                                .downcast_mut::<SmartElectricalSoket>()
+                                .unwrap()
+                                .switch()
+                                .unwrap()
+                        })
+                        .unwrap();
+                    }
+                })
+                .unwrap();
+            }
+        })
+    };
+    for _ in 0..200 {
+        let snapshot = home.snapshot();
+        assert_eq!(
+            is_on(&snapshot, "Кухня", "S1"),
+            is_on(&snapshot, "Кухня", "S2")
+        );
+    }
+    writer.join().unwrap();
+
+    // Изменения снимка не попадают в дом и не порождают его событий
+    let receiver = home.events().channel();
+    let mut snapshot = home.snapshot();
+    snapshot.delete_room("Спальня").unwrap();
+    assert_eq!(home.room_keys(), vec!["Кухня", "Спальня"]);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn readers_do_not_block_other_rooms() {
+    let home = SharedHome::new(create_home());
+    let (entered, wait_entered) = mpsc::channel();
+    let (release, wait_release) = mpsc::channel::<()>();
+
+    // Долгое чтение спальни не мешает изменять кухню
+    let reader = {
+        let home = home.clone();
+        thread::spawn(move || {
+            home.with_room("Спальня", |_| {
+                entered.send(()).unwrap();
+                wait_release.recv_timeout(Duration::from_secs(5)).unwrap();
+            })
+            .unwrap();
+        })
+    };
+    wait_entered.recv().unwrap();
+    switch(&home, "Кухня", "S1");
+    release.send(()).unwrap();
+    reader.join().unwrap();
+
+    assert!(
+        home.report_as(ReportFormat::Text)
+            .contains("Kettle': включена")
+    );
+}
+
+#[test]
+fn rooms_are_added_and_removed_with_events() {
+    let home = SharedHome::new(create_home());
+    let receiver = home.events().channel();
+
+    home.add_room_with_key(String::from("Зал"), Room::new(String::from("Зал")))
+        .unwrap();
+    home.with_room_mut("Зал", |room| {
+        room.add_device_with_key(
+            String::from("S3"),
+            SmartElectricalSoket::new(String::from("Lamp"), 60.0).into(),
+        )
+        .unwrap()
+    })
+    .unwrap();
+    let removed = home.delete_room("Зал").unwrap();
+    assert!(removed.get_device("S3").is_some());
+
+    let events: Vec<HomeEvent> = receiver.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            HomeEvent::RoomAdded {
+                room: String::from("Зал")
+            },
+            HomeEvent::DeviceAdded {
+                room: String::from("Зал"),
+                device: String::from("S3")
+            },
+            HomeEvent::RoomRemoved {
+                room: String::from("Зал")
+            },
+        ]
+    );
+    assert!(home.delete_room("Зал").is_err());
+    assert!(home.with_device("Кухня", "Nope", |_| ()).is_err());
+}
+
+#[test]
+fn devices_are_found_by_id() {
+    let home = SharedHome::new(create_home());
+    let id = home
+        .with_room("Кухня", |room| room.device_id("S1").unwrap())
+        .unwrap();
+    assert_eq!(
+        home.locate_device(id),
+        Some((String::from("Кухня"), String::from("S1")))
+    );
+    home.update_device_by_id(id, |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+            .unwrap()
+    })
+    .unwrap();
+    assert_eq!(
+        home.with_device_by_id(id, |device| device.status())
+            .unwrap(),
+        "включена"
+    );
+
+    home.with_room_mut("Кухня", |room| room.delete_device("S1"))
+        .unwrap()
+        .unwrap();
+    assert!(home.locate_device(id).is_none());
+    assert!(home.with_device_by_id(id, |_| ()).is_err());
+}
+
+#[test]
+fn devices_are_moved_and_renamed() {
+    let home = SharedHome::new(create_home());
+    home.add_room(Room::new(String::from("Зал"))).unwrap();
+    let receiver = home.events().channel();
+
+    // Встречные переносы не блокируют друг друга
+    let movers: Vec<_> = [("Кухня", "Зал"), ("Зал", "Кухня")]
+        .into_iter()
+        .map(|(from, to)| {
+            let home = home.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let _ = home.move_device(from, to, "S1");
+                }
+            })
+        })
+        .collect();
+    for mover in movers {
+        mover.join().unwrap();
+    }
+    let room = ["Кухня", "Зал"]
+        .into_iter()
+        .find(|room| home.with_device(room, "S1", |_| ()).is_ok())
+        .expect("device is in one of the rooms");
+    let other = if room == "Кухня" {
+        "Зал"
+    } else {
+        "Кухня"
+    };
+    assert!(home.with_device(other, "S1", |_| ()).is_err());
+    // Каждый перенос - удаление из одной комнаты и добавление в другую
+    let events: Vec<HomeEvent> = receiver.try_iter().collect();
+    assert_eq!(events.len() % 2, 0);
+
+    home.rename_device(room, "S1", String::from("Socket"))
+        .unwrap();
+    home.rename_room(room, String::from("Новая")).unwrap();
+    assert!(home.with_device("Новая", "Socket", |_| ()).is_ok());
+    assert!(home.rename_room("Нет", String::from("Другая")).is_err());
+}
+
+#[test]
+fn subscribers_may_call_back_into_home() {
+    let home = SharedHome::new(create_home());
+    let (sender, receiver) = mpsc::channel();
+    {
+        let home = home.clone();
+        let sender = sender.clone();
+        home.clone().events().subscribe(move |event| {
+            // События рассылаются без блокировок, поэтому обращение к дому не зависает
+            if let HomeEvent::StateChanged { room, .. } = event {
+                let snapshot = home.snapshot();
+                let devices = home.with_room(room, |room| room.devices().count()).unwrap();
+                let _ = sender.send((snapshot.rooms().count(), devices));
+            } else if let HomeEvent::DeviceAdded { room, .. } = event
+                && room == "Спальня"
+            {
+                let _ = home.add_room(Room::new(String::from("Холл")));
+            }
+        });
+    }
+
+    let worker = {
+        let home = home.clone();
+        thread::spawn(move || {
+            switch(&home, "Кухня", "S1");
+            home.move_device("Кухня", "Спальня", "S2").unwrap();
+        })
+    };
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(2)).unwrap(),
+        (2, 2)
+    );
+    worker.join().unwrap();
+    assert!(home.room_keys().contains(&String::from("Холл")));
+}
+
+#[test]
+fn restore_replaces_rooms_from_snapshot() {
+    let home = SharedHome::new(create_home());
+    let events = home.events().channel();
+    let before = home.snapshot();
+    home.add_room(Room::new(String::from("Холл"))).unwrap();
+    switch(&home, "Кухня", "S1");
+
+    home.restore(before);
+    assert_eq!(home.room_keys(), vec!["Кухня", "Спальня"]);
+    let status = home.with_device("Кухня", "S1", |device| device.status());
+    assert_eq!(status.unwrap(), "выключена");
+    let removed = events
+        .try_iter()
+        .filter(|event| matches!(event, HomeEvent::RoomRemoved { .. }))
+        .count();
+    assert_eq!(removed, 3);
+
+    // Восстановленные комнаты снова рассылают события
+    switch(&home, "Кухня", "S1");
+    assert!(matches!(
+        events.try_iter().last(),
+        Some(HomeEvent::StateChanged { .. })
+    ));
+}
@@ -0,0 +1,208 @@
+use smartlib::clock::ManualClock;
+use smartlib::errors::SmartHomeErrors;
+use smartlib::report::ReportFormat;
+use smartlib::simulator::{Simulator, SimulatorConfig};
+use smartlib::smart_devices::{SmartElectricalSoket, SmartThermometer, TempMeasures};
+use smartlib::{SmartHome, add_room};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn create_home(clock: &ManualClock) -> SmartHome {
+    let clock: Arc<ManualClock> = Arc::new(clock.clone());
+    let mut kettle =
+        SmartElectricalSoket::new(String::from("Kettle"), 2000.0).with_clock(clock.clone());
+    kettle.turn_on().unwrap();
+    let kitchen = add_room!(
+        String::from("Кухня"),
+        (
+            "T1",
+            SmartThermometer::new(String::from("Termo"), TempMeasures::C, 20.0)
+                .with_clock(clock.clone())
+        ),
+        ("Kettle", kettle),
+    );
+    let balcony = add_room!(
+        String::from("Балкон"),
+        (
+            "T2",
+            SmartThermometer::new(String::from("Outside"), TempMeasures::F, 50.0)
+                .with_clock(clock.clone())
+        ),
+    );
+    SmartHome::try_new(String::from("MyHome"), vec![kitchen, balcony]).unwrap()
+}
+
+fn simulate(seed: u64, steps: usize) -> String {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    let mut simulator =
+        Simulator::new(seed, SimulatorConfig::default()).with_clock(Arc::new(clock.clone()));
+    for _ in 0..steps {
+        simulator.step(&mut home);
+        clock.advance(Duration::from_secs(600));
+    }
+    // CSV не содержит идентификаторов, которые у разных домов различаются
+    home.report_as(ReportFormat::Csv)
+}
+
+fn tempreture(home: &SmartHome, room: &str, key: &str) -> f32 {
+    home.get_device_from_room(room, key)
+        .unwrap()
+        .downcast_ref::<SmartThermometer>()
+        .unwrap()
+        .get_tempreture()
+}
+
+fn quiet_config() -> SimulatorConfig {
+    SimulatorConfig {
+        drift_step: 0.0,
+        load_fluctuation: 0.0,
+        failure_probability: 0.0,
+        ..SimulatorConfig::default()
+    }
+}
+
+#[test]
+fn same_seed_reproduces_simulation() {
+    assert_eq!(simulate(42, 100), simulate(42, 100));
+    assert_ne!(simulate(42, 100), simulate(7, 100));
+}
+
+#[test]
+fn daily_cycle_moves_temperature() {
+    // Начинаем в 03:00, в самое холодное время
+    let clock = ManualClock::new(UNIX_EPOCH + HOUR * 3);
+    let mut home = create_home(&clock);
+    let mut simulator = Simulator::new(1, quiet_config()).with_clock(Arc::new(clock.clone()));
+
+    simulator.step(&mut home);
+    assert_eq!(tempreture(&home, "Кухня", "T1"), 20.0);
+
+    clock.advance(HOUR * 12);
+    let report = simulator.step(&mut home);
+    assert_eq!(report.updated, 3);
+    assert!((tempreture(&home, "Кухня", "T1") - 26.0).abs() < 1e-3);
+    // Термометр в Фаренгейтах: 6° C суточного хода - это 10.8° F
+    assert!((tempreture(&home, "Балкон", "T2") - 60.8).abs() < 1e-3);
+
+    let thermo = home.get_device_from_room("Кухня", "T1").unwrap();
+    let history = thermo.downcast_ref::<SmartThermometer>().unwrap().history();
+    assert_eq!(history.len(), 2);
+}
+
+#[test]
+fn load_fluctuates_within_bounds() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    let config = SimulatorConfig {
+        failure_probability: 0.0,
+        ..SimulatorConfig::default()
+    };
+    let mut simulator = Simulator::new(3, config).with_clock(Arc::new(clock.clone()));
+    let mut powers = Vec::new();
+    for _ in 0..20 {
+        simulator.step(&mut home);
+        let socket = home.get_device_from_room("Кухня", "Kettle").unwrap();
+        powers.push(
+            socket
+                .downcast_ref::<SmartElectricalSoket>()
+                .unwrap()
+                .get_power(),
+        );
+    }
+    assert!(powers.iter().all(|power| (1800.0..=2200.0).contains(power)));
+    assert!(powers.windows(2).any(|pair| pair[0] != pair[1]));
+}
+
+#[test]
+fn failures_and_recovery() {
+    let clock = ManualClock::new(UNIX_EPOCH);
+    let mut home = create_home(&clock);
+    let config = SimulatorConfig {
+        failure_probability: 1.0,
+        recovery_probability: 1.0,
+        ..quiet_config()
+    };
+    let mut simulator = Simulator::new(5, config).with_clock(Arc::new(clock.clone()));
+
+    let report = simulator.step(&mut home);
+    assert_eq!(report.failed.len(), 3);
+    assert_eq!(report.updated, 0);
+    assert!(simulator.is_failed("Кухня", "Kettle"));
+    let kettle = home.get_device_from_room("Кухня", "Kettle").unwrap();
+    assert!(
+        !kettle
+            .downcast_ref::<SmartElectricalSoket>()
+            .unwrap()
+            .is_on()
+    );
+
+    // Отказ виден через дом: термометр не дает показаний, розетку не включить
+    let termo = home.get_device_from_room("Кухня", "T1").unwrap();
+    assert!(termo.is_offline());
+    let err = termo
+        .downcast_ref::<SmartThermometer>()
+        .unwrap()
+        .current_tempreture()
+        .unwrap_err();
+    assert!(matches!(err, SmartHomeErrors::DeviceOffline { ref device, .. } if device == "Termo"));
+    assert_eq!(termo.status(), "20° C (не в сети)");
+    let err = home
+        .update_device("Кухня", "Kettle", |device| {
+            device
+                .downcast_mut::<SmartElectricalSoket>()
+                .unwrap()
+                .turn_on()
+        })
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.code(), "device_offline");
+
+    let report = simulator.step(&mut home);
+    assert_eq!(report.recovered.len(), 3);
+    assert!(!simulator.is_failed("Кухня", "Kettle"));
+    let termo = home.get_device_from_room("Кухня", "T1").unwrap();
+    assert!(!termo.is_offline());
+    home.update_device("Кухня", "Kettle", |device| {
+        device
+            .downcast_mut::<SmartElectricalSoket>()
+            .unwrap()
+            .turn_on()
+    })
+    .unwrap()
+    .unwrap();
+}
+
+#[test]
+fn invalid_config_is_rejected() {
+    assert!(SimulatorConfig::default().validate().is_ok());
+    let invalid = [
+        SimulatorConfig {
+            failure_probability: 1.5,
+            ..SimulatorConfig::default()
+        },
+        SimulatorConfig {
+            recovery_probability: f64::NAN,
+            ..SimulatorConfig::default()
+        },
+        SimulatorConfig {
+            drift_step: -0.1,
+            ..SimulatorConfig::default()
+        },
+        SimulatorConfig {
+            load_fluctuation: f32::NAN,
+            ..SimulatorConfig::default()
+        },
+        SimulatorConfig {
+            daily_amplitude: f32::INFINITY,
+            ..SimulatorConfig::default()
+        },
+    ];
+    for config in invalid {
+        let err = Simulator::try_new(0, config).err().unwrap();
+        assert!(matches!(err, SmartHomeErrors::InvalidValue { .. }));
+        assert_eq!(err.code(), "invalid_value");
+    }
+}
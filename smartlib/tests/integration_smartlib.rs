@@ -1,5 +1,6 @@
 use smartlib::structures::Report;
-use smartlib::{Room, SmartDevice, SmartHome};
+use smartlib::{Device, Room, SmartDevice, SmartHome};
+use std::fmt;
 
 #[test]
 fn reports_include_room_and_device_lines() {
@@ -13,13 +14,16 @@ fn reports_include_room_and_device_lines() {
     );
     let socket = smartlib::smart_devices::SmartElectricalSoket::new(String::from("Sock1"), 150.0);
 
-    room.add_device_with_key(String::from("Termo1"), thermo.into());
-    room.add_device_with_key(String::from("Sock1"), SmartDevice::ElectricalSocket(socket));
+    room.add_device_with_key(String::from("Termo1"), thermo.into())
+        .unwrap();
+    room.add_device_with_key(String::from("Sock1"), SmartDevice::from(socket))
+        .unwrap();
 
-    let mut home = SmartHome::new(String::from("MyHome"), vec![room]);
+    let mut home = SmartHome::try_new(String::from("MyHome"), vec![room]).unwrap();
 
     // also ensure add/get work in public API from integration
-    home.add_room_with_key(String::from("Кухня"), Room::new(String::from("Кухня")));
+    home.add_room_with_key(String::from("Кухня"), Room::new(String::from("Кухня")))
+        .unwrap();
 
     let report = home.report();
 
@@ -30,8 +34,64 @@ fn reports_include_room_and_device_lines() {
 
 #[test]
 fn errors_are_displayed_meaningfully() {
-    let home = SmartHome::new(String::from("H"), vec![]);
+    let home = SmartHome::try_new(String::from("H"), vec![]).unwrap();
     let err = home.get_device_from_room("Nope", "X").unwrap_err();
     let msg = err.to_string();
     assert!(msg.contains("Room Nope not found"));
 }
+
+/// Устройство, объявленное вне библиотеки
+#[derive(Debug, Clone)]
+struct Lamp {
+    name: String,
+    brightness: u8,
+}
+
+impl fmt::Display for Lamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Лампа '{}': яркость {}%", self.name, self.brightness)
+    }
+}
+
+impl Report for Lamp {
+    fn report(&self) -> String {
+        format!("| -- {}", self)
+    }
+}
+
+impl Device for Lamp {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> &str {
+        "lamp"
+    }
+
+    fn status(&self) -> String {
+        format!("{}%", self.brightness)
+    }
+}
+
+#[test]
+fn custom_devices_can_be_added_to_room() {
+    let lamp = Lamp {
+        name: String::from("Торшер"),
+        brightness: 40,
+    };
+    let mut room = smartlib::add_room!(
+        String::from("Спальня"),
+        ("Lamp", Box::new(lamp) as SmartDevice)
+    );
+
+    let device = room.get_device("Lamp").unwrap();
+    assert_eq!(device.kind(), "lamp");
+    assert_eq!(device.status(), "40%");
+    assert!(room.report().contains("Лампа 'Торшер'"));
+
+    room.get_mutable_device("Lamp")
+        .and_then(|device| device.downcast_mut::<Lamp>())
+        .unwrap()
+        .brightness = 100;
+    assert_eq!(room.get_device("Lamp").unwrap().status(), "100%");
+}
@@ -0,0 +1,106 @@
+use smartlib::errors::{ConnectionErrors, SmartHomeErrors};
+use smartlib::protocol::{ErrorCode, Response, read_frame, write_frame};
+use smartlib::smart_devices::SmartElectricalSoket;
+use smartlib::tcp::{SocketClient, SocketServer};
+use std::error::Error;
+use std::net::TcpStream;
+
+fn start_server() -> (std::net::SocketAddr, SocketServer) {
+    let socket = SmartElectricalSoket::new(String::from("RemoteSocket"), 1500.0);
+    let server = SocketServer::bind("127.0.0.1:0", socket).unwrap();
+    (server.local_addr().unwrap(), server)
+}
+
+#[test]
+fn client_controls_remote_socket() {
+    let (addr, server) = start_server();
+    let shared = server.socket();
+    server.spawn();
+
+    let mut client = SocketClient::connect(addr).unwrap();
+    assert_eq!(client.get_name().unwrap(), "RemoteSocket");
+    assert!(!client.is_on().unwrap());
+    assert_eq!(client.get_power().unwrap(), 0.0);
+
+    client.turn_on().unwrap();
+    assert!(client.is_on().unwrap());
+    assert_eq!(client.get_power().unwrap(), 1500.0);
+    assert!(shared.lock().unwrap().is_on());
+
+    client.switch().unwrap();
+    assert!(!client.is_on().unwrap());
+
+    client.turn_on().unwrap();
+    client.turn_off().unwrap();
+    assert!(!shared.lock().unwrap().is_on());
+}
+
+#[test]
+fn several_clients_share_one_socket() {
+    let (addr, server) = start_server();
+    server.spawn();
+
+    let mut first = SocketClient::connect(addr).unwrap();
+    let mut second = SocketClient::connect(addr).unwrap();
+    first.turn_on().unwrap();
+    assert!(second.is_on().unwrap());
+}
+
+#[test]
+fn offline_socket_is_reported_to_client() {
+    let (addr, server) = start_server();
+    let shared = server.socket();
+    server.spawn();
+    shared.lock().unwrap().set_offline(true);
+
+    let mut client = SocketClient::connect(addr).unwrap();
+    let err = client.turn_on().unwrap_err();
+    assert!(matches!(
+        err,
+        SmartHomeErrors::DeviceOffline {
+            source: Some(ConnectionErrors::Remote(ErrorCode::DeviceOffline)),
+            ..
+        }
+    ));
+    assert!(!client.is_on().unwrap());
+}
+
+#[test]
+fn server_reports_protocol_errors() {
+    let (addr, server) = start_server();
+    server.spawn();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write_frame(&mut stream, &[99]).unwrap();
+    let (_, payload) = read_frame(&mut stream).unwrap();
+    assert_eq!(
+        Response::decode(&payload).unwrap(),
+        Response::Error(ErrorCode::UnknownCommand)
+    );
+
+    // Кадр с неизвестной версией протокола
+    use std::io::Write;
+    stream.write_all(&[42, 0, 0, 0, 1, 1]).unwrap();
+    let (_, payload) = read_frame(&mut stream).unwrap();
+    assert_eq!(
+        Response::decode(&payload).unwrap(),
+        Response::Error(ErrorCode::UnsupportedVersion)
+    );
+}
+
+#[test]
+fn connect_to_closed_port_fails() {
+    let (addr, server) = start_server();
+    drop(server);
+    let err = SocketClient::connect(addr).err().unwrap();
+    assert!(matches!(
+        err,
+        SmartHomeErrors::DeviceOffline {
+            source: Some(ConnectionErrors::Io(_)),
+            ..
+        }
+    ));
+    assert_eq!(err.code(), "device_offline");
+    assert!(err.to_string().contains(&addr.to_string()));
+    assert!(err.source().unwrap().is::<ConnectionErrors>());
+}
@@ -0,0 +1,125 @@
+use smartlib::errors::{ConnectionErrors, SmartHomeErrors};
+use smartlib::smart_devices::{SmartThermometer, TempMeasures};
+use smartlib::structures::Device;
+use smartlib::udp::{TelemetryPacket, ThermometerEmitter};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Ожидает выполнения условия, не дольше двух секунд
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn thermometer_receives_emitted_readings() {
+    let mut receiver = SmartThermometer::listen(
+        String::from("Remote"),
+        TempMeasures::C,
+        "127.0.0.1:0",
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    assert!(receiver.is_stale());
+
+    let source = SmartThermometer::new(String::from("Sensor"), TempMeasures::C, 23.0);
+    let emitter = ThermometerEmitter::spawn(
+        source,
+        receiver.telemetry_addr().unwrap(),
+        Duration::from_millis(10),
+    )
+    .unwrap();
+
+    assert!(wait_for(|| receiver.get_tempreture() == 23.0));
+    assert!(!receiver.is_stale());
+    assert_eq!(receiver.current_tempreture().unwrap(), 23.0);
+
+    // Показания в Цельсиях переводятся в единицы получателя
+    receiver.change_measure();
+    assert!((receiver.get_tempreture() - 73.4).abs() < 0.001);
+
+    emitter.thermometer().lock().unwrap().set_tempreture(25.0);
+    assert!(wait_for(|| (receiver.get_tempreture() - 77.0).abs() < 0.001));
+    emitter.stop();
+
+    let stats = receiver.telemetry_stats().unwrap();
+    assert!(stats.received > 0);
+}
+
+#[test]
+fn readings_resume_after_emitter_restart() {
+    let receiver = SmartThermometer::listen(
+        String::from("Remote"),
+        TempMeasures::C,
+        "127.0.0.1:0",
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    let target = receiver.telemetry_addr().unwrap();
+
+    let source = SmartThermometer::new(String::from("Sensor"), TempMeasures::C, 23.0);
+    let emitter = ThermometerEmitter::spawn(source, target, Duration::from_millis(10)).unwrap();
+    assert!(wait_for(
+        || receiver.telemetry_stats().unwrap().received >= 5
+    ));
+    emitter.stop();
+
+    // Перезапущенный эмиттер снова начинает номера с нуля
+    let source = SmartThermometer::new(String::from("Sensor"), TempMeasures::C, 30.0);
+    let emitter = ThermometerEmitter::spawn(source, target, Duration::from_millis(10)).unwrap();
+    assert!(wait_for(|| receiver.get_tempreture() == 30.0));
+    emitter.stop();
+    assert_eq!(receiver.telemetry_stats().unwrap().restarts, 1);
+}
+
+#[test]
+fn receiver_detects_loss_and_staleness() {
+    let receiver = SmartThermometer::listen(
+        String::from("Remote"),
+        TempMeasures::C,
+        "127.0.0.1:0",
+        Duration::from_millis(100),
+    )
+    .unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = receiver.telemetry_addr().unwrap();
+
+    for sequence in [0, 1, 5] {
+        let packet = TelemetryPacket {
+            session: 1,
+            sequence,
+            tempreture: 30.0,
+            measure: TempMeasures::C,
+        };
+        sender.send_to(&packet.encode(), target).unwrap();
+    }
+
+    assert!(wait_for(
+        || receiver.telemetry_stats().unwrap().received == 3
+    ));
+    let stats = receiver.telemetry_stats().unwrap();
+    assert_eq!(stats.lost, 3);
+    assert_eq!(stats.last_sequence, Some(5));
+
+    assert!(wait_for(|| receiver.is_stale()));
+    assert!(receiver.status().contains("устарело"));
+    let err = receiver.current_tempreture().unwrap_err();
+    assert!(matches!(
+        err,
+        SmartHomeErrors::DeviceOffline {
+            ref device,
+            source: Some(ConnectionErrors::Timeout),
+        } if device == "Remote"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Device Remote is offline: Operation timed out"
+    );
+}